@@ -74,6 +74,7 @@ pub enum Role {
 pub enum DeniedReason {
     PasswordExpired,
     AccountLocked{reason: String},
+    TooManyAttempts,
 }
 
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,84 @@
+use auth_json::*;
+use crate::{protocol::*, server::Shared};
+
+/// Does this token belong to a live admin session?
+pub fn is_admin(shared: &Shared, token: &str) -> bool {
+    matches!(shared.sessions.validate(token), Some(Session { role: Role::Admin, .. }))
+}
+
+/// Run an admin command on behalf of the session identified by `token`.
+pub fn handle(shared: &Shared, token: &str, command: AdminCommand) -> AdminResponse {
+    if !is_admin(shared, token) {
+        return AdminResponse::NotAuthorized;
+    }
+
+    match command {
+        AdminCommand::ListUsers => {
+            let users = shared.users
+                .read()
+                .values()
+                .map(UserInfo::from)
+                .collect();
+            AdminResponse::Users(users)
+        }
+        AdminCommand::AddUser { username, password, action } => {
+            let username = normalize(&username);
+            let mut users = shared.users.write();
+            if users.contains_key(&username) {
+                return AdminResponse::UserExists;
+            }
+            let user = User::new(&username, password.trim(), action);
+            let info = UserInfo::from(&user);
+            users.insert(username, user);
+            drop(users);
+            changed(shared, Event::UserChanged(info))
+        }
+        AdminCommand::DeleteUser(username) => {
+            let username = normalize(&username);
+            if shared.users.write().remove(&username).is_none() {
+                return AdminResponse::UnknownUser;
+            }
+            shared.sessions.end_all(&username);
+            changed(shared, Event::UserDeleted(username))
+        }
+        AdminCommand::SetAction { username, action } => {
+            let username = normalize(&username);
+            let denied = matches!(action, LoginAction::Denied(_));
+            let info = match shared.users.write().get_mut(&username) {
+                Some(user) => {
+                    user.action = action;
+                    UserInfo::from(&*user)
+                }
+                None => return AdminResponse::UnknownUser,
+            };
+            if denied {
+                shared.sessions.end_all(&username);
+            }
+            changed(shared, Event::UserChanged(info))
+        }
+        AdminCommand::SetPassword { username, password } => {
+            let username = normalize(&username);
+            let info = match shared.users.write().get_mut(&username) {
+                Some(user) => {
+                    user.password = password.trim().to_string();
+                    UserInfo::from(&*user)
+                }
+                None => return AdminResponse::UnknownUser,
+            };
+            changed(shared, Event::UserChanged(info))
+        }
+    }
+}
+
+/// Usernames are stored trimmed and lowercase, as `auth_json::login` looks them up.
+/// Passwords are stored trimmed, as it compares them.
+fn normalize(username: &str) -> String {
+    username.trim().to_lowercase()
+}
+
+// Handlers only touch the in-memory map; the writer task takes care of the file.
+fn changed(shared: &Shared, event: Event) -> AdminResponse {
+    shared.users.request_save();
+    shared.events.publish(event);
+    AdminResponse::Done
+}
@@ -0,0 +1,88 @@
+use std::time::Duration;
+use tokio::{net::{TcpStream, ToSocketAddrs}, time::{Instant, Interval, interval_at, timeout}};
+use crate::{framing::{Format, FrameReader, write_frame}, protocol::*};
+
+/// A connection to the login server that has completed the hello exchange.
+pub struct Client {
+    stream: TcpStream,
+    reader: FrameReader,
+    format: Format,
+    heartbeats: bool,
+    heartbeat: Interval,
+    last_heard: Instant,
+}
+
+impl Client {
+    /// Connect to the server at `address` and agree on a protocol version.
+    pub async fn connect(address: impl ToSocketAddrs, format: Format) -> anyhow::Result<Self> {
+        let mut stream = TcpStream::connect(address).await?;
+        let mut reader = FrameReader::new();
+        write_frame(&mut stream, format, &Hello::new()).await?;
+        let features = match reader.read_message(&mut stream).await? {
+            Some(HelloResponse::Accepted { features, .. }) => features,
+            Some(HelloResponse::UnsupportedVersion { supported }) => return Err(anyhow::Error::msg(format!(
+                "The server speaks protocol version {supported}, but this client speaks version {PROTOCOL_VERSION}"
+            ))),
+            None => return Err(anyhow::Error::msg("The server closed the connection")),
+        };
+        Ok(Self {
+            stream,
+            reader,
+            format,
+            heartbeats: features & FEATURE_HEARTBEAT != 0,
+            heartbeat: interval_at(Instant::now() + HEARTBEAT_INTERVAL, HEARTBEAT_INTERVAL),
+            last_heard: Instant::now(),
+        })
+    }
+
+    /// Wait for the next message from the server, keeping up our side of the
+    /// heartbeat while we wait and skipping the server's heartbeats.
+    pub async fn receive(&mut self) -> anyhow::Result<Response> {
+        loop {
+            let frame = tokio::select! {
+                read = self.reader.read_frame(&mut self.stream) => read?,
+                _ = self.heartbeat.tick(), if self.heartbeats => {
+                    if self.last_heard.elapsed() > HEARTBEAT_INTERVAL * MISSED_HEARTBEAT_LIMIT {
+                        return Err(anyhow::Error::msg("The server stopped sending heartbeats"));
+                    }
+                    write_frame(&mut self.stream, self.format, &Request::Heartbeat).await?;
+                    continue;
+                }
+            };
+            let frame = frame.ok_or_else(|| anyhow::Error::msg("The server closed the connection"))?;
+            self.last_heard = Instant::now();
+            match frame.decode()? {
+                Response::Heartbeat => continue,
+                response => return Ok(response),
+            }
+        }
+    }
+
+    /// Whether the connection still looks alive, without waiting for the
+    /// server. Reads whatever arrived while the connection sat idle, and fails
+    /// if the server has hung up or its heartbeats stopped. Connections that
+    /// sit idle never read, so this is how they notice a dead server.
+    pub async fn check(&mut self) -> anyhow::Result<()> {
+        // A zero timeout still polls the read once, so it takes whatever has
+        // already arrived; read_frame keeps partial frames for next time
+        while let Ok(frame) = timeout(Duration::ZERO, self.reader.read_frame(&mut self.stream)).await {
+            let frame = frame?.ok_or_else(|| anyhow::Error::msg("The server closed the connection"))?;
+            self.last_heard = Instant::now();
+            if !matches!(frame.decode()?, Response::Heartbeat) {
+                return Err(anyhow::Error::msg("The server sent a message nobody asked for"));
+            }
+        }
+        if self.heartbeats && self.last_heard.elapsed() > HEARTBEAT_INTERVAL * MISSED_HEARTBEAT_LIMIT {
+            return Err(anyhow::Error::msg("The server stopped sending heartbeats"));
+        }
+        Ok(())
+    }
+
+    /// Send a request and wait for its response. Fails straight away if the
+    /// connection died while it was idle.
+    pub async fn call(&mut self, request: &Request) -> anyhow::Result<Response> {
+        self.check().await?;
+        write_frame(&mut self.stream, self.format, request).await?;
+        self.receive().await
+    }
+}
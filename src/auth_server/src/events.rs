@@ -0,0 +1,25 @@
+use tokio::sync::broadcast;
+use crate::protocol::Event;
+
+/// How many events a subscriber may fall behind before it is disconnected.
+const EVENT_BACKLOG: usize = 128;
+
+/// Fans user changes out to every subscribed connection.
+pub struct Events {
+    sender: broadcast::Sender<Event>,
+}
+
+impl Events {
+    pub fn new() -> Self {
+        Self { sender: broadcast::channel(EVENT_BACKLOG).0 }
+    }
+
+    /// Tell every subscriber about a change. It's fine if nobody is listening.
+    pub fn publish(&self, event: Event) {
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+}
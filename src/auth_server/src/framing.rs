@@ -0,0 +1,261 @@
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Every message on the wire is prefixed with its length as a big-endian u32.
+/// The length counts the format byte and the payload that follow it.
+const HEADER_SIZE: usize = 4;
+
+/// Refuse frames larger than this, rather than buffering whatever a client claims to send.
+pub const MAX_FRAME_SIZE: usize = 64 * 1024;
+
+/// How a frame's payload is encoded, sent as a single byte in front of it.
+/// Bincode is compact and fast; JSON can be read by a human with netcat;
+/// postcard uses variable-length integers, so it's smaller still.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Format {
+    Bincode,
+    Json,
+    Postcard,
+}
+
+impl Format {
+    fn to_byte(self) -> u8 {
+        match self {
+            Format::Bincode => b'B',
+            Format::Json => b'J',
+            Format::Postcard => b'P',
+        }
+    }
+
+    fn from_byte(byte: u8) -> anyhow::Result<Self> {
+        match byte {
+            b'B' => Ok(Format::Bincode),
+            b'J' => Ok(Format::Json),
+            b'P' => Ok(Format::Postcard),
+            _ => Err(anyhow::Error::msg(format!("unknown content format {byte:#04x}"))),
+        }
+    }
+
+    pub fn serialize<T: Serialize>(self, message: &T) -> anyhow::Result<Vec<u8>> {
+        Ok(match self {
+            Format::Bincode => bincode::serialize(message)?,
+            Format::Json => serde_json::to_vec(message)?,
+            Format::Postcard => postcard::to_allocvec(message)?,
+        })
+    }
+
+    pub fn deserialize<T: DeserializeOwned>(self, payload: &[u8]) -> anyhow::Result<T> {
+        Ok(match self {
+            Format::Bincode => bincode::deserialize(payload)?,
+            Format::Json => serde_json::from_slice(payload)?,
+            Format::Postcard => postcard::from_bytes(payload)?,
+        })
+    }
+}
+
+impl std::str::FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "bincode" => Ok(Format::Bincode),
+            "json" => Ok(Format::Json),
+            "postcard" => Ok(Format::Postcard),
+            _ => Err(anyhow::Error::msg(format!("unknown format {s:?}, expected bincode, json or postcard"))),
+        }
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Format::Bincode => write!(f, "bincode"),
+            Format::Json => write!(f, "json"),
+            Format::Postcard => write!(f, "postcard"),
+        }
+    }
+}
+
+/// A message as it came off the wire.
+pub struct Frame {
+    pub format: Format,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn decode<T: DeserializeOwned>(&self) -> anyhow::Result<T> {
+        self.format.deserialize(&self.payload)
+    }
+}
+
+/// Encode a message as a UDP datagram. Datagrams arrive whole or not at all,
+/// so they carry the format byte but no length.
+pub fn encode_datagram<T: Serialize>(format: Format, message: &T) -> anyhow::Result<Vec<u8>> {
+    let mut datagram = vec![format.to_byte()];
+    datagram.extend_from_slice(&format.serialize(message)?);
+    Ok(datagram)
+}
+
+/// Split a received datagram into its format and payload.
+pub fn decode_datagram(datagram: &[u8]) -> anyhow::Result<Frame> {
+    match datagram.split_first() {
+        Some((format, payload)) => Ok(Frame { format: Format::from_byte(*format)?, payload: payload.to_vec() }),
+        None => Err(anyhow::Error::msg("empty datagram")),
+    }
+}
+
+/// Serialize a message and send it as a single frame.
+pub async fn write_frame<T: Serialize>(stream: &mut (impl AsyncWrite + Unpin), format: Format, message: &T) -> anyhow::Result<()> {
+    let payload = format.serialize(message)?;
+    let mut frame = Vec::with_capacity(HEADER_SIZE + 1 + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32 + 1).to_be_bytes());
+    frame.push(format.to_byte());
+    frame.extend_from_slice(&payload);
+    stream.write_all(&frame).await?;
+    Ok(())
+}
+
+/// Accumulates everything read from a connection and hands out complete frames.
+/// A frame split across several reads, or several frames arriving in one read,
+/// both come out as whole messages.
+pub struct FrameReader {
+    buffer: Vec<u8>,
+}
+
+impl Default for FrameReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameReader {
+    pub fn new() -> Self {
+        Self { buffer: Vec::with_capacity(1024) }
+    }
+
+    /// Remove the next complete frame from the buffer, if one has fully arrived.
+    fn next_frame(&mut self) -> anyhow::Result<Option<Frame>> {
+        if self.buffer.len() < HEADER_SIZE {
+            return Ok(None);
+        }
+        let mut header = [0; HEADER_SIZE];
+        header.copy_from_slice(&self.buffer[..HEADER_SIZE]);
+        let size = u32::from_be_bytes(header) as usize;
+        if size == 0 || size > MAX_FRAME_SIZE {
+            return Err(anyhow::Error::msg(format!("invalid frame size of {size} bytes")));
+        }
+        if self.buffer.len() < HEADER_SIZE + size {
+            return Ok(None);
+        }
+        let format = Format::from_byte(self.buffer[HEADER_SIZE])?;
+        let payload = self.buffer[HEADER_SIZE + 1..HEADER_SIZE + size].to_vec();
+        self.buffer.drain(..HEADER_SIZE + size);
+        Ok(Some(Frame { format, payload }))
+    }
+
+    /// Read from the stream until a whole frame is available. Returns `None` if
+    /// the peer closed the connection cleanly between frames.
+    pub async fn read_frame(&mut self, stream: &mut (impl AsyncRead + Unpin)) -> anyhow::Result<Option<Frame>> {
+        let mut chunk = [0; 1024];
+        loop {
+            if let Some(frame) = self.next_frame()? {
+                return Ok(Some(frame));
+            }
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 {
+                return if self.buffer.is_empty() {
+                    Ok(None)
+                } else {
+                    Err(anyhow::Error::msg("connection closed part-way through a message"))
+                };
+            }
+            self.buffer.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    /// Read and deserialize the next message. Returns `None` if the peer has gone.
+    pub async fn read_message<T: DeserializeOwned>(&mut self, stream: &mut (impl AsyncRead + Unpin)) -> anyhow::Result<Option<T>> {
+        match self.read_frame(stream).await? {
+            Some(frame) => Ok(Some(frame.decode()?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn frame(payload: &[u8]) -> Vec<u8> {
+        let mut result = (payload.len() as u32 + 1).to_be_bytes().to_vec();
+        result.push(b'B');
+        result.extend_from_slice(payload);
+        result
+    }
+
+    fn payload(frame: Option<Frame>) -> Option<Vec<u8>> {
+        frame.map(|f| f.payload)
+    }
+
+    #[test]
+    fn test_partial_frame_waits_for_the_rest() {
+        let whole = frame(b"hello");
+        let mut reader = FrameReader::new();
+        reader.buffer.extend_from_slice(&whole[..3]);
+        assert!(reader.next_frame().unwrap().is_none());
+        reader.buffer.extend_from_slice(&whole[3..7]);
+        assert!(reader.next_frame().unwrap().is_none());
+        reader.buffer.extend_from_slice(&whole[7..]);
+        assert_eq!(payload(reader.next_frame().unwrap()), Some(b"hello".to_vec()));
+        assert!(reader.buffer.is_empty());
+    }
+
+    #[test]
+    fn test_pipelined_frames_are_split() {
+        let mut reader = FrameReader::new();
+        reader.buffer.extend_from_slice(&frame(b"one"));
+        reader.buffer.extend_from_slice(&frame(b"two"));
+        let three = frame(b"three");
+        reader.buffer.extend_from_slice(&three[..6]);
+        assert_eq!(payload(reader.next_frame().unwrap()), Some(b"one".to_vec()));
+        assert_eq!(payload(reader.next_frame().unwrap()), Some(b"two".to_vec()));
+        assert!(reader.next_frame().unwrap().is_none());
+        reader.buffer.extend_from_slice(&three[6..]);
+        assert_eq!(payload(reader.next_frame().unwrap()), Some(b"three".to_vec()));
+    }
+
+    #[test]
+    fn test_oversized_frame_is_rejected() {
+        let mut reader = FrameReader::new();
+        reader.buffer.extend_from_slice(&(MAX_FRAME_SIZE as u32 + 1).to_be_bytes());
+        assert!(reader.next_frame().is_err());
+    }
+
+    #[test]
+    fn test_unknown_format_is_rejected() {
+        let mut reader = FrameReader::new();
+        reader.buffer.extend_from_slice(&[0, 0, 0, 2, b'X', 0]);
+        assert!(reader.next_frame().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_through_a_stream() {
+        // A tiny pipe forces messages to arrive in several pieces
+        let (mut client, mut server) = tokio::io::duplex(8);
+        tokio::spawn(async move {
+            write_frame(&mut client, Format::Bincode, &"first".to_string()).await.unwrap();
+            write_frame(&mut client, Format::Json, &"second".to_string()).await.unwrap();
+            write_frame(&mut client, Format::Postcard, &"third".to_string()).await.unwrap();
+        });
+
+        let mut reader = FrameReader::new();
+        let first: Option<String> = reader.read_message(&mut server).await.unwrap();
+        let second: Option<String> = reader.read_message(&mut server).await.unwrap();
+        let third: Option<String> = reader.read_message(&mut server).await.unwrap();
+        let end: Option<String> = reader.read_message(&mut server).await.unwrap();
+        assert_eq!(first.as_deref(), Some("first"));
+        assert_eq!(second.as_deref(), Some("second"));
+        assert_eq!(third.as_deref(), Some("third"));
+        assert_eq!(end, None);
+    }
+}
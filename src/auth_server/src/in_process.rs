@@ -0,0 +1,84 @@
+use std::{collections::HashMap, net::{IpAddr, Ipv4Addr, SocketAddr}};
+use tokio::sync::broadcast;
+use auth_json::User;
+use crate::{admin, protocol::{Event, Request, Response}, server::{handle_request, Shared}, users::UserStore};
+
+/// Where every request appears to come from, as it would for a front end
+/// sharing one connection to a real server.
+const ADDRESS: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+
+/// A server without a socket, answering requests in the calling process.
+/// Users are held in memory, so changes only last as long as it does.
+pub struct InProcess {
+    shared: Shared,
+}
+
+impl InProcess {
+    pub fn new(users: HashMap<String, User>) -> Self {
+        Self { shared: Shared::new(UserStore::in_memory(users)) }
+    }
+
+    /// Answer a request just as a server would over TCP.
+    pub async fn call(&self, request: Request) -> Response {
+        handle_request(&self.shared, request, ADDRESS).await
+    }
+
+    /// Follow logins and user changes. Only admins may.
+    pub fn subscribe(&self, token: &str) -> Option<broadcast::Receiver<Event>> {
+        admin::is_admin(&self.shared, token).then(|| self.shared.events.subscribe())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use auth_json::{LoginAction, Role};
+    use crate::protocol::{AdminCommand, AdminResponse, PasswordChange};
+
+    fn server() -> InProcess {
+        let users = [
+            User::new("herbert", "password", LoginAction::Accept(Role::Admin)),
+            User::new("bob", "password", LoginAction::Accept(Role::User)),
+        ];
+        InProcess::new(users.into_iter().map(|user| (user.username.clone(), user)).collect())
+    }
+
+    async fn login(server: &InProcess, username: &str) -> String {
+        login_with(server, username, "password").await
+    }
+
+    async fn login_with(server: &InProcess, username: &str, password: &str) -> String {
+        let request = Request::Login { username: username.to_string(), password: password.to_string() };
+        match server.call(request).await {
+            Response::Login { token: Some(token), .. } => token,
+            other => panic!("{username} couldn't log in: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn deleting_a_user_ends_their_sessions() {
+        let server = server();
+        let admin = login(&server, "herbert").await;
+        let bob = login(&server, "bob").await;
+        let mut events = server.subscribe(&admin).expect("herbert is an admin");
+        assert!(server.subscribe(&bob).is_none());
+
+        let delete = Request::Admin { token: admin, command: AdminCommand::DeleteUser(" Bob ".to_string()) };
+        assert!(matches!(server.call(delete).await, Response::Admin(AdminResponse::Done)));
+        assert!(matches!(server.call(Request::ValidateToken(bob)).await, Response::Session(None)));
+        assert!(matches!(events.recv().await, Ok(Event::UserDeleted(name)) if name == "bob"));
+    }
+
+    #[tokio::test]
+    async fn passwords_are_stored_trimmed() {
+        let server = server();
+        let admin = login(&server, "herbert").await;
+        let set = AdminCommand::SetPassword { username: "bob".to_string(), password: " hunter22 ".to_string() };
+        server.call(Request::Admin { token: admin, command: set }).await;
+        let bob = login_with(&server, "bob", "hunter22").await;
+
+        let change = Request::ChangePassword { token: bob, current_password: "hunter22".to_string(), new_password: "\tswordfish9\n".to_string() };
+        assert!(matches!(server.call(change).await, Response::PasswordChange(PasswordChange::Done)));
+        login_with(&server, "bob", "swordfish9").await;
+    }
+}
@@ -0,0 +1,78 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use auth_json::Role;
+
+/// Who is logged in to a web frontend, and the auth server session backing it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Identity {
+    pub username: String,
+    pub role: Role,
+    /// The auth server's session token, for checking the session is still live
+    pub token: String,
+}
+
+/// How bearer tokens are signed. Rocket reads it from Rocket.toml, hence the names.
+#[derive(Deserialize)]
+pub struct JwtConfig {
+    #[serde(rename = "jwt_secret")]
+    pub secret: String,
+    #[serde(rename = "jwt_lifetime_secs")]
+    pub lifetime_secs: u64,
+}
+
+/// What a bearer token says about its holder. Every frontend uses the same
+/// claims, so a token from one works with another if they share a secret.
+#[derive(Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    role: Role,
+    /// The auth server's session token
+    session: String,
+    /// Seconds since the Unix epoch
+    exp: u64,
+}
+
+impl JwtConfig {
+    /// A signed token for `identity`, so it can prove who it is without a cookie.
+    pub fn issue(&self, identity: &Identity) -> String {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let claims = Claims {
+            sub: identity.username.clone(),
+            role: identity.role.clone(),
+            session: identity.token.clone(),
+            exp: now + self.lifetime_secs,
+        };
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(self.secret.as_bytes())).unwrap()
+    }
+
+    /// The identity in a token, if we signed it and it hasn't expired.
+    pub fn verify(&self, token: &str) -> Option<Identity> {
+        let claims = decode::<Claims>(token, &DecodingKey::from_secret(self.secret.as_bytes()), &Validation::default())
+            .ok()?
+            .claims;
+        Some(Identity { username: claims.sub, role: claims.role, token: claims.session })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config(secret: &str) -> JwtConfig {
+        JwtConfig { secret: secret.to_string(), lifetime_secs: 60 }
+    }
+
+    #[test]
+    fn tokens_round_trip() {
+        let identity = Identity { username: "bob".to_string(), role: Role::User, token: "session".to_string() };
+        let verified = config("secret").verify(&config("secret").issue(&identity)).unwrap();
+        assert_eq!((verified.username.as_str(), verified.token.as_str()), ("bob", "session"));
+    }
+
+    #[test]
+    fn other_secrets_are_refused() {
+        let identity = Identity { username: "bob".to_string(), role: Role::User, token: "session".to_string() };
+        assert!(config("other").verify(&config("secret").issue(&identity)).is_none());
+    }
+}
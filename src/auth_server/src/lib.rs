@@ -0,0 +1,66 @@
+//! The login server as a library, so it can be embedded in other programs and
+//! tests as well as run from the `tcp_login_server` command line:
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! auth_server::Server::builder()
+//!     .bind("127.0.0.1:8123")
+//!     .store("users.json")
+//!     .serve()
+//!     .await
+//! # }
+//! ```
+
+// Request and response types shared by the client and server
+pub mod protocol;
+
+// Length-prefixed messages, so reads always yield whole requests
+pub mod framing;
+
+// Session tokens handed out on successful login
+mod sessions;
+
+// The user database, and reloading it when it changes on disk
+mod users;
+
+// User management requests
+mod admin;
+
+// Slows down addresses that keep failing to log in
+mod throttle;
+
+// Accepts connections and answers requests
+mod server;
+pub use server::{Server, ServerBuilder, DEFAULT_ADDRESS, DEFAULT_STORE};
+
+// The server without a socket, for front ends that run on their own
+mod in_process;
+pub use in_process::InProcess;
+
+// Talking to the server over TCP
+mod client;
+pub use client::Client;
+
+// A pool of client connections, for web frontends
+pub mod pool;
+
+// Bearer tokens handed out by web frontends
+pub mod jwt;
+
+// The admin account web frontends act through
+pub mod service;
+
+// What makes a password acceptable
+pub mod policy;
+
+// Connectionless login and ping over UDP
+pub mod udp;
+
+// Notifies subscribers when users change
+mod events;
+
+// Counters describing what the server has been up to
+mod stats;
+
+// Password checks, kept off the async reactor
+mod verify;
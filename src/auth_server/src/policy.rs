@@ -0,0 +1,45 @@
+/// Everything wrong with a proposed password. Empty if it's acceptable.
+/// Surrounding whitespace is ignored, as it is when logging in.
+pub fn password_problems(username: &str, password: &str) -> Vec<&'static str> {
+    let password = password.trim();
+    let mut problems = Vec::new();
+    if password.chars().count() < 8 {
+        problems.push("Passwords must be at least 8 characters long");
+    }
+    if !password.chars().any(|c| c.is_alphabetic()) {
+        problems.push("Passwords must contain a letter");
+    }
+    if !password.chars().any(|c| c.is_ascii_digit()) {
+        problems.push("Passwords must contain a digit");
+    }
+    if password.eq_ignore_ascii_case(username.trim()) {
+        problems.push("Passwords can't be the same as the username");
+    }
+    problems
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn good_password_passes() {
+        assert!(password_problems("bob", "correct horse 9").is_empty());
+    }
+
+    #[test]
+    fn short_password_fails() {
+        assert_eq!(password_problems("bob", "abc1"), vec!["Passwords must be at least 8 characters long"]);
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        assert_eq!(password_problems("bob", "").len(), 3);
+        assert_eq!(password_problems("password", "PASSWORD").len(), 2);
+    }
+
+    #[test]
+    fn padding_doesnt_count() {
+        assert_eq!(password_problems("bob", "  abc1   "), vec!["Passwords must be at least 8 characters long"]);
+    }
+}
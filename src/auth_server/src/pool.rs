@@ -0,0 +1,292 @@
+use std::{net::ToSocketAddrs, sync::Mutex, time::Duration};
+use serde::Serialize;
+use tokio::{sync::{Semaphore, SemaphorePermit}, time::{sleep, timeout, Instant}};
+use crate::{Client, framing::Format, protocol::{Event, Request, Response}};
+
+/// Connections idle for longer than this are pinged before being handed out,
+/// in case the server has hung up on them. Every idle connection is checked
+/// for a hang-up or missed heartbeats first, which doesn't need a round trip.
+const HEALTH_CHECK_AFTER: Duration = Duration::from_secs(5);
+
+struct Idle {
+    client: Client,
+    since: Instant,
+}
+
+/// Where the server is and how patient to be with it.
+#[derive(Clone, Debug)]
+pub struct PoolConfig {
+    pub address: String,
+    pub size: usize,
+    /// How long to wait for a connection from the pool, including making one
+    pub checkout_timeout: Duration,
+    pub connect_timeout: Duration,
+    /// How long to wait for the answer to a request
+    pub read_timeout: Duration,
+    /// How many more times to try connecting after the first attempt fails
+    pub connect_retries: u32,
+    /// Stop trying to connect after this many failures in a row...
+    pub trip_after: u32,
+    /// ...until this much time has passed
+    pub cooldown: Duration,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            address: crate::DEFAULT_ADDRESS.to_string(),
+            size: 4,
+            checkout_timeout: Duration::from_secs(2),
+            connect_timeout: Duration::from_millis(500),
+            read_timeout: Duration::from_secs(1),
+            connect_retries: 2,
+            trip_after: 3,
+            cooldown: Duration::from_secs(10),
+        }
+    }
+}
+
+impl PoolConfig {
+    /// Catch settings that could never work, so programs can refuse to start
+    /// rather than fail on their first request.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.address.to_socket_addrs().map_or(true, |mut addrs| addrs.next().is_none()) {
+            return Err(anyhow::Error::msg(format!("{:?} isn't a reachable host:port", self.address)));
+        }
+        if self.size == 0 || self.trip_after == 0 {
+            return Err(anyhow::Error::msg("the pool size and trip_after must be at least 1"));
+        }
+        if [self.checkout_timeout, self.connect_timeout, self.read_timeout].contains(&Duration::ZERO) {
+            return Err(anyhow::Error::msg("timeouts must be longer than zero"));
+        }
+        Ok(())
+    }
+}
+
+/// Whether the pool is trying to reach the server.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CircuitState {
+    /// All's well; connect as usual.
+    Closed,
+    /// The server kept failing, so requests fail straight away for a while
+    /// instead of piling up waiting for it.
+    Open,
+    /// The cooldown is over; the next connection attempt decides whether to close or open again.
+    HalfOpen,
+}
+
+/// A circuit breaker around connection attempts.
+struct Breaker {
+    failures: u32,
+    opened: Option<Instant>,
+}
+
+impl Breaker {
+    fn state(&self, cooldown: Duration, now: Instant) -> CircuitState {
+        match self.opened {
+            None => CircuitState::Closed,
+            Some(opened) if now.duration_since(opened) < cooldown => CircuitState::Open,
+            Some(..) => CircuitState::HalfOpen,
+        }
+    }
+
+    fn succeeded(&mut self) {
+        self.failures = 0;
+        self.opened = None;
+    }
+
+    fn failed(&mut self, trip_after: u32, now: Instant) {
+        // Failures only reset on success, so a failed trial while half-open re-opens the circuit
+        self.failures += 1;
+        if self.failures >= trip_after {
+            self.opened = Some(now);
+        }
+    }
+}
+
+/// A small pool of connections to the server, kept open between requests.
+/// Web frontends keep one of these, and handlers check connections out of it.
+pub struct Pool {
+    config: PoolConfig,
+    idle: Mutex<Vec<Idle>>,
+    /// One permit per connection; checking out takes one
+    permits: Semaphore,
+    breaker: Mutex<Breaker>,
+}
+
+impl Pool {
+    /// Make an empty pool. Connections are made as they're needed.
+    pub fn new(config: PoolConfig) -> Self {
+        Self {
+            permits: Semaphore::new(config.size),
+            idle: Mutex::new(Vec::new()),
+            breaker: Mutex::new(Breaker { failures: 0, opened: None }),
+            config,
+        }
+    }
+
+    /// Check out a connection, waiting if they're all in use. Idle connections
+    /// are reused if they're still healthy; otherwise a new one is made.
+    pub async fn get(&self) -> anyhow::Result<Connection<'_>> {
+        timeout(self.config.checkout_timeout, self.checkout())
+            .await
+            .map_err(|_| anyhow::Error::msg("Timed out waiting for a connection to the server"))?
+    }
+
+    async fn checkout(&self) -> anyhow::Result<Connection<'_>> {
+        let permit = self.permits.acquire().await?;
+        loop {
+            // Don't hold the lock across an await
+            let idle = self.idle.lock().unwrap().pop();
+            let Some(Idle { mut client, since }) = idle else { break };
+            let alive = client.check().await.is_ok()
+                && (since.elapsed() < HEALTH_CHECK_AFTER || matches!(client.call(&Request::Ping).await, Ok(Response::Pong)));
+            if alive {
+                return Ok(Connection { pool: self, client: Some(client), broken: false, _permit: permit });
+            }
+            // It's dead; drop it and try the next
+        }
+        let client = self.connect().await?;
+        Ok(Connection { pool: self, client: Some(client), broken: false, _permit: permit })
+    }
+
+    /// Follow the server's events on a connection of its own, since a
+    /// subscribed connection can't be used for anything else. `None` if the
+    /// token doesn't belong to an admin.
+    pub async fn subscribe(&self, token: &str) -> anyhow::Result<Option<Subscription>> {
+        let mut client = self.connect().await?;
+        match client.call(&Request::Subscribe { token: token.to_string() }).await? {
+            Response::Subscribed => Ok(Some(Subscription { client })),
+            _ => Ok(None),
+        }
+    }
+
+    /// Whether the pool is currently willing to try the server.
+    pub fn circuit(&self) -> CircuitState {
+        self.breaker.lock().unwrap().state(self.config.cooldown, Instant::now())
+    }
+
+    /// Make a new connection, trying again a few times (waiting longer each
+    /// time) if the server doesn't answer. Fails straight away while the
+    /// circuit is open.
+    async fn connect(&self) -> anyhow::Result<Client> {
+        let retries = match self.circuit() {
+            CircuitState::Closed => self.config.connect_retries,
+            // One try decides it
+            CircuitState::HalfOpen => 0,
+            CircuitState::Open => return Err(anyhow::Error::msg("The server is down; not trying again yet")),
+        };
+        let result = self.try_connect(retries).await;
+        let mut breaker = self.breaker.lock().unwrap();
+        match &result {
+            Ok(..) => breaker.succeeded(),
+            Err(..) => breaker.failed(self.config.trip_after, Instant::now()),
+        }
+        result
+    }
+
+    async fn try_connect(&self, retries: u32) -> anyhow::Result<Client> {
+        let limit = self.config.connect_timeout;
+        let mut delay = Duration::from_millis(100);
+        let mut attempt = 0;
+        loop {
+            let error = match timeout(limit, Client::connect(self.config.address.as_str(), Format::Bincode)).await {
+                Ok(Ok(client)) => return Ok(client),
+                Ok(Err(e)) => e,
+                Err(_) => anyhow::Error::msg("Timed out connecting to the server"),
+            };
+            if attempt >= retries {
+                return Err(error);
+            }
+            attempt += 1;
+            sleep(delay).await;
+            delay *= 2;
+        }
+    }
+}
+
+/// A connection checked out of the pool. It goes back when dropped - unless a
+/// call failed, in which case who knows what state it's in, so it's closed.
+pub struct Connection<'a> {
+    pool: &'a Pool,
+    client: Option<Client>,
+    broken: bool,
+    _permit: SemaphorePermit<'a>,
+}
+
+impl Connection<'_> {
+    pub async fn call(&mut self, request: &Request) -> anyhow::Result<Response> {
+        let limit = self.pool.config.read_timeout;
+        let client = self.client.as_mut().expect("connections are only emptied on drop");
+        let response = timeout(limit, client.call(request))
+            .await
+            .unwrap_or_else(|_| Err(anyhow::Error::msg("Timed out waiting for the server")));
+        self.broken |= response.is_err();
+        response
+    }
+}
+
+impl Drop for Connection<'_> {
+    fn drop(&mut self) {
+        if let (Some(client), false) = (self.client.take(), self.broken) {
+            self.pool.idle.lock().unwrap().push(Idle { client, since: Instant::now() });
+        }
+    }
+}
+
+/// Events pushed by the server.
+pub struct Subscription {
+    client: Client,
+}
+
+impl Subscription {
+    /// Wait for the next event. Errors mean the connection is gone.
+    pub async fn next(&mut self) -> anyhow::Result<Event> {
+        loop {
+            if let Response::Event(event) = self.client.receive().await? {
+                return Ok(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(PoolConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn nonsense_settings_are_rejected() {
+        assert!(PoolConfig { address: "nowhere".to_string(), ..Default::default() }.validate().is_err());
+        assert!(PoolConfig { size: 0, ..Default::default() }.validate().is_err());
+        assert!(PoolConfig { read_timeout: Duration::ZERO, ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn breaker_opens_and_recovers() {
+        let (cooldown, now) = (Duration::from_secs(10), Instant::now());
+        let mut breaker = Breaker { failures: 0, opened: None };
+        breaker.failed(2, now);
+        assert_eq!(breaker.state(cooldown, now), CircuitState::Closed);
+        breaker.failed(2, now);
+        assert_eq!(breaker.state(cooldown, now), CircuitState::Open);
+        assert_eq!(breaker.state(cooldown, now + cooldown), CircuitState::HalfOpen);
+        breaker.succeeded();
+        assert_eq!(breaker.state(cooldown, now + cooldown), CircuitState::Closed);
+    }
+
+    #[test]
+    fn failed_trial_reopens() {
+        let (cooldown, now) = (Duration::from_secs(10), Instant::now());
+        let mut breaker = Breaker { failures: 0, opened: None };
+        breaker.failed(1, now);
+        let later = now + cooldown;
+        assert_eq!(breaker.state(cooldown, later), CircuitState::HalfOpen);
+        breaker.failed(1, later);
+        assert_eq!(breaker.state(cooldown, later), CircuitState::Open);
+    }
+}
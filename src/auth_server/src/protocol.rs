@@ -0,0 +1,191 @@
+use std::time::Duration;
+use serde::{Serialize, Deserialize};
+use auth_json::*;
+
+/// Bumped whenever the request/response format changes incompatibly.
+pub const PROTOCOL_VERSION: u16 = 3;
+
+/// Every hello starts with these bytes, so we can spot clients that aren't speaking our protocol.
+pub const MAGIC: [u8; 4] = *b"AUTH";
+
+/// Both sides send `Heartbeat` messages while the connection is open, and hang
+/// up if the other side goes quiet for too long. Clients only heartbeat while
+/// waiting for an answer, and check what arrived while idle before their next
+/// request. Heartbeats don't count as activity for the server's idle timeout:
+/// they prove the client is alive, not that it needs the connection.
+pub const FEATURE_HEARTBEAT: u32 = 1 << 0;
+
+/// Optional protocol features, advertised as bit flags in the hello exchange.
+/// Both sides use whatever subset they have in common.
+pub const SUPPORTED_FEATURES: u32 = FEATURE_HEARTBEAT;
+
+/// How often each side sends a heartbeat, when heartbeats are enabled.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Hang up after hearing nothing for this many heartbeat intervals.
+pub const MISSED_HEARTBEAT_LIMIT: u32 = 3;
+
+/// The first message on every connection, sent by the client.
+#[derive(Serialize, Deserialize)]
+pub struct Hello {
+    pub magic: [u8; 4],
+    pub version: u16,
+    pub features: u32,
+}
+
+impl Hello {
+    pub fn new() -> Self {
+        Self { magic: MAGIC, version: PROTOCOL_VERSION, features: SUPPORTED_FEATURES }
+    }
+}
+
+impl Default for Hello {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The server's answer to a `Hello`.
+#[derive(Serialize, Deserialize, Debug)]
+pub enum HelloResponse {
+    /// Go ahead, using this version and the common subset of features.
+    Accepted { version: u16, features: u32 },
+    /// The server can't talk to this client. The connection will be closed.
+    UnsupportedVersion { supported: u16 },
+}
+
+/// Messages a client can send to the login server.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Request {
+    /// Check a username and password. Accepted logins receive a session token.
+    Login { username: String, password: String },
+    /// Check that a session token issued by a previous login is still valid.
+    ValidateToken(String),
+    /// Manage the user database. The token must belong to an admin session.
+    Admin { token: String, command: AdminCommand },
+    /// Liveness check. Always answered with `Response::Pong`.
+    Ping,
+    /// Receive `Response::Event` messages whenever the user database changes or
+    /// someone tries to log in.
+    /// The token must belong to an admin session.
+    Subscribe { token: String },
+    /// Ask for a snapshot of the server's counters.
+    Stats,
+    /// "I'm still here". Not answered - the server sends its own heartbeats.
+    Heartbeat,
+    /// Change the password of the user the token belongs to. Their other
+    /// sessions are ended if it works.
+    ChangePassword { token: String, current_password: String, new_password: String },
+}
+
+/// User management operations available to admins.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum AdminCommand {
+    ListUsers,
+    AddUser { username: String, password: String, action: LoginAction },
+    DeleteUser(String),
+    SetAction { username: String, action: LoginAction },
+    SetPassword { username: String, password: String },
+}
+
+/// Messages the login server sends back.
+#[derive(Serialize, Deserialize, Debug)]
+pub enum Response {
+    /// The result of a login. `None` means the user is unknown or the password was wrong.
+    Login { action: Option<LoginAction>, token: Option<String> },
+    /// The session attached to a token, or `None` if the token is unknown or expired.
+    Session(Option<Session>),
+    /// The request couldn't be decoded.
+    BadRequest,
+    /// The outcome of an admin command.
+    Admin(AdminResponse),
+    /// Reply to `Request::Ping`.
+    Pong,
+    /// The subscription is active; events will follow on this connection.
+    Subscribed,
+    /// Something changed, pushed to subscribers without a request.
+    Event(Event),
+    /// Reply to `Request::Stats`.
+    Stats(ServerStats),
+    /// "I'm still here", sent periodically when heartbeats are enabled.
+    Heartbeat,
+    /// The outcome of `Request::ChangePassword`.
+    PasswordChange(PasswordChange),
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum PasswordChange {
+    Done,
+    /// The current password didn't match.
+    WrongPassword,
+    /// The token is unknown or expired.
+    NotLoggedIn,
+    /// The new password breaks the password policy, for each of these reasons.
+    Rejected(Vec<String>),
+}
+
+/// A snapshot of what the server has been doing.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ServerStats {
+    pub active_connections: u64,
+    pub total_connections: u64,
+    /// Correct password, and the user was let in.
+    pub logins_accepted: u64,
+    /// Correct password, but the account is expired or locked.
+    pub logins_denied: u64,
+    /// Unknown user or wrong password.
+    pub logins_failed: u64,
+    /// Refused because the client failed too often, or too many logins were waiting.
+    pub logins_throttled: u64,
+    pub uptime_secs: u64,
+}
+
+/// Changes to the user database and login attempts, as seen by subscribers.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Event {
+    UserChanged(UserInfo),
+    UserDeleted(String),
+    /// Someone tried to log in.
+    Login { username: String, outcome: LoginOutcome },
+}
+
+/// How a login attempt went, as seen by subscribers.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum LoginOutcome {
+    Accepted,
+    /// The password was right, but the account can't be used.
+    Denied(DeniedReason),
+    /// Unknown user or wrong password.
+    Failed,
+    /// Refused because the client failed too often, or too many logins were waiting.
+    Throttled,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum AdminResponse {
+    Done,
+    Users(Vec<UserInfo>),
+    NotAuthorized,
+    UserExists,
+    UnknownUser,
+}
+
+/// A user as reported to admins - without the password.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UserInfo {
+    pub username: String,
+    pub action: LoginAction,
+}
+
+impl From<&User> for UserInfo {
+    fn from(user: &User) -> Self {
+        Self { username: user.username.clone(), action: user.action.clone() }
+    }
+}
+
+/// Who a session token belongs to.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Session {
+    pub username: String,
+    pub role: Role,
+}
@@ -0,0 +1,379 @@
+use std::{net::SocketAddr, path::PathBuf, sync::{Arc, Weak}, time::Duration};
+use tokio::{net::{TcpListener, TcpStream, UdpSocket}, spawn, io::AsyncWriteExt, sync::broadcast, time::{Instant, interval_at, sleep, sleep_until, timeout}};
+use auth_json::*;
+use crate::{admin, events::Events, policy, framing::{Format, FrameReader, write_frame}, protocol::*, sessions::Sessions, stats::Stats, throttle::{self, Throttle}, udp, users::UserStore, verify::{Verifier, VerifyError}};
+
+/// Where the server listens unless told otherwise.
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:8123";
+
+/// The user file the server loads unless told otherwise.
+pub const DEFAULT_STORE: &str = "users.json";
+
+/// Everything the connections of one server share.
+pub(crate) struct Shared {
+    pub users: Arc<UserStore>,
+    pub sessions: Sessions,
+    pub throttle: Throttle,
+    pub events: Events,
+    pub stats: Stats,
+    pub verifier: Verifier,
+}
+
+impl Shared {
+    pub fn new(users: Arc<UserStore>) -> Self {
+        Self {
+            users,
+            sessions: Sessions::default(),
+            throttle: Throttle::default(),
+            events: Events::new(),
+            stats: Stats::new(),
+            verifier: Verifier::new(),
+        }
+    }
+}
+
+/// Configures a [`Server`]. Start with [`Server::builder`].
+pub struct ServerBuilder {
+    address: String,
+    store: PathBuf,
+    idle_timeout: Duration,
+    udp: bool,
+}
+
+impl ServerBuilder {
+    /// The address to listen on. Use port 0 to let the OS pick a free port.
+    pub fn bind(mut self, address: impl Into<String>) -> Self {
+        self.address = address.into();
+        self
+    }
+
+    /// The JSON file holding the user database. Changes are written back to it.
+    pub fn store(mut self, path: impl Into<PathBuf>) -> Self {
+        self.store = path.into();
+        self
+    }
+
+    /// How long a TCP connection may go without a request before the server
+    /// closes it. Heartbeats don't count.
+    pub fn idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Answer single datagrams over UDP instead of accepting TCP connections.
+    pub fn udp(mut self, udp: bool) -> Self {
+        self.udp = udp;
+        self
+    }
+
+    /// Load the users and bind the socket, without accepting anything yet.
+    pub async fn build(self) -> anyhow::Result<Server> {
+        let listener = if self.udp {
+            Listener::Udp(Arc::new(UdpSocket::bind(&self.address).await?))
+        } else {
+            Listener::Tcp(TcpListener::bind(&self.address).await?)
+        };
+        let shared = Arc::new(Shared::new(UserStore::open(self.store)?));
+        spawn(reload_on_sighup(Arc::downgrade(&shared)));
+        Ok(Server { listener, shared, idle_timeout: self.idle_timeout })
+    }
+
+    /// Build the server and run it until it fails.
+    pub async fn serve(self) -> anyhow::Result<()> {
+        self.build().await?.serve().await
+    }
+}
+
+enum Listener {
+    Tcp(TcpListener),
+    Udp(Arc<UdpSocket>),
+}
+
+/// A login server, bound and ready to answer requests.
+pub struct Server {
+    listener: Listener,
+    shared: Arc<Shared>,
+    idle_timeout: Duration,
+}
+
+impl Server {
+    pub fn builder() -> ServerBuilder {
+        ServerBuilder {
+            address: DEFAULT_ADDRESS.to_string(),
+            store: PathBuf::from(DEFAULT_STORE),
+            idle_timeout: Duration::from_secs(30),
+            udp: false,
+        }
+    }
+
+    /// The address the server is listening on, useful after binding to port 0.
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        match &self.listener {
+            Listener::Tcp(listener) => listener.local_addr(),
+            Listener::Udp(socket) => socket.local_addr(),
+        }
+    }
+
+    /// A snapshot of the server's counters.
+    pub fn stats(&self) -> ServerStats {
+        self.shared.stats.snapshot()
+    }
+
+    /// Answer requests until the socket fails.
+    pub async fn serve(self) -> anyhow::Result<()> {
+        let listener = match self.listener {
+            Listener::Tcp(listener) => listener,
+            Listener::Udp(socket) => return udp::serve(socket, self.shared).await,
+        };
+        loop {
+            let (mut socket, address) = listener.accept().await?;
+            let shared = self.shared.clone();
+            let idle_timeout = self.idle_timeout;
+            spawn(async move {
+                let _active = shared.stats.connection();
+                if let Err(e) = handle_connection(&shared, &mut socket, address, idle_timeout).await {
+                    println!("Error on connection from {address}: {e}");
+                }
+                // Whatever happened, say goodbye properly
+                let _ = socket.shutdown().await;
+            });
+        }
+    }
+}
+
+/// Reload the user file whenever the process receives SIGHUP, for as long as the server exists.
+#[cfg(unix)]
+async fn reload_on_sighup(shared: Weak<Shared>) {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            println!("Unable to listen for SIGHUP: {e}");
+            return;
+        }
+    };
+    while hangup.recv().await.is_some() {
+        let Some(shared) = shared.upgrade() else {
+            break;
+        };
+        shared.users.reload(&shared.events);
+    }
+}
+
+#[cfg(not(unix))]
+async fn reload_on_sighup(_shared: Weak<Shared>) {}
+
+pub(crate) async fn handle_request(shared: &Shared, request: Request, address: SocketAddr) -> Response {
+    match request {
+        Request::ChangePassword { .. } if shared.throttle.is_throttled(address.ip()) => {
+            sleep(throttle::PENALTY_DELAY).await;
+            Response::PasswordChange(PasswordChange::WrongPassword)
+        }
+        Request::Login { username, .. } if shared.throttle.is_throttled(address.ip()) => {
+            shared.stats.login_throttled();
+            shared.events.publish(Event::Login { username, outcome: LoginOutcome::Throttled });
+            sleep(throttle::PENALTY_DELAY).await;
+            Response::Login {
+                action: Some(LoginAction::Denied(DeniedReason::TooManyAttempts)),
+                token: None,
+            }
+        }
+        Request::Login { username, password } => {
+            let action = match shared.verifier.login(&shared.users, username.clone(), password).await {
+                Ok(action) => action,
+                // Our problem, not the client's, so neither counts against them
+                Err(VerifyError::Busy) => {
+                    shared.stats.login_throttled();
+                    shared.events.publish(Event::Login { username, outcome: LoginOutcome::Throttled });
+                    return Response::Login {
+                        action: Some(LoginAction::Denied(DeniedReason::TooManyAttempts)),
+                        token: None,
+                    };
+                }
+                Err(VerifyError::Failed(e)) => {
+                    println!("Unable to check password for {username}: {e}");
+                    return Response::Login { action: None, token: None };
+                }
+            };
+            let outcome = match &action {
+                Some(LoginAction::Accept(..)) => {
+                    shared.stats.login_accepted();
+                    LoginOutcome::Accepted
+                }
+                Some(LoginAction::Denied(reason)) => {
+                    shared.stats.login_denied();
+                    LoginOutcome::Denied(reason.clone())
+                }
+                None => {
+                    shared.stats.login_failed();
+                    shared.throttle.record_failure(address.ip());
+                    LoginOutcome::Failed
+                }
+            };
+            shared.events.publish(Event::Login { username: username.clone(), outcome });
+            let token = match &action {
+                Some(LoginAction::Accept(role)) => Some(shared.sessions.create(Session {
+                    username: username.trim().to_lowercase(),
+                    role: role.clone(),
+                })),
+                _ => None,
+            };
+            Response::Login { action, token }
+        }
+        Request::ValidateToken(token) => Response::Session(shared.sessions.validate(&token)),
+        Request::Admin { token, command } => Response::Admin(admin::handle(shared, &token, command)),
+        Request::Ping => Response::Pong,
+        // Subscriptions belong to a connection, so handle_connection deals with them
+        Request::Subscribe { .. } => Response::BadRequest,
+        Request::Stats => Response::Stats(shared.stats.snapshot()),
+        // Nothing to say back; the connection keeps its own heartbeat
+        Request::Heartbeat => Response::BadRequest,
+        Request::ChangePassword { token, current_password, new_password } => {
+            Response::PasswordChange(change_password(shared, &token, current_password, new_password, address).await)
+        }
+    }
+}
+
+async fn change_password(shared: &Shared, token: &str, current: String, new: String, address: SocketAddr) -> PasswordChange {
+    let Some(session) = shared.sessions.validate(token) else {
+        return PasswordChange::NotLoggedIn;
+    };
+    // Logins trim what they're given, so store what they'll compare against
+    let new = new.trim().to_string();
+    // Front ends check too, to answer sooner, but only this check is certain to run
+    let problems = policy::password_problems(&session.username, &new);
+    if !problems.is_empty() {
+        return PasswordChange::Rejected(problems.into_iter().map(String::from).collect());
+    }
+    // Locked or expired accounts still count as knowing the password
+    match shared.verifier.login(&shared.users, session.username.clone(), current).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            shared.throttle.record_failure(address.ip());
+            return PasswordChange::WrongPassword;
+        }
+        Err(e) => {
+            println!("Unable to check password for {}: {e}", session.username);
+            return PasswordChange::WrongPassword;
+        }
+    }
+    let info = match shared.users.write().get_mut(&session.username) {
+        Some(user) => {
+            user.password = new;
+            UserInfo::from(&*user)
+        }
+        // Deleted since they logged in
+        None => return PasswordChange::NotLoggedIn,
+    };
+    shared.users.request_save();
+    shared.events.publish(Event::UserChanged(info));
+    shared.sessions.end_others(&session.username, token);
+    PasswordChange::Done
+}
+
+/// Read the client's `Hello` and agree on a protocol version, returning the
+/// format the client used and the negotiated feature flags.
+async fn handshake(socket: &mut TcpStream, reader: &mut FrameReader) -> anyhow::Result<(Format, u32)> {
+    let Some(frame) = reader.read_frame(socket).await? else {
+        return Err(anyhow::Error::msg("connection closed before hello"));
+    };
+    let hello = frame.decode::<Hello>();
+    let reply = match hello {
+        Ok(hello) if hello.magic == MAGIC && hello.version == PROTOCOL_VERSION => {
+            HelloResponse::Accepted {
+                version: PROTOCOL_VERSION,
+                features: hello.features & SUPPORTED_FEATURES,
+            }
+        }
+        _ => HelloResponse::UnsupportedVersion { supported: PROTOCOL_VERSION },
+    };
+    write_frame(socket, frame.format, &reply).await?;
+
+    match reply {
+        HelloResponse::Accepted { features, .. } => Ok((frame.format, features)),
+        HelloResponse::UnsupportedVersion { .. } => Err(anyhow::Error::msg("unsupported protocol version")),
+    }
+}
+
+async fn handle_connection(shared: &Shared, socket: &mut TcpStream, address: SocketAddr, idle_timeout: Duration) -> anyhow::Result<()> {
+    let mut reader = FrameReader::new();
+
+    // Agree on a protocol version before accepting any requests
+    let (hello_format, features) = match timeout(idle_timeout, handshake(socket, &mut reader)).await {
+        Ok(negotiated) => negotiated?,
+        Err(..) => {
+            println!("Closing idle connection from {address}");
+            return Ok(());
+        }
+    };
+    let heartbeats = features & FEATURE_HEARTBEAT != 0;
+    let mut heartbeat = interval_at(Instant::now() + HEARTBEAT_INTERVAL, HEARTBEAT_INTERVAL);
+
+    // Set once the client subscribes to events, along with the format it wants them in
+    let mut subscription: Option<(Format, broadcast::Receiver<Event>)> = None;
+
+    // When we last heard anything at all, and when we last had something to do
+    let mut last_heard = Instant::now();
+    let mut last_request = Instant::now();
+
+    loop {
+        let frame = tokio::select! {
+            read = reader.read_frame(socket) => read?,
+            event = next_event(&mut subscription) => {
+                let (format, _) = subscription.as_ref().expect("events only arrive when subscribed");
+                write_frame(socket, *format, &Response::Event(event?)).await?;
+                continue;
+            }
+            _ = heartbeat.tick(), if heartbeats => {
+                if last_heard.elapsed() > HEARTBEAT_INTERVAL * MISSED_HEARTBEAT_LIMIT {
+                    println!("{address} stopped sending heartbeats, closing connection");
+                    return Ok(());
+                }
+                write_frame(socket, hello_format, &Response::Heartbeat).await?;
+                continue;
+            }
+            // Don't let a silent (or dead) client hold the connection open forever.
+            // Heartbeats don't reset this, so an idle client that's merely alive
+            // still gets closed. Subscribers are expected to sit quietly, so they're exempt.
+            _ = sleep_until(last_request + idle_timeout), if subscription.is_none() => {
+                println!("Closing idle connection from {address}");
+                return Ok(());
+            }
+        };
+
+        // The client hung up
+        let Some(frame) = frame else {
+            return Ok(());
+        };
+        last_heard = Instant::now();
+
+        let response = match frame.decode::<Request>() {
+            Ok(Request::Heartbeat) => continue,
+            Ok(Request::Subscribe { token }) if admin::is_admin(shared, &token) => {
+                subscription = Some((frame.format, shared.events.subscribe()));
+                Response::Subscribed
+            }
+            Ok(Request::Subscribe { .. }) => Response::Admin(AdminResponse::NotAuthorized),
+            Ok(request) => handle_request(shared, request, address).await,
+            Err(..) => Response::BadRequest,
+        };
+
+        // Answer in whatever format the client asked in
+        write_frame(socket, frame.format, &response).await?;
+        last_request = Instant::now();
+    }
+}
+
+/// Wait for the next event on a subscription. Never completes if there isn't one.
+async fn next_event(subscription: &mut Option<(Format, broadcast::Receiver<Event>)>) -> anyhow::Result<Event> {
+    match subscription {
+        Some((_, events)) => match events.recv().await {
+            Ok(event) => Ok(event),
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                Err(anyhow::Error::msg(format!("subscriber fell behind and missed {missed} events")))
+            }
+            Err(broadcast::error::RecvError::Closed) => Err(anyhow::Error::msg("event feed closed")),
+        },
+        None => std::future::pending().await,
+    }
+}
@@ -0,0 +1,37 @@
+use std::future::Future;
+use serde::Deserialize;
+use auth_json::{LoginAction, Role};
+use crate::{pool::Connection, protocol::{Request, Response}};
+
+/// Somewhere to send requests: a pooled connection, or a frontend's in-process
+/// stand-in for one.
+pub trait Call {
+    fn call(&mut self, request: &Request) -> impl Future<Output = anyhow::Result<Response>> + Send;
+}
+
+impl Call for Connection<'_> {
+    fn call(&mut self, request: &Request) -> impl Future<Output = anyhow::Result<Response>> + Send {
+        Connection::call(self, request)
+    }
+}
+
+/// The admin account a web frontend uses to act on users' behalf, such as
+/// registering them. Rocket reads it from Rocket.toml, hence the names.
+#[derive(Deserialize)]
+pub struct ServiceAccount {
+    #[serde(rename = "service_username")]
+    pub username: String,
+    #[serde(rename = "service_password")]
+    pub password: String,
+}
+
+impl ServiceAccount {
+    /// Log in as the service account, returning its session token.
+    pub async fn login(&self, client: &mut impl Call) -> anyhow::Result<String> {
+        let request = Request::Login { username: self.username.clone(), password: self.password.clone() };
+        match client.call(&request).await? {
+            Response::Login { action: Some(LoginAction::Accept(Role::Admin)), token: Some(token) } => Ok(token),
+            _ => Err(anyhow::Error::msg("The service account can't log in as an admin")),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::{collections::HashMap, time::{Duration, Instant}};
+use parking_lot::RwLock;
+use crate::protocol::Session;
+
+/// How long a session token remains valid after login.
+const SESSION_TTL: Duration = Duration::from_secs(60 * 60);
+
+struct ActiveSession {
+    session: Session,
+    expires: Instant,
+}
+
+/// The sessions handed out by one server, keyed by token.
+#[derive(Default)]
+pub struct Sessions {
+    sessions: RwLock<HashMap<String, ActiveSession>>,
+}
+
+impl Sessions {
+    /// Start a new session, returning the token that identifies it.
+    pub fn create(&self, session: Session) -> String {
+        let token = uuid::Uuid::new_v4().to_string();
+        let mut sessions = self.sessions.write();
+        // Tidy up while we hold the write lock anyway
+        let now = Instant::now();
+        sessions.retain(|_, s| s.expires > now);
+        sessions.insert(token.clone(), ActiveSession { session, expires: now + SESSION_TTL });
+        token
+    }
+
+    /// Look up a token, returning the session if it exists and hasn't expired.
+    pub fn validate(&self, token: &str) -> Option<Session> {
+        self.sessions
+            .read()
+            .get(token)
+            .filter(|s| s.expires > Instant::now())
+            .map(|s| s.session.clone())
+    }
+
+    /// End every session belonging to `username` except `keep`.
+    pub fn end_others(&self, username: &str, keep: &str) {
+        self.sessions
+            .write()
+            .retain(|token, s| token == keep || s.session.username != username);
+    }
+
+    /// End every session belonging to `username`, so a deleted or denied
+    /// user is locked out straight away.
+    pub fn end_all(&self, username: &str) {
+        self.sessions
+            .write()
+            .retain(|_, s| s.session.username != username);
+    }
+}
@@ -0,0 +1,71 @@
+use std::{sync::atomic::{AtomicU64, Ordering}, time::Instant};
+use crate::protocol::ServerStats;
+
+/// Counters describing what one server has been up to.
+pub struct Stats {
+    started: Instant,
+    active_connections: AtomicU64,
+    total_connections: AtomicU64,
+    logins_accepted: AtomicU64,
+    logins_denied: AtomicU64,
+    logins_failed: AtomicU64,
+    logins_throttled: AtomicU64,
+}
+
+/// Counts a connection as active for as long as it is alive.
+pub struct ConnectionGuard<'a>(&'a Stats);
+
+impl Drop for ConnectionGuard<'_> {
+    fn drop(&mut self) {
+        self.0.active_connections.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Stats {
+    /// Start the uptime clock.
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            active_connections: AtomicU64::new(0),
+            total_connections: AtomicU64::new(0),
+            logins_accepted: AtomicU64::new(0),
+            logins_denied: AtomicU64::new(0),
+            logins_failed: AtomicU64::new(0),
+            logins_throttled: AtomicU64::new(0),
+        }
+    }
+
+    pub fn connection(&self) -> ConnectionGuard<'_> {
+        self.active_connections.fetch_add(1, Ordering::Relaxed);
+        self.total_connections.fetch_add(1, Ordering::Relaxed);
+        ConnectionGuard(self)
+    }
+
+    pub fn login_accepted(&self) {
+        self.logins_accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn login_denied(&self) {
+        self.logins_denied.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn login_failed(&self) {
+        self.logins_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn login_throttled(&self) {
+        self.logins_throttled.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> ServerStats {
+        ServerStats {
+            active_connections: self.active_connections.load(Ordering::Relaxed),
+            total_connections: self.total_connections.load(Ordering::Relaxed),
+            logins_accepted: self.logins_accepted.load(Ordering::Relaxed),
+            logins_denied: self.logins_denied.load(Ordering::Relaxed),
+            logins_failed: self.logins_failed.load(Ordering::Relaxed),
+            logins_throttled: self.logins_throttled.load(Ordering::Relaxed),
+            uptime_secs: self.started.elapsed().as_secs(),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::{collections::HashMap, net::IpAddr, time::{Duration, Instant}};
+use parking_lot::Mutex;
+
+/// Failed logins an address may accumulate before it is refused outright.
+const MAX_FAILURES: f64 = 5.0;
+
+/// Failures are forgiven at this rate, so an address recovers if it backs off.
+const FORGIVEN_PER_SECOND: f64 = 0.1;
+
+/// How long a throttled client waits for its refusal.
+pub const PENALTY_DELAY: Duration = Duration::from_secs(1);
+
+struct Failures {
+    score: f64,
+    updated: Instant,
+}
+
+impl Failures {
+    /// The score after decaying it up to `now`.
+    fn current(&self, now: Instant) -> f64 {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        f64::max(0.0, self.score - elapsed * FORGIVEN_PER_SECOND)
+    }
+}
+
+/// Recent failed logins, by address.
+#[derive(Default)]
+pub struct Throttle {
+    failures: Mutex<HashMap<IpAddr, Failures>>,
+}
+
+impl Throttle {
+    /// Has this address failed too many logins recently?
+    pub fn is_throttled(&self, address: IpAddr) -> bool {
+        self.failures
+            .lock()
+            .get(&address)
+            .map(|f| f.current(Instant::now()) >= MAX_FAILURES)
+            .unwrap_or(false)
+    }
+
+    /// Count a failed login against an address.
+    pub fn record_failure(&self, address: IpAddr) {
+        let now = Instant::now();
+        let mut failures = self.failures.lock();
+        // Forget addresses that have fully recovered, so the map doesn't grow forever
+        failures.retain(|_, f| f.current(now) > 0.0);
+        let score = failures.get(&address).map(|f| f.current(now)).unwrap_or(0.0) + 1.0;
+        failures.insert(address, Failures { score, updated: now });
+    }
+}
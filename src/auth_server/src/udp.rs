@@ -0,0 +1,61 @@
+use std::{sync::Arc, time::Duration};
+use tokio::{net::{ToSocketAddrs, UdpSocket}, spawn, time::timeout};
+use crate::{framing::{Format, decode_datagram, encode_datagram}, protocol::*, server::{self, Shared}};
+
+/// Largest datagram we expect to receive.
+const MAX_DATAGRAM: usize = 1500;
+
+/// How long the client waits for an answer before giving up. UDP doesn't retransmit.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Answer login, ping and stats requests sent as single datagrams. There's no
+/// connection, so no hello exchange - each datagram stands alone.
+pub(crate) async fn serve(socket: Arc<UdpSocket>, shared: Arc<Shared>) -> anyhow::Result<()> {
+    let mut buf = vec![0; MAX_DATAGRAM];
+    loop {
+        let (n, address) = socket.recv_from(&mut buf).await?;
+        let frame = match decode_datagram(&buf[0..n]) {
+            Ok(frame) => frame,
+            Err(e) => {
+                println!("Ignoring datagram from {address}: {e}");
+                continue;
+            }
+        };
+
+        // Handle each datagram separately, so a throttled client can't stall everyone else
+        let socket = socket.clone();
+        let shared = shared.clone();
+        spawn(async move {
+            let response = match frame.decode::<Request>() {
+                Ok(request @ (Request::Login { .. } | Request::Ping | Request::Stats)) => server::handle_request(&shared, request, address).await,
+                _ => Response::BadRequest,
+            };
+            let sent = match encode_datagram(frame.format, &response) {
+                Ok(reply) => socket.send_to(&reply, address).await.map(|_| ()).map_err(anyhow::Error::from),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = sent {
+                println!("Unable to reply to {address}: {e}");
+            }
+        });
+    }
+}
+
+/// Send one request as a datagram to the server at `address` and wait for the reply.
+pub async fn call(address: impl ToSocketAddrs, format: Format, request: &Request) -> anyhow::Result<Response> {
+    let address = tokio::net::lookup_host(address)
+        .await?
+        .next()
+        .ok_or_else(|| anyhow::Error::msg("The server's address didn't resolve"))?;
+    // Any local address of the same family, so servers on other hosts (and IPv6) are reachable
+    let local = if address.is_ipv6() { "[::]:0" } else { "0.0.0.0:0" };
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect(address).await?;
+    socket.send(&encode_datagram(format, request)?).await?;
+
+    let mut buf = vec![0; MAX_DATAGRAM];
+    let n = timeout(REPLY_TIMEOUT, socket.recv(&mut buf))
+        .await
+        .map_err(|_| anyhow::Error::msg("No reply from the server"))??;
+    decode_datagram(&buf[0..n])?.decode()
+}
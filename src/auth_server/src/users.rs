@@ -0,0 +1,121 @@
+use std::{collections::HashMap, path::{Path, PathBuf}, sync::{Arc, Weak}, time::Duration};
+use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tokio::{spawn, sync::mpsc, time::sleep};
+use auth_json::*;
+use crate::{events::Events, protocol::{Event, UserInfo}};
+
+/// How long the writer waits for further changes before saving a burst of them.
+const SAVE_DELAY: Duration = Duration::from_millis(250);
+
+/// The user database, loaded from a JSON file and written back when it changes.
+pub struct UserStore {
+    // Neither is set for a store that only lives in memory
+    path: Option<PathBuf>,
+    users: RwLock<HashMap<String, User>>,
+    save_queue: Option<mpsc::Sender<()>>,
+}
+
+/// Read the user file without panicking, so a bad edit can't take the server down.
+fn load_users(path: &Path) -> anyhow::Result<HashMap<String, User>> {
+    let json = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+impl UserStore {
+    /// Load the users from `path` and start the write-behind task that saves
+    /// them whenever they change. Must be called from within a Tokio runtime.
+    pub fn open(path: impl Into<PathBuf>) -> anyhow::Result<Arc<Self>> {
+        let path = path.into();
+        let users = load_users(&path)
+            .map_err(|e| anyhow::Error::msg(format!("Unable to load {}: {e}", path.display())))?;
+        let (save_queue, rx) = mpsc::channel(1);
+        let store = Arc::new(Self { path: Some(path.clone()), users: RwLock::new(users), save_queue: Some(save_queue) });
+        spawn_writer(Arc::downgrade(&store), path, rx);
+        Ok(store)
+    }
+
+    /// Hold `users` in memory only: changes are never saved, and there's nothing to reload.
+    pub fn in_memory(users: HashMap<String, User>) -> Arc<Self> {
+        Arc::new(Self { path: None, users: RwLock::new(users), save_queue: None })
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<'_, HashMap<String, User>> {
+        self.users.read()
+    }
+
+    pub fn write(&self) -> RwLockWriteGuard<'_, HashMap<String, User>> {
+        self.users.write()
+    }
+
+    /// Re-read the user file and swap it in. On failure the current users are kept.
+    pub fn reload(&self, events: &Events) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        match load_users(path) {
+            Ok(users) => {
+                let count = users.len();
+                let mut current = self.users.write();
+                let changes = changes(&current, &users);
+                *current = users;
+                drop(current);
+                println!("Reloaded {count} users from {}", path.display());
+                changes.into_iter().for_each(|event| events.publish(event));
+            }
+            Err(e) => println!("Unable to reload {}, keeping current users: {e}", path.display()),
+        }
+    }
+
+    /// Ask the writer task to persist the current users. Bursts of changes are coalesced.
+    pub fn request_save(&self) {
+        // A full queue means a save is already pending, and it will include this change.
+        if let Some(save_queue) = &self.save_queue {
+            let _ = save_queue.try_send(());
+        }
+    }
+
+    /// Write a snapshot of the users to a temporary file, then rename it over the
+    /// real one so readers never see a half-written file.
+    async fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(&*self.users.read())?;
+        let tmp = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, json).await?;
+        tokio::fs::rename(&tmp, path).await?;
+        Ok(())
+    }
+}
+
+/// Save the store whenever a save is requested. The task ends once the store is dropped.
+fn spawn_writer(store: Weak<UserStore>, path: PathBuf, mut rx: mpsc::Receiver<()>) {
+    spawn(async move {
+        while rx.recv().await.is_some() {
+            sleep(SAVE_DELAY).await;
+            // Anything requested while we waited is covered by this snapshot
+            let _ = rx.try_recv();
+            let Some(store) = store.upgrade() else {
+                break;
+            };
+            if let Err(e) = store.save(&path).await {
+                println!("Unable to save {}: {e}", path.display());
+            }
+        }
+    });
+}
+
+/// Work out what a reload changed, so subscribers hear about it.
+fn changes(old: &HashMap<String, User>, new: &HashMap<String, User>) -> Vec<Event> {
+    let mut events: Vec<Event> = new
+        .iter()
+        .filter(|(name, user)| match old.get(*name) {
+            Some(previous) => previous.password != user.password || previous.action != user.action,
+            None => true,
+        })
+        .map(|(_, user)| Event::UserChanged(UserInfo::from(user)))
+        .collect();
+    events.extend(
+        old.keys()
+            .filter(|name| !new.contains_key(*name))
+            .map(|name| Event::UserDeleted(name.clone()))
+    );
+    events
+}
@@ -0,0 +1,110 @@
+use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
+use tokio::{sync::{Semaphore, SemaphorePermit}, task::{JoinError, spawn_blocking}};
+use auth_json::*;
+use crate::users::UserStore;
+
+/// How many logins may wait for a free thread, per thread.
+const WAITING_PER_THREAD: usize = 8;
+
+/// Password checks are CPU work (and will get much heavier once passwords are
+/// hashed with something like Argon2), so they run on Tokio's blocking pool
+/// rather than stalling the reactor. This caps how many run at once, and how
+/// many logins may queue for a turn. Past that, logins are turned away rather
+/// than piling up during a flood.
+pub struct Verifier {
+    slots: Semaphore,
+    waiting: AtomicUsize,
+    max_waiting: usize,
+}
+
+/// Why a password couldn't be checked.
+#[derive(Debug)]
+pub enum VerifyError {
+    /// The queue is full; try again later.
+    Busy,
+    /// The check itself failed to run.
+    Failed(JoinError),
+}
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            VerifyError::Busy => write!(f, "too many password checks are waiting"),
+            VerifyError::Failed(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// Counts a caller as waiting until it's dropped, even if it gives up.
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Verifier {
+    pub fn new() -> Self {
+        let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+        Self::with_capacity(threads, threads * WAITING_PER_THREAD)
+    }
+
+    /// Run up to `running` checks at once, with up to `waiting` more queued.
+    fn with_capacity(running: usize, waiting: usize) -> Self {
+        Self { slots: Semaphore::new(running), waiting: AtomicUsize::new(0), max_waiting: waiting }
+    }
+
+    /// Check a username and password without blocking the async runtime.
+    pub async fn login(&self, users: &Arc<UserStore>, username: String, password: String) -> Result<Option<LoginAction>, VerifyError> {
+        let _slot = self.slot().await.ok_or(VerifyError::Busy)?;
+        let users = users.clone();
+        spawn_blocking(move || auth_json::login(&users.read(), &username, &password))
+            .await
+            .map_err(VerifyError::Failed)
+    }
+
+    /// Wait for a turn, or `None` straight away if the queue is full.
+    async fn slot(&self) -> Option<SemaphorePermit<'_>> {
+        if let Ok(slot) = self.slots.try_acquire() {
+            return Some(slot);
+        }
+        self.waiting
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |waiting| (waiting < self.max_waiting).then_some(waiting + 1))
+            .ok()?;
+        let _waiting = Waiting(&self.waiting);
+        Some(self.slots.acquire().await.expect("the semaphore is never closed"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn a_full_queue_turns_callers_away() {
+        let verifier = Verifier::with_capacity(1, 1);
+        let running = verifier.slot().await.unwrap();
+
+        // The second caller queues...
+        let queued = verifier.slot();
+        tokio::pin!(queued);
+        assert!(tokio::time::timeout(Duration::from_millis(10), &mut queued).await.is_err());
+
+        // ...so the third is refused without waiting
+        assert!(verifier.slot().await.is_none());
+
+        drop(running);
+        assert!(queued.await.is_some());
+        assert_eq!(verifier.waiting.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn giving_up_leaves_the_queue() {
+        let verifier = Verifier::with_capacity(1, 1);
+        let _running = verifier.slot().await.unwrap();
+        assert!(tokio::time::timeout(Duration::from_millis(10), verifier.slot()).await.is_err());
+        assert_eq!(verifier.waiting.load(Ordering::SeqCst), 0);
+    }
+}
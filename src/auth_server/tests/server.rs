@@ -0,0 +1,210 @@
+use std::{collections::HashMap, net::SocketAddr, path::PathBuf};
+use auth_json::*;
+use auth_server::{Client, Server, framing::Format, protocol::*, udp};
+
+/// Write a small user file where this test won't trip over any other.
+fn user_file(name: &str) -> PathBuf {
+    let users: HashMap<String, User> = [
+        User::new("herbert", "password", LoginAction::Accept(Role::Admin)),
+        User::new("bob", "password", LoginAction::Accept(Role::User)),
+    ]
+    .into_iter()
+    .map(|user| (user.username.clone(), user))
+    .collect();
+    let path = std::env::temp_dir().join(format!("auth_server_{name}_{}.json", std::process::id()));
+    std::fs::write(&path, serde_json::to_string(&users).unwrap()).unwrap();
+    path
+}
+
+async fn start(name: &str, use_udp: bool) -> SocketAddr {
+    let server = Server::builder()
+        .bind("127.0.0.1:0")
+        .store(user_file(name))
+        .udp(use_udp)
+        .build()
+        .await
+        .unwrap();
+    let address = server.local_addr().unwrap();
+    tokio::spawn(server.serve());
+    address
+}
+
+#[tokio::test]
+async fn login_and_validate_token() {
+    let address = start("login", false).await;
+    let mut client = Client::connect(address, Format::Json).await.unwrap();
+
+    let request = Request::Login { username: "bob".to_string(), password: "password".to_string() };
+    let token = match client.call(&request).await.unwrap() {
+        Response::Login { action: Some(LoginAction::Accept(Role::User)), token: Some(token) } => token,
+        response => panic!("unexpected response {response:?}"),
+    };
+
+    match client.call(&Request::ValidateToken(token)).await.unwrap() {
+        Response::Session(Some(session)) => assert_eq!(session.username, "bob"),
+        response => panic!("unexpected response {response:?}"),
+    }
+}
+
+#[tokio::test]
+async fn admin_adds_a_user() {
+    let address = start("admin", false).await;
+    let mut client = Client::connect(address, Format::Bincode).await.unwrap();
+
+    let request = Request::Login { username: "herbert".to_string(), password: "password".to_string() };
+    let Response::Login { token: Some(token), .. } = client.call(&request).await.unwrap() else {
+        panic!("admin login failed");
+    };
+    let command = AdminCommand::AddUser {
+        username: "alice".to_string(),
+        password: "secret".to_string(),
+        action: LoginAction::Accept(Role::User),
+    };
+    assert!(matches!(
+        client.call(&Request::Admin { token, command }).await.unwrap(),
+        Response::Admin(AdminResponse::Done)
+    ));
+
+    let request = Request::Login { username: "alice".to_string(), password: "secret".to_string() };
+    assert!(matches!(
+        client.call(&request).await.unwrap(),
+        Response::Login { action: Some(LoginAction::Accept(Role::User)), .. }
+    ));
+}
+
+#[tokio::test]
+async fn deleting_a_user_ends_their_sessions() {
+    let address = start("delete", false).await;
+    let mut client = Client::connect(address, Format::Json).await.unwrap();
+
+    let mut tokens = Vec::new();
+    for username in ["herbert", "bob"] {
+        let request = Request::Login { username: username.to_string(), password: "password".to_string() };
+        let Response::Login { token: Some(token), .. } = client.call(&request).await.unwrap() else {
+            panic!("login failed");
+        };
+        tokens.push(token);
+    }
+
+    // Names are matched however they're typed, as they are at login
+    let command = AdminCommand::DeleteUser(" Bob".to_string());
+    assert!(matches!(
+        client.call(&Request::Admin { token: tokens[0].clone(), command }).await.unwrap(),
+        Response::Admin(AdminResponse::Done)
+    ));
+    assert!(matches!(client.call(&Request::ValidateToken(tokens[1].clone())).await.unwrap(), Response::Session(None)));
+}
+
+#[tokio::test]
+async fn change_password_ends_other_sessions() {
+    let address = start("password", false).await;
+    let mut client = Client::connect(address, Format::Json).await.unwrap();
+
+    let mut tokens = Vec::new();
+    for _ in 0..2 {
+        let request = Request::Login { username: "bob".to_string(), password: "password".to_string() };
+        let Response::Login { token: Some(token), .. } = client.call(&request).await.unwrap() else {
+            panic!("login failed");
+        };
+        tokens.push(token);
+    }
+
+    let request = Request::ChangePassword {
+        token: tokens[0].clone(),
+        current_password: "wrong".to_string(),
+        new_password: "hunter22".to_string(),
+    };
+    assert!(matches!(
+        client.call(&request).await.unwrap(),
+        Response::PasswordChange(PasswordChange::WrongPassword)
+    ));
+
+    // Protocol clients get no way around the password policy
+    let request = Request::ChangePassword {
+        token: tokens[0].clone(),
+        current_password: "password".to_string(),
+        new_password: "x".to_string(),
+    };
+    match client.call(&request).await.unwrap() {
+        Response::PasswordChange(PasswordChange::Rejected(problems)) => assert_eq!(problems.len(), 2),
+        response => panic!("unexpected response {response:?}"),
+    }
+
+    let request = Request::ChangePassword {
+        token: tokens[0].clone(),
+        current_password: "password".to_string(),
+        new_password: "hunter22".to_string(),
+    };
+    assert!(matches!(
+        client.call(&request).await.unwrap(),
+        Response::PasswordChange(PasswordChange::Done)
+    ));
+
+    assert!(matches!(client.call(&Request::ValidateToken(tokens[0].clone())).await.unwrap(), Response::Session(Some(_))));
+    assert!(matches!(client.call(&Request::ValidateToken(tokens[1].clone())).await.unwrap(), Response::Session(None)));
+
+    let request = Request::Login { username: "bob".to_string(), password: "hunter22".to_string() };
+    assert!(matches!(
+        client.call(&request).await.unwrap(),
+        Response::Login { action: Some(LoginAction::Accept(Role::User)), .. }
+    ));
+}
+
+#[tokio::test]
+async fn idle_clients_notice_the_server_hanging_up() {
+    let server = Server::builder()
+        .bind("127.0.0.1:0")
+        .store(user_file("idle"))
+        .idle_timeout(std::time::Duration::from_millis(100))
+        .build()
+        .await
+        .unwrap();
+    let address = server.local_addr().unwrap();
+    tokio::spawn(server.serve());
+
+    let mut client = Client::connect(address, Format::Json).await.unwrap();
+    assert!(client.check().await.is_ok());
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    assert!(client.check().await.is_err());
+    assert!(client.call(&Request::Ping).await.is_err());
+}
+
+#[tokio::test]
+async fn subscribers_see_logins() {
+    let address = start("events", false).await;
+    let mut admin = Client::connect(address, Format::Json).await.unwrap();
+    let request = Request::Login { username: "herbert".to_string(), password: "password".to_string() };
+    let Response::Login { token: Some(token), .. } = admin.call(&request).await.unwrap() else {
+        panic!("admin login failed");
+    };
+    assert!(matches!(admin.call(&Request::Subscribe { token }).await.unwrap(), Response::Subscribed));
+
+    let mut client = Client::connect(address, Format::Bincode).await.unwrap();
+    let request = Request::Login { username: "bob".to_string(), password: "wrong".to_string() };
+    client.call(&request).await.unwrap();
+
+    match admin.receive().await.unwrap() {
+        Response::Event(Event::Login { username, outcome: LoginOutcome::Failed }) => assert_eq!(username, "bob"),
+        response => panic!("unexpected response {response:?}"),
+    }
+}
+
+#[tokio::test]
+async fn udp_ping_over_ipv6() {
+    let server = Server::builder()
+        .bind("[::1]:0")
+        .store(user_file("udp6"))
+        .udp(true)
+        .build()
+        .await
+        .unwrap();
+    let address = server.local_addr().unwrap();
+    tokio::spawn(server.serve());
+    assert!(matches!(udp::call(address, Format::Json, &Request::Ping).await.unwrap(), Response::Pong));
+}
+
+#[tokio::test]
+async fn udp_ping() {
+    let address = start("udp", true).await;
+    assert!(matches!(udp::call(address, Format::Bincode, &Request::Ping).await.unwrap(), Response::Pong));
+}
@@ -0,0 +1,78 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::{delete, get, put};
+use axum::{Json, Router};
+use serde::Deserialize;
+use auth_json::{LoginAction, Role};
+use auth_server::protocol::{AdminCommand, AdminResponse, Request, Response, UserInfo};
+use crate::{errors::ApiError, extract::AdminUser, policy_check, AppState};
+
+/// The same user management API as rocket2, under /api/admin/users.
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/admin/users", get(list_users).post(create_user))
+        .route("/api/admin/users/{username}", delete(delete_user))
+        .route("/api/admin/users/{username}/role", put(set_role))
+        .route("/api/admin/users/{username}/password", put(reset_password))
+}
+
+/// Run an admin command as the logged-in admin. The `AdminUser` extractor has
+/// already turned away everyone else, but the auth server has the last word.
+async fn run(admin: AdminUser, state: &AppState, command: AdminCommand) -> Result<AdminResponse, ApiError> {
+    let mut client = state.pool.get().await?;
+    match client.call(&Request::Admin { token: admin.0.token, command }).await? {
+        Response::Admin(AdminResponse::NotAuthorized) => Err(ApiError::forbidden()),
+        Response::Admin(AdminResponse::UserExists) => Err(ApiError::new(StatusCode::CONFLICT, "That username is taken")),
+        Response::Admin(AdminResponse::UnknownUser) => Err(ApiError::new(StatusCode::NOT_FOUND, "There's no such user")),
+        Response::Admin(response) => Ok(response),
+        _ => Err(ApiError::internal()),
+    }
+}
+
+async fn list_users(admin: AdminUser, State(state): State<AppState>) -> Result<Json<Vec<UserInfo>>, ApiError> {
+    match run(admin, &state, AdminCommand::ListUsers).await? {
+        AdminResponse::Users(users) => Ok(Json(users)),
+        _ => Err(ApiError::internal()),
+    }
+}
+
+#[derive(Deserialize)]
+struct NewUser {
+    username: String,
+    password: String,
+    role: Role,
+}
+
+async fn create_user(admin: AdminUser, State(state): State<AppState>, Json(user): Json<NewUser>) -> Result<StatusCode, ApiError> {
+    let NewUser { username, password, role } = user;
+    policy_check(&username, &password)?;
+    run(admin, &state, AdminCommand::AddUser { username, password, action: LoginAction::Accept(role) }).await?;
+    Ok(StatusCode::CREATED)
+}
+
+async fn delete_user(admin: AdminUser, State(state): State<AppState>, Path(username): Path<String>) -> Result<StatusCode, ApiError> {
+    run(admin, &state, AdminCommand::DeleteUser(username)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+struct NewRole {
+    role: Role,
+}
+
+async fn set_role(admin: AdminUser, State(state): State<AppState>, Path(username): Path<String>, Json(role): Json<NewRole>) -> Result<StatusCode, ApiError> {
+    run(admin, &state, AdminCommand::SetAction { username, action: LoginAction::Accept(role.role) }).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+struct NewPassword {
+    password: String,
+}
+
+async fn reset_password(admin: AdminUser, State(state): State<AppState>, Path(username): Path<String>, Json(password): Json<NewPassword>) -> Result<StatusCode, ApiError> {
+    let password = password.password;
+    policy_check(&username, &password)?;
+    run(admin, &state, AdminCommand::SetPassword { username, password }).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,58 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use crate::RegisterResponse;
+
+/// The JSON body of most API errors - the same shape rocket2 sends, minus the request ID.
+#[derive(Serialize, Debug)]
+struct ErrorBody {
+    code: u16,
+    message: String,
+}
+
+/// Handlers return this for anything that isn't a success.
+#[derive(Debug)]
+pub enum ApiError {
+    Message { status: StatusCode, message: String },
+    /// A password broke the policy: 422, with every problem, in the body
+    /// rocket2 uses for them
+    Rejected(Vec<String>),
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: &str) -> Self {
+        Self::Message { status, message: message.to_string() }
+    }
+
+    pub fn unauthorized() -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, "You need to log in first")
+    }
+
+    pub fn forbidden() -> Self {
+        Self::new(StatusCode::FORBIDDEN, "You aren't allowed to do that")
+    }
+
+    /// The auth server said something we didn't expect.
+    pub fn internal() -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "Something went wrong on our side")
+    }
+}
+
+/// Failing to reach the auth server is the only way talking to it goes wrong,
+/// so `?` on a pool call answers 503.
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        println!("Auth server problem: {e}");
+        Self::new(StatusCode::SERVICE_UNAVAILABLE, "The login service isn't available right now")
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        match self {
+            ApiError::Message { status, message } => (status, Json(ErrorBody { code: status.as_u16(), message })).into_response(),
+            ApiError::Rejected(problems) => RegisterResponse::refused(StatusCode::UNPROCESSABLE_ENTITY, problems).into_response(),
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use axum::extract::FromRequestParts;
+use axum::http::{header::AUTHORIZATION, request::Parts};
+use auth_json::Role;
+use auth_server::jwt::Identity;
+use crate::{errors::ApiError, AppState};
+
+/// Anyone with a valid `Authorization: Bearer` token from `/api/login`.
+/// Handlers taking one answer 401 to everyone else.
+pub struct AnyUser(pub Identity);
+
+impl FromRequestParts<AppState> for AnyUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, ApiError> {
+        parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .and_then(|token| state.jwt.verify(token))
+            .map(AnyUser)
+            .ok_or_else(ApiError::unauthorized)
+    }
+}
+
+/// A logged-in admin. 401 for anyone not logged in, and 403 for users who aren't admins.
+pub struct AdminUser(pub Identity);
+
+impl FromRequestParts<AppState> for AdminUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, ApiError> {
+        let AnyUser(identity) = AnyUser::from_request_parts(parts, state).await?;
+        match identity.role {
+            Role::Admin => Ok(AdminUser(identity)),
+            _ => Err(ApiError::forbidden()),
+        }
+    }
+}
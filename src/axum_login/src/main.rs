@@ -0,0 +1,223 @@
+//! The rocket2 login API again, on axum and tower instead of Rocket, so the
+//! two can be compared side by side. Both talk to the auth server through the
+//! same connection pool from `auth_server::pool`, and share its bearer tokens
+//! and service account code too. There are no pages or
+//! cookies here: callers log in and send the bearer token they get back.
+
+use std::sync::Arc;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use auth_json::{DeniedReason, LoginAction, Role};
+use auth_server::pool::{Pool, PoolConfig};
+use auth_server::{policy, protocol::{AdminCommand, AdminResponse, PasswordChange, Request, Response, Session}};
+use auth_server::{jwt::{Identity, JwtConfig}, service::ServiceAccount};
+use errors::ApiError;
+use extract::AnyUser;
+
+// JSON error bodies
+mod errors;
+
+// Extractors for handlers that need someone logged in
+mod extract;
+
+// User management, for admins
+mod admin;
+
+#[derive(Parser)]
+#[command(about = "The rocket2 login API, served with axum")]
+struct Args {
+    /// Where to listen for HTTP requests
+    #[arg(long, default_value = "127.0.0.1:8001")]
+    bind: String,
+
+    /// Where the auth server is listening
+    #[arg(long, default_value = auth_server::DEFAULT_ADDRESS)]
+    backend: String,
+
+    /// How many connections to keep open to the auth server
+    #[arg(long, default_value_t = 4)]
+    pool_size: usize,
+
+    /// Signs the bearer tokens handed out at login. Change it for anything real!
+    #[arg(long, default_value = "classroom-secret-change-me")]
+    jwt_secret: String,
+
+    /// The admin account used to register new users
+    #[arg(long, default_value = "herbert")]
+    service_username: String,
+
+    #[arg(long, default_value = "password")]
+    service_password: String,
+}
+
+/// Everything the handlers share. Cheap to clone, as axum requires.
+#[derive(Clone)]
+pub struct AppState {
+    pool: Arc<Pool>,
+    jwt: Arc<JwtConfig>,
+    service: Arc<ServiceAccount>,
+}
+
+#[derive(Deserialize)]
+struct Login {
+    username: String,
+    password: String,
+}
+
+#[derive(Serialize)]
+struct LoginResponse {
+    success: bool,
+    role: Option<Role>,
+    /// Why a correct password still didn't let you in
+    reason: Option<DeniedReason>,
+    /// Send this as `Authorization: Bearer <token>`
+    token: Option<String>,
+}
+
+/// 200 with the user's role and a token if they're in, 423 (Locked) with the
+/// reason if the password was right but the account can't be used, and 401 otherwise.
+async fn login(State(state): State<AppState>, Json(user): Json<Login>) -> Result<(StatusCode, Json<LoginResponse>), ApiError> {
+    let mut client = state.pool.get().await?;
+    let request = Request::Login { username: user.username.clone(), password: user.password };
+    let (status, response) = match client.call(&request).await? {
+        Response::Login { action: Some(LoginAction::Accept(role)), token: Some(token) } => {
+            let identity = Identity { username: user.username, role: role.clone(), token };
+            (StatusCode::OK, LoginResponse { success: true, role: Some(role), reason: None, token: Some(state.jwt.issue(&identity)) })
+        }
+        Response::Login { action: Some(LoginAction::Denied(reason)), .. } => {
+            (StatusCode::LOCKED, LoginResponse { success: false, role: None, reason: Some(reason), token: None })
+        }
+        _ => (StatusCode::UNAUTHORIZED, LoginResponse { success: false, role: None, reason: None, token: None }),
+    };
+    Ok((status, Json(response)))
+}
+
+/// Bearer tokens can't be taken back, so there's nothing to do; it's here so
+/// clients written against rocket2 still work.
+async fn logout() -> StatusCode {
+    StatusCode::NO_CONTENT
+}
+
+/// Who your token says you are - as long as the auth server agrees the session is still live.
+async fn me(user: AnyUser, State(state): State<AppState>) -> Result<Json<Session>, ApiError> {
+    let mut client = state.pool.get().await?;
+    match client.call(&Request::ValidateToken(user.0.token)).await? {
+        Response::Session(Some(session)) => Ok(Json(session)),
+        _ => Err(ApiError::unauthorized()),
+    }
+}
+
+#[derive(Serialize)]
+pub struct RegisterResponse {
+    success: bool,
+    /// Why the registration was refused
+    problems: Vec<String>,
+}
+
+impl RegisterResponse {
+    pub fn refused(status: StatusCode, problems: Vec<String>) -> (StatusCode, Json<Self>) {
+        (status, Json(Self { success: false, problems }))
+    }
+}
+
+/// Create a new user account. 201 if it was created, 422 if the password isn't
+/// good enough, and 409 if the username is taken.
+async fn register(State(state): State<AppState>, Json(user): Json<Login>) -> Result<(StatusCode, Json<RegisterResponse>), ApiError> {
+    let Login { username, password } = user;
+    let problems = policy::password_problems(&username, &password);
+    if !problems.is_empty() {
+        return Ok(RegisterResponse::refused(StatusCode::UNPROCESSABLE_ENTITY, problems.into_iter().map(String::from).collect()));
+    }
+
+    let mut client = state.pool.get().await?;
+    let Ok(token) = state.service.login(&mut client).await else {
+        return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Registration isn't available right now"));
+    };
+    let command = AdminCommand::AddUser { username, password, action: LoginAction::Accept(Role::User) };
+    match client.call(&Request::Admin { token, command }).await? {
+        Response::Admin(AdminResponse::Done) => Ok((StatusCode::CREATED, Json(RegisterResponse { success: true, problems: Vec::new() }))),
+        Response::Admin(AdminResponse::UserExists) => Ok(RegisterResponse::refused(StatusCode::CONFLICT, vec!["That username is taken".to_string()])),
+        _ => Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Registration isn't available right now")),
+    }
+}
+
+#[derive(Deserialize)]
+struct NewPassword {
+    current_password: String,
+    new_password: String,
+}
+
+/// Change your own password, which also signs you out everywhere else. 204 if
+/// it worked, 422 if the new password isn't good enough, 403 if the current
+/// password is wrong and 401 if your session has ended. Problems are reported
+/// the same way as for `register`.
+async fn change_password(user: AnyUser, State(state): State<AppState>, Json(change): Json<NewPassword>) -> Result<StatusCode, ApiError> {
+    let NewPassword { current_password, new_password } = change;
+    // The auth server enforces the policy; checking here just saves a round trip
+    policy_check(&user.0.username, &new_password)?;
+
+    let mut client = state.pool.get().await?;
+    let request = Request::ChangePassword { token: user.0.token, current_password, new_password };
+    match client.call(&request).await? {
+        Response::PasswordChange(PasswordChange::Done) => Ok(StatusCode::NO_CONTENT),
+        Response::PasswordChange(PasswordChange::WrongPassword) => Err(ApiError::new(StatusCode::FORBIDDEN, "The current password is wrong")),
+        Response::PasswordChange(PasswordChange::NotLoggedIn) => Err(ApiError::unauthorized()),
+        Response::PasswordChange(PasswordChange::Rejected(problems)) => Err(ApiError::Rejected(problems)),
+        _ => Err(ApiError::internal()),
+    }
+}
+
+/// Every way `password` breaks the policy, as a 422 shaped like `register`'s.
+pub fn policy_check(username: &str, password: &str) -> Result<(), ApiError> {
+    let problems = policy::password_problems(username, password);
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(ApiError::Rejected(problems.into_iter().map(String::from).collect()))
+}
+
+/// 200 if the auth server answers a ping, 503 if not.
+async fn healthz(State(state): State<AppState>) -> StatusCode {
+    let mut client = match state.pool.get().await {
+        Ok(client) => client,
+        Err(..) => return StatusCode::SERVICE_UNAVAILABLE,
+    };
+    match client.call(&Request::Ping).await {
+        Ok(Response::Pong) => StatusCode::OK,
+        _ => StatusCode::SERVICE_UNAVAILABLE,
+    }
+}
+
+fn app(state: AppState) -> Router {
+    Router::new()
+        .route("/api/login", post(login))
+        .route("/api/logout", post(logout))
+        .route("/api/me", get(me))
+        .route("/api/register", post(register))
+        .route("/api/change-password", post(change_password))
+        .route("/healthz", get(healthz))
+        .merge(admin::routes())
+        .with_state(state)
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    let config = PoolConfig { address: args.backend, size: args.pool_size, ..Default::default() };
+    config.validate()?;
+    let state = AppState {
+        pool: Arc::new(Pool::new(config)),
+        jwt: Arc::new(JwtConfig { secret: args.jwt_secret, lifetime_secs: 3600 }),
+        service: Arc::new(ServiceAccount { username: args.service_username, password: args.service_password }),
+    };
+
+    let listener = tokio::net::TcpListener::bind(&args.bind).await?;
+    println!("Listening on http://{}", listener.local_addr()?);
+    axum::serve(listener, app(state)).await?;
+    Ok(())
+}
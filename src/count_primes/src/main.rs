@@ -1,19 +1,75 @@
+use std::time::{Duration, Instant};
+use clap::{error::ErrorKind, CommandFactory, Parser, ValueEnum};
+use primes::strategies::sieve;
+
 fn is_prime(n: u32) -> bool {
-    (2 ..= n/2).all(|i| n % i != 0 )
+    (2 ..= n/2).all(|i| !n.is_multiple_of(i))
  }
 
-const MAX:u32 = 200000;
+/// Count the primes in `2..max` by testing each number on its own.
+fn trial_division(max: u32) -> usize {
+    (2 .. max).filter(|n| is_prime(*n)).count()
+}
+
+/// Run one way of counting, timing it.
+fn run<T>(name: &str, max: T, count_primes: impl Fn(T) -> usize) -> (usize, Duration) {
+    let now = Instant::now();
+    let count = count_primes(max);
+    let time = now.elapsed();
+    println!("{name}: found {count} primes in {} seconds", time.as_secs_f32());
+    (count, time)
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Algorithm {
+    Trial,
+    Sieve,
+    /// Sieve a segment at a time on every thread; works far beyond u32
+    Segmented,
+    /// Run trial division and the sieve, check they agree and compare the times
+    Both,
+}
+
+#[derive(Parser)]
+struct Args {
+    /// Count the primes below this
+    #[arg(long, default_value_t = 200_000)]
+    max: u64,
+
+    #[arg(long, value_enum, default_value_t = Algorithm::Both)]
+    algorithm: Algorithm,
+
+    /// How many threads the segmented sieve splits the work between
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u32).range(1..))]
+    threads: u32,
+}
 
 fn main() {
-    let mut count = 0;
-    let now = std::time::Instant::now();
-    for i in 2 .. MAX {
-        if is_prime(i) {
-            count += 1;
-        }
+    let args = Args::parse();
+    match args.algorithm {
+        Algorithm::Trial => { run("Trial division", small(args.max), trial_division); }
+        Algorithm::Sieve => { run("Sieve", small(args.max), sieve); }
+        Algorithm::Segmented => { run("Segmented sieve", args.max, |max| primes::sieve::count_below(max, args.threads)); }
+        Algorithm::Both => compare(small(args.max)),
     }
-    let time = now.elapsed();
-    println!("Found {count} primes in {} seconds", time.as_secs_f32());
+}
+
+/// Only the segmented sieve goes past u32; the others stop here.
+fn small(max: u64) -> u32 {
+    u32::try_from(max).unwrap_or_else(|_| {
+        Args::command()
+            .error(ErrorKind::ValueValidation, format!("--max above {} needs --algorithm segmented", u32::MAX))
+            .exit()
+    })
+}
+
+fn compare(max: u32) {
+    let (trial_count, trial_time) = run("Trial division", max, trial_division);
+    let (sieve_count, sieve_time) = run("Sieve", max, sieve);
+    assert_eq!(trial_count, sieve_count, "the two methods disagree");
+
+    // A better algorithm usually beats more threads
+    println!("The sieve was {:.0}x faster", trial_time.as_secs_f64() / sieve_time.as_secs_f64());
 }
 
 #[cfg(test)]
@@ -29,4 +85,12 @@ mod test {
            [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97]
         );
      }
-}
\ No newline at end of file
+
+    #[test]
+    fn sieve_agrees_with_trial_division() {
+        for max in [0, 1, 2, 3, 4, 10, 100, 1000, 7919, 7920] {
+            assert_eq!(sieve(max), trial_division(max), "counting primes below {max}");
+            assert_eq!(primes::sieve::count_below(max.into(), 4), sieve(max), "counting primes below {max}");
+        }
+    }
+}
@@ -1,39 +1,57 @@
-use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use clap::Parser;
+use primes::timing::{print_timings, Timing};
 
 fn is_prime(n: u32) -> bool {
-    (2 ..= n/2).all(|i| n % i != 0 )
+    (2 ..= n/2).all(|i| !n.is_multiple_of(i))
  }
 
+#[derive(Parser)]
+struct Args {
+    /// Count the primes below this
+    #[arg(long, default_value_t = 200_000)]
+    max: u32,
+
+    /// How many threads to split the work between
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u32).range(1..))]
+    threads: u32,
+}
+
 fn main() {
-    const MAX: u32 = 200_000;
-    const N_THREADS: u32 = 8;
+    let args = Args::parse();
+    let (max, n_threads) = (args.max, args.threads);
 
     static COUNTER: AtomicUsize = AtomicUsize::new(0);
 
     // Hold thread handles
-    let mut threads = Vec::with_capacity(N_THREADS as usize);
+    let mut threads = Vec::with_capacity(n_threads as usize);
 
-    // Generate all the numbers we want to check
-    let group = MAX / N_THREADS;
+    // Split the numbers we want to check between the threads
+    let ranges = primes::partition(2 .. max, n_threads);
 
     let now = std::time::Instant::now();
+    let progress = primes::progress::Progress::new(max.saturating_sub(2).into());
 
-    for i in 0 .. N_THREADS {
-        let counter = i;
+    for range in ranges {
+        let done = progress.counter();
         threads.push(std::thread::spawn(move || {
-            let range = u32::max(2, counter*group) .. (i+1)*group;
-            COUNTER.fetch_add(
-                range.filter(|n| is_prime(*n)).count(),
-                std::sync::atomic::Ordering::Relaxed
-            );
+            let start = now.elapsed();
+            let primes = range.inspect(|_| { done.fetch_add(1, Ordering::Relaxed); }).filter(|n| is_prime(*n)).count();
+            COUNTER.fetch_add(primes, Ordering::Relaxed);
+            Timing { start, stop: now.elapsed(), primes }
         }));
     }
 
+    let mut timings = Vec::with_capacity(n_threads as usize);
     for thread in threads {
-        let _ = thread.join();
+        if let Ok(timing) = thread.join() {
+            timings.push(timing);
+        }
     }
-    
+
     let duration = now.elapsed();
-    println!("Found {} prime numbers in the range 2..{MAX}", COUNTER.load(std::sync::atomic::Ordering::Relaxed));
+    progress.finish();
+    print_timings(&timings);
+    println!("Found {} prime numbers in the range 2..{max}", COUNTER.load(Ordering::Relaxed));
     println!("Execution took {} seconds", duration.as_secs_f32());
  }
\ No newline at end of file
@@ -0,0 +1,96 @@
+use std::sync::{atomic::{AtomicUsize, Ordering}, mpsc, Mutex};
+use std::time::{Duration, Instant};
+use clap::Parser;
+
+fn is_prime(n: u32) -> bool {
+    (2 ..= n/2).all(|i| !n.is_multiple_of(i))
+ }
+
+#[derive(Parser)]
+struct Args {
+    /// Count the primes below this
+    #[arg(long, default_value_t = 200_000)]
+    max: u32,
+
+    /// How many threads to split the work between
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u32).range(1..))]
+    threads: u32,
+}
+
+/// Each worker adds its primes to one shared, locked Vec.
+fn with_mutex(max: u32, n_threads: u32) -> usize {
+    static PRIMES: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+    PRIMES.lock().unwrap().clear();
+
+    let threads: Vec<_> = primes::partition(2 .. max, n_threads)
+        .into_iter()
+        .map(|range| std::thread::spawn(move || {
+            let my_primes: Vec<u32> = range.filter(|n| is_prime(*n)).collect();
+            PRIMES.lock().unwrap().extend(my_primes);
+        }))
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    PRIMES.lock().unwrap().len()
+}
+
+/// Each worker adds its count to one shared atomic.
+fn with_atomic(max: u32, n_threads: u32) -> usize {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    COUNTER.store(0, Ordering::Relaxed);
+
+    let threads: Vec<_> = primes::partition(2 .. max, n_threads)
+        .into_iter()
+        .map(|range| std::thread::spawn(move || {
+            COUNTER.fetch_add(range.filter(|n| is_prime(*n)).count(), Ordering::Relaxed);
+        }))
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    COUNTER.load(Ordering::Relaxed)
+}
+
+/// Workers share nothing at all: they send each prime down a channel as they
+/// find it, and a collector thread gathers them up.
+fn with_channel(max: u32, n_threads: u32) -> usize {
+    let (tx, rx) = mpsc::channel::<u32>();
+
+    // The collector finishes when every sender has gone, i.e. every worker is done
+    let collector = std::thread::spawn(move || rx.iter().collect::<Vec<u32>>());
+
+    for range in primes::partition(2 .. max, n_threads) {
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            for n in range.filter(|n| is_prime(*n)) {
+                tx.send(n).unwrap();
+            }
+        });
+    }
+    // Otherwise the collector would wait for us forever
+    drop(tx);
+
+    collector.join().unwrap().len()
+}
+
+fn time(f: impl FnOnce() -> usize) -> (usize, Duration) {
+    let now = Instant::now();
+    let count = f();
+    (count, now.elapsed())
+}
+
+fn main() {
+    let args = Args::parse();
+    let results = [
+        ("Mutex", time(|| with_mutex(args.max, args.threads))),
+        ("Atomic", time(|| with_atomic(args.max, args.threads))),
+        ("Channel", time(|| with_channel(args.max, args.threads))),
+    ];
+
+    println!("{:<10}{:>8}{:>12}{:>16}", "Strategy", "Primes", "Seconds", "Numbers/second");
+    for (name, (count, duration)) in results {
+        let rate = args.max as f64 / duration.as_secs_f64();
+        println!("{name:<10}{count:>8}{:>12.4}{rate:>16.0}", duration.as_secs_f32());
+    }
+}
@@ -1,18 +1,98 @@
+use std::fs::File;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use clap::Parser;
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 
 fn is_prime(n: u32) -> bool {
-    (2 ..= n/2).all(|i| n % i != 0 )
+    (2 ..= n/2).all(|i| !n.is_multiple_of(i))
  }
 
-fn main() {
-    const MAX:u32 = 200000;
-    let now = std::time::Instant::now();
+/// One thread, one iterator chain.
+fn sequential(max: u32) -> usize {
+    (2..max)
+        .filter(|n| is_prime(*n))
+        .count()
+}
+
+/// The way we did it by hand: split the range, spawn a thread per piece and add up the answers.
+fn threaded(max: u32, n_threads: u32) -> usize {
+    let threads: Vec<_> = primes::partition(2 .. max, n_threads)
+        .into_iter()
+        .map(|range| std::thread::spawn(move || range.filter(|n| is_prime(*n)).count()))
+        .collect();
+    threads.into_iter().map(|t| t.join().unwrap()).sum()
+}
 
-    let count = (2..MAX)
+/// Rayon: one extra line.
+fn rayon(max: u32) -> usize {
+    (2..max)
         .into_par_iter()
         .filter(|n| is_prime(*n))
-        .count();
+        .count()
+}
+
+fn time(f: impl FnOnce() -> usize) -> (usize, Duration) {
+    let now = Instant::now();
+    let count = f();
+    (count, now.elapsed())
+}
+
+#[derive(Parser)]
+struct Args {
+    /// Count the primes below this
+    #[arg(long, default_value_t = 200_000)]
+    max: u32,
 
-    let duration = now.elapsed();
+    /// How many threads to use. Rayon picks one per CPU core if you don't say.
+    #[arg(long)]
+    threads: Option<usize>,
+
+    /// Instead, time every thread count in FIRST..LAST (both included) and
+    /// write the results to --csv
+    #[arg(long, value_name = "FIRST..LAST", value_parser = primes::sweep::parse_range)]
+    sweep: Option<RangeInclusive<u32>>,
+
+    /// Where --sweep writes its results
+    #[arg(long, default_value = "sweep.csv")]
+    csv: PathBuf,
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    if let Some(threads) = args.sweep {
+        primes::sweep::sweep(threads, &[
+            ("threads", &|n| { threaded(args.max, n); }),
+            // A pool of its own for each run, since the global one can't be resized
+            ("rayon", &|n| {
+                let pool = rayon::ThreadPoolBuilder::new().num_threads(n as usize).build().unwrap();
+                pool.install(|| rayon(args.max));
+            }),
+        ], File::create(&args.csv)?)?;
+        println!("Wrote {}", args.csv.display());
+        return Ok(());
+    }
+
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new().num_threads(threads).build_global().unwrap();
+    }
+    let n_threads = rayon::current_num_threads() as u32;
+
+    let (count, duration) = time(|| rayon(args.max));
     println!("Found {count} primes in {} seconds", duration.as_secs_f32());
+
+    // How does that compare with doing it ourselves?
+    let results = [
+        ("Sequential", time(|| sequential(args.max))),
+        ("Threads (by hand)", time(|| threaded(args.max, n_threads))),
+        ("Rayon", (count, duration)),
+    ];
+    println!();
+    println!("{:<20}{:>8}{:>12}", "Strategy", "Primes", "Seconds");
+    for (name, (count, duration)) in results {
+        println!("{name:<20}{count:>8}{:>12.4}", duration.as_secs_f32());
+    }
+    println!("Both parallel versions used {n_threads} threads");
+    Ok(())
 }
@@ -0,0 +1,45 @@
+use clap::Parser;
+
+fn is_prime(n: u32) -> bool {
+    (2 ..= n/2).all(|i| !n.is_multiple_of(i))
+ }
+
+#[derive(Parser)]
+struct Args {
+    /// Count the primes below this
+    #[arg(long, default_value_t = 200_000)]
+    max: u32,
+
+    /// How many threads to split the work between
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u32).range(1..))]
+    threads: u32,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    // Plain local variables. No statics, no Mutex, no Arc.
+    let numbers: Vec<u32> = (2 .. args.max).collect();
+    let mut results: Vec<Vec<u32>> = vec![Vec::new(); args.threads as usize];
+    let chunk_size = numbers.len().div_ceil(args.threads as usize).max(1);
+
+    let now = std::time::Instant::now();
+
+    // Every thread spawned on `scope` is joined before `scope` returns, so the
+    // threads can borrow `numbers` and each write into its own slot of
+    // `results` - the compiler knows neither will be gone before they finish.
+    std::thread::scope(|scope| {
+        for (chunk, result) in numbers.chunks(chunk_size).zip(results.iter_mut()) {
+            scope.spawn(move || {
+                *result = chunk.iter().copied().filter(|n| is_prime(*n)).collect();
+            });
+        }
+    });
+
+    let duration = now.elapsed();
+    for (i, primes) in results.iter().enumerate() {
+        println!("Thread {i} found {} primes", primes.len());
+    }
+    println!("Found {} prime numbers in the range 2..{}", results.iter().map(Vec::len).sum::<usize>(), args.max);
+    println!("Execution took {} seconds", duration.as_secs_f32());
+}
@@ -1,26 +1,39 @@
+use std::sync::atomic::Ordering;
 use std::thread::JoinHandle;
+use clap::Parser;
 
 fn is_prime(n: u32) -> bool {
-    (2 ..= n/2).all(|i| n % i != 0 )
+    (2 ..= n/2).all(|i| !n.is_multiple_of(i))
  }
 
+#[derive(Parser)]
+struct Args {
+    /// Count the primes below this
+    #[arg(long, default_value_t = 200_000)]
+    max: u32,
+
+    /// How many threads to split the work between
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u32).range(1..))]
+    threads: u32,
+}
+
 fn main() {
-    const MAX: u32 = 200_000;
-    const N_THREADS: u32 = 8;
+    let args = Args::parse();
+    let (max, n_threads) = (args.max, args.threads);
 
     // Hold thread handles
-    let mut threads: Vec<JoinHandle<Vec<u32>>> = Vec::with_capacity(N_THREADS as usize);
+    let mut threads: Vec<JoinHandle<Vec<u32>>> = Vec::with_capacity(n_threads as usize);
 
-    // Generate all the numbers we want to check
-    let group = MAX / N_THREADS;
+    // Split the numbers we want to check between the threads
+    let ranges = primes::partition(2 .. max, n_threads);
 
     let now = std::time::Instant::now();
+    let progress = primes::progress::Progress::new(max.saturating_sub(2).into());
 
-    for i in 0 .. N_THREADS {
-        let counter = i;
+    for range in ranges {
+        let done = progress.counter();
         threads.push(std::thread::spawn(move || {
-            let range = u32::max(2, counter*group) .. (i+1)*group;
-            range.filter(|n| is_prime(*n)).collect()
+            range.inspect(|_| { done.fetch_add(1, Ordering::Relaxed); }).filter(|n| is_prime(*n)).collect()
         }));
     }
 
@@ -34,6 +47,7 @@ fn main() {
     }
     
     let duration = now.elapsed();
-    println!("Found {} prime numbers in the range 2..{MAX}", primes.len());
+    progress.finish();
+    println!("Found {} prime numbers in the range 2..{max}", primes.len());
     println!("Execution took {} seconds", duration.as_secs_f32());
  }
\ No newline at end of file
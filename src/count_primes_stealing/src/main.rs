@@ -0,0 +1,132 @@
+use std::fs::File;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use clap::Parser;
+use primes::progress::Progress;
+
+fn is_prime(n: u32) -> bool {
+    (2 ..= n/2).all(|i| !n.is_multiple_of(i))
+ }
+
+#[derive(Parser)]
+struct Args {
+    /// Count the primes below this
+    #[arg(long, default_value_t = 200_000)]
+    max: u32,
+
+    /// How many threads to split the work between
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u32).range(1..))]
+    threads: u32,
+
+    /// How many numbers a thread takes at a time when work stealing
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u32).range(1..))]
+    block: u32,
+
+    /// Instead, time every thread count in FIRST..LAST (both included) and
+    /// write the results to --csv
+    #[arg(long, value_name = "FIRST..LAST", value_parser = primes::sweep::parse_range)]
+    sweep: Option<RangeInclusive<u32>>,
+
+    /// Where --sweep writes its results
+    #[arg(long, default_value = "sweep.csv")]
+    csv: PathBuf,
+}
+
+/// What one thread got done.
+#[derive(Default)]
+struct Report {
+    blocks: u32,
+    numbers: u32,
+    primes: usize,
+    busy: Duration,
+}
+
+/// Give each thread one contiguous slice of the range. Bigger numbers take
+/// longer to check, so the last thread has far more to do than the first.
+fn contiguous(max: u32, n_threads: u32) -> Vec<Report> {
+    let progress = Progress::new(max.saturating_sub(2).into());
+    let threads: Vec<_> = primes::partition(2 .. max, n_threads)
+        .into_iter()
+        .map(|range| {
+            let done = progress.counter();
+            std::thread::spawn(move || {
+                let start = Instant::now();
+                Report {
+                    blocks: 1,
+                    numbers: range.len() as u32,
+                    primes: range.inspect(|_| { done.fetch_add(1, Ordering::Relaxed); }).filter(|n| is_prime(*n)).count(),
+                    busy: start.elapsed(),
+                }
+            })
+        })
+        .collect();
+    threads.into_iter().map(|t| t.join().unwrap()).collect()
+}
+
+/// Let threads help themselves to small blocks from a shared cursor until
+/// there are none left. Threads that got cheap blocks come back for more, so
+/// everyone finishes at about the same time.
+fn stealing(max: u32, n_threads: u32, block: u32) -> Vec<Report> {
+    // 64 bits, so racing past u32::MAX can't wrap around to the start
+    static NEXT: AtomicU64 = AtomicU64::new(2);
+    NEXT.store(2, Ordering::Relaxed);
+
+    let progress = Progress::new(max.saturating_sub(2).into());
+    let threads: Vec<_> = (0 .. n_threads)
+        .map(|_| {
+            let done = progress.counter();
+            std::thread::spawn(move || {
+                let start = Instant::now();
+                let mut report = Report::default();
+                loop {
+                    let first = NEXT.fetch_add(block as u64, Ordering::Relaxed);
+                    if first >= max as u64 {
+                        break;
+                    }
+                    let range = first as u32 .. u32::min((first as u32).saturating_add(block), max);
+                    let numbers = range.len();
+                    report.blocks += 1;
+                    report.numbers += numbers as u32;
+                    report.primes += range.filter(|n| is_prime(*n)).count();
+                    done.fetch_add(numbers, Ordering::Relaxed);
+                }
+                report.busy = start.elapsed();
+                report
+            })
+        })
+        .collect();
+    threads.into_iter().map(|t| t.join().unwrap()).collect()
+}
+
+fn print(title: &str, reports: &[Report], elapsed: Duration) {
+    println!("{title}: {} primes in {} seconds", reports.iter().map(|r| r.primes).sum::<usize>(), elapsed.as_secs_f32());
+    println!("{:>8}{:>10}{:>12}{:>10}{:>12}", "Thread", "Blocks", "Numbers", "Primes", "Busy (s)");
+    for (i, report) in reports.iter().enumerate() {
+        println!("{i:>8}{:>10}{:>12}{:>10}{:>12.4}", report.blocks, report.numbers, report.primes, report.busy.as_secs_f32());
+    }
+    println!();
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+
+    if let Some(threads) = args.sweep {
+        primes::sweep::sweep(threads, &[
+            ("contiguous", &|n| { contiguous(args.max, n); }),
+            ("stealing", &|n| { stealing(args.max, n, args.block); }),
+        ], File::create(&args.csv)?)?;
+        println!("Wrote {}", args.csv.display());
+        return Ok(());
+    }
+
+    let now = Instant::now();
+    let reports = contiguous(args.max, args.threads);
+    print("Contiguous chunks", &reports, now.elapsed());
+
+    let now = Instant::now();
+    let reports = stealing(args.max, args.threads, args.block);
+    print("Work stealing", &reports, now.elapsed());
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use std::time::Duration;
+use clap::Parser;
+use tokio::{sync::oneshot, task::JoinHandle, time::{interval, Instant}};
+
+fn is_prime(n: u32) -> bool {
+    (2 ..= n/2).all(|i| !n.is_multiple_of(i))
+ }
+
+#[derive(Parser)]
+struct Args {
+    /// Count the primes below this
+    #[arg(long, default_value_t = 200_000)]
+    max: u32,
+
+    /// How many pieces to split the work into
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u32).range(1..))]
+    tasks: u32,
+}
+
+/// How often the ticker wants to run. Think of it as a server answering requests.
+const TICK: Duration = Duration::from_millis(10);
+
+/// Ticks every `TICK` until told to stop, returning the longest it ever went
+/// between ticks. If the runtime's threads are busy, it gets starved.
+fn start_ticker() -> (oneshot::Sender<()>, JoinHandle<Duration>) {
+    let (stop_tx, mut stop_rx) = oneshot::channel();
+    let ticker = tokio::spawn(async move {
+        let mut ticks = interval(TICK);
+        let mut last = Instant::now();
+        let mut worst = Duration::ZERO;
+        loop {
+            tokio::select! {
+                _ = ticks.tick() => {
+                    worst = worst.max(last.elapsed());
+                    last = Instant::now();
+                }
+                _ = &mut stop_rx => return worst,
+            }
+        }
+    });
+    (stop_tx, ticker)
+}
+
+/// Count the primes with `tokio::spawn`: the work runs on the runtime's own
+/// threads, and nothing else gets a turn until it's done.
+async fn on_the_runtime(max: u32, tasks: u32) -> usize {
+    let handles: Vec<_> = primes::partition(2 .. max, tasks)
+        .into_iter()
+        .map(|range| tokio::spawn(async move { range.filter(|n| is_prime(*n)).count() }))
+        .collect();
+    let mut count = 0;
+    for handle in handles {
+        count += handle.await.unwrap();
+    }
+    count
+}
+
+/// Count the primes with `spawn_blocking`: the work runs on Tokio's separate
+/// pool of blocking threads, leaving the runtime free for async tasks.
+async fn on_blocking_threads(max: u32, tasks: u32) -> usize {
+    let handles: Vec<_> = primes::partition(2 .. max, tasks)
+        .into_iter()
+        .map(|range| tokio::task::spawn_blocking(move || range.filter(|n| is_prime(*n)).count()))
+        .collect();
+    let mut count = 0;
+    for handle in handles {
+        count += handle.await.unwrap();
+    }
+    count
+}
+
+// Two worker threads, so the CPU-bound tasks can easily hog them all
+#[tokio::main(flavor = "multi_thread", worker_threads = 2)]
+async fn main() {
+    let args = Args::parse();
+
+    println!("{:<20}{:>8}{:>12}{:>20}", "Strategy", "Primes", "Seconds", "Worst tick gap (ms)");
+
+    let (stop, ticker) = start_ticker();
+    let now = Instant::now();
+    let count = on_the_runtime(args.max, args.tasks).await;
+    let elapsed = now.elapsed();
+    let _ = stop.send(());
+    let worst = ticker.await.unwrap();
+    println!("{:<20}{count:>8}{:>12.4}{:>20}", "tokio::spawn", elapsed.as_secs_f32(), worst.as_millis());
+
+    let (stop, ticker) = start_ticker();
+    let now = Instant::now();
+    let count = on_blocking_threads(args.max, args.tasks).await;
+    let elapsed = now.elapsed();
+    let _ = stop.send(());
+    let worst = ticker.await.unwrap();
+    println!("{:<20}{count:>8}{:>12.4}{:>20}", "spawn_blocking", elapsed.as_secs_f32(), worst.as_millis());
+
+    println!("The ticker wants to run every {}ms", TICK.as_millis());
+}
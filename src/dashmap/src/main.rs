@@ -1,39 +1,226 @@
+use clap::Parser;
 use dashmap::DashMap;
-use once_cell::sync::Lazy;
-use std::{thread, time::Duration};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::{thread, time::{Duration, Instant}};
 
-static MAP: Lazy<DashMap<usize, usize>> = Lazy::new(DashMap::new);
+/// A count per key that any number of threads can update and read.
+trait Counts: Sync {
+    fn increment(&self, key: usize);
+    fn get(&self, key: usize) -> Option<usize>;
+}
 
-fn main() {
-    let mut threads = Vec::new();
-
-    // Adder Threads
-    for i in 0..10 {
-        threads.push(thread::spawn(move || {
-            for _ in 0..100 {
-                if let Some(mut count) = MAP.get_mut(&i) {
-                    *count += 1;
-                } else {
-                    MAP.insert(i, 1);
-                }
-                std::thread::sleep(Duration::from_secs_f32(0.1));
-            }
-        }));
+impl Counts for DashMap<usize, usize> {
+    fn increment(&self, key: usize) {
+        // Checking with get_mut and then inserting is two steps, and another
+        // thread can insert in between - then we'd overwrite its count with
+        // 1. The entry holds the shard's lock from the check to the update.
+        self.entry(key).and_modify(|count| *count += 1).or_insert(1);
+    }
+
+    fn get(&self, key: usize) -> Option<usize> {
+        DashMap::get(self, &key).map(|count| *count)
+    }
+}
+
+/// Readers share the lock, but a writer waits for all of them to finish.
+impl Counts for RwLock<HashMap<usize, usize>> {
+    fn increment(&self, key: usize) {
+        *self.write().unwrap().entry(key).or_insert(0) += 1;
+    }
+
+    fn get(&self, key: usize) -> Option<usize> {
+        self.read().unwrap().get(&key).copied()
+    }
+}
+
+/// Everyone takes turns, readers included.
+impl Counts for Mutex<HashMap<usize, usize>> {
+    fn increment(&self, key: usize) {
+        *self.lock().unwrap().entry(key).or_insert(0) += 1;
+    }
+
+    fn get(&self, key: usize) -> Option<usize> {
+        self.lock().unwrap().get(&key).copied()
+    }
+}
+
+#[derive(Parser)]
+struct Args {
+    /// How many threads to run, readers and adders together
+    #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u32).range(2..))]
+    threads: u32,
+
+    /// How many increments each adder makes
+    #[arg(long, default_value_t = 100)]
+    ops: usize,
+
+    /// Milliseconds each thread sleeps after every operation. 0 to go flat out
+    #[arg(long, default_value_t = 100)]
+    sleep_ms: u64,
+
+    /// How to split the threads between readers and adders, as READERS:ADDERS
+    #[arg(long, default_value = "1:1", value_parser = parse_ratio)]
+    ratio: (u32, u32),
+
+    /// Don't print every count the readers see
+    #[arg(long)]
+    quiet: bool,
+
+    /// Instead, compare every thread using one key against each adder having
+    /// its own
+    #[arg(long)]
+    contention: bool,
+}
+
+fn parse_ratio(s: &str) -> Result<(u32, u32), String> {
+    let (readers, adders) = s.split_once(':').ok_or("expected READERS:ADDERS, like 1:1")?;
+    let readers = readers.parse().map_err(|e| format!("{readers:?}: {e}"))?;
+    let adders = adders.parse().map_err(|e| format!("{adders:?}: {e}"))?;
+    if adders == 0 {
+        return Err("there has to be at least one adder".to_string());
     }
+    Ok((readers, adders))
+}
+
+/// Split `threads` into (readers, adders) as close to `ratio` as we can,
+/// keeping at least one adder.
+fn split(threads: u32, (readers, adders): (u32, u32)) -> (u32, u32) {
+    let n_readers = (threads * readers / (readers + adders)).min(threads - 1);
+    (n_readers, threads - n_readers)
+}
+
+/// Run the adder and reader threads against `map`, returning how many
+/// operations they did and how long it took. With `hot_key`, every thread
+/// uses key 0; otherwise each adder has a key of its own.
+fn run(map: &impl Counts, args: &Args, hot_key: bool) -> (usize, Duration) {
+    let (n_readers, n_adders) = split(args.threads, args.ratio);
+    let sleep = Duration::from_millis(args.sleep_ms);
+    let stop = AtomicBool::new(false);
 
-    // Reader Threads
-    for i in 0..10 {
-        threads.push(thread::spawn(move || {
-            for _ in 0..20 {
-                if let Some(count) = MAP.get(&i) {
-                    println!("Count of {i}: {}", *count);
-                    std::thread::sleep(Duration::from_secs_f32(0.5));
+    let now = Instant::now();
+    let ops: usize = thread::scope(|scope| {
+        // Adder Threads
+        let adders: Vec<_> = (0 .. n_adders as usize)
+            .map(|i| scope.spawn(move || {
+                let key = if hot_key { 0 } else { i };
+                for _ in 0 .. args.ops {
+                    map.increment(key);
+                    thread::sleep(sleep);
                 }
+                args.ops
+            }))
+            .collect();
+
+        // Reader Threads, which keep going until the adders are done
+        let readers: Vec<_> = (0 .. n_readers as usize)
+            .map(|i| {
+                let stop = &stop;
+                scope.spawn(move || {
+                    let key = if hot_key { 0 } else { i % n_adders as usize };
+                    let mut reads = 0;
+                    while !stop.load(Ordering::Relaxed) {
+                        if let Some(count) = map.get(key) {
+                            if !args.quiet {
+                                println!("Count of {key}: {count}");
+                            }
+                        }
+                        reads += 1;
+                        thread::sleep(sleep);
+                    }
+                    reads
+                })
+            })
+            .collect();
+
+        let added: usize = adders.into_iter().map(|t| t.join().unwrap()).sum();
+        stop.store(true, Ordering::Relaxed);
+        added + readers.into_iter().map(|t| t.join().unwrap()).sum::<usize>()
+    });
+    (ops, now.elapsed())
+}
+
+fn ops_per_second((ops, duration): (usize, Duration)) -> f64 {
+    ops as f64 / duration.as_secs_f64()
+}
+
+/// Operations per second with a key per adder, then with one key for
+/// everyone. Checks no increments went missing on the shared key.
+fn contention<M: Counts>(new_map: impl Fn() -> M, args: &Args) -> (f64, f64) {
+    let spread = ops_per_second(run(&new_map(), args, false));
+
+    let map = new_map();
+    let hot = ops_per_second(run(&map, args, true));
+    let (_, n_adders) = split(args.threads, args.ratio);
+    assert_eq!(map.get(0), Some(n_adders as usize * args.ops), "lost some increments");
+
+    (spread, hot)
+}
+
+fn main() {
+    let args = Args::parse();
+    let (n_readers, n_adders) = split(args.threads, args.ratio);
+
+    if args.contention {
+        let results = [
+            ("DashMap", contention(DashMap::new, &args)),
+            ("RwLock<HashMap>", contention(|| RwLock::new(HashMap::new()), &args)),
+            ("Mutex<HashMap>", contention(|| Mutex::new(HashMap::new()), &args)),
+        ];
+
+        println!();
+        println!("{n_readers} readers and {n_adders} adders, {} increments each", args.ops);
+        println!("{:<20}{:>16}{:>16}{:>10}", "Map", "Spread ops/s", "One key ops/s", "Slowdown");
+        for (name, (spread, hot)) in results {
+            println!("{name:<20}{spread:>16.0}{hot:>16.0}{:>9.2}x", spread / hot);
+        }
+        return;
+    }
+
+    let results = [
+        ("DashMap", run(&DashMap::new(), &args, false)),
+        ("RwLock<HashMap>", run(&RwLock::new(HashMap::new()), &args, false)),
+        ("Mutex<HashMap>", run(&Mutex::new(HashMap::new()), &args, false)),
+    ];
+
+    println!();
+    println!("{n_readers} readers and {n_adders} adders, {} increments each", args.ops);
+    println!("{:<20}{:>10}{:>12}{:>14}", "Map", "Ops", "Seconds", "Ops/second");
+    for (name, result) in results {
+        let (ops, duration) = result;
+        println!("{name:<20}{ops:>10}{:>12.3}{:>14.0}", duration.as_secs_f64(), ops_per_second(result));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn splits_threads_by_ratio() {
+        assert_eq!(split(20, (1, 1)), (10, 10));
+        assert_eq!(split(20, (3, 1)), (15, 5));
+        assert_eq!(split(4, (0, 1)), (0, 4));
+        // Always leave an adder, or the readers would never be told to stop
+        assert_eq!(split(4, (1, 0)), (3, 1));
+    }
+
+    #[test]
+    fn no_increments_are_lost() {
+        let map = DashMap::new();
+        thread::scope(|scope| {
+            for _ in 0 .. 8 {
+                scope.spawn(|| (0 .. 1000).for_each(|_| map.increment(0)));
             }
-        }));
+        });
+        assert_eq!(Counts::get(&map, 0), Some(8000));
     }
 
-    for t in threads {
-        let _ = t.join();
+    #[test]
+    fn parses_ratios() {
+        assert_eq!(parse_ratio("3:1"), Ok((3, 1)));
+        assert!(parse_ratio("3").is_err());
+        assert!(parse_ratio("1:0").is_err());
     }
 }
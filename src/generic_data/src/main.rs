@@ -1,47 +1,45 @@
-use std::ops::Index;
-
-#[derive(Debug)]
-struct StableVec<T> {
-    data: Vec<Option<T>>
-}
-
-impl <T> StableVec<T> {
-    fn new() -> Self {
-        Self {
-            data: Vec::new(),
-        }
-    }
-
-    fn push(&mut self, item: T) -> usize {
-        let id = self.data.len();
-        self.data.push(Some(item));
-        id
-    }
-
-    fn remove(&mut self, id: usize) {
-        self.data[id] = None;
-    }
-
-    fn get(&self, id: usize) -> &Option<T> {
-        &self.data[id]
-    }
-}
-
-impl<T> Index<usize> for StableVec<T> {
-    type Output = Option<T>;
-    fn index(&self, index: usize) -> &Self::Output {
-        &self.data[index]
-    }
-}
+use stable_vec::StableVec;
 
 fn main() {
     let mut store = StableVec::<String>::new();
     let a = store.push("A".to_string());
     let b = store.push("B".to_string());
     let c = store.push("C".to_string());
-    store.remove(b);
+    println!("Removed {:?}", store.remove(b));
     println!("{:?}", store.get(a));
     println!("{:?}", store.get(b));
     println!("{:?}", store.get(c));
     println!("{:?}", store[c]);
+
+    // Items can be changed in place, or replaced
+    if let Some(a) = store.get_mut(a) {
+        a.push('!');
+    }
+    store[c] = "C again".to_string();
+    println!("{:?}", store.get(a));
+    println!("{:?}", store[c]);
+
+    // D takes B's old slot, but B's key doesn't find it
+    let d = store.push("D".to_string());
+    println!("{b:?} finds {:?}, {d:?} finds {:?}", store.get(b), store.get(d));
+
+    let a = store.remove(a).unwrap();
+    println!("Took {a} out");
+    for (_, item) in store.iter_mut() {
+        item.make_ascii_lowercase();
+    }
+    println!("Still here: {:?}", store.keys().collect::<Vec<_>>());
+    for (key, item) in store.iter() {
+        println!("{}: {item}", key.index());
+    }
+
+    store.retain(|_, item| item.starts_with('c'));
+    println!("{} left, room for {}", store.len(), store.capacity());
+    store.clear();
+    println!("Empty: {}", store.is_empty());
+
+    let mut store: StableVec<_> = ["X", "Y"].into_iter().collect();
+    let keys = store.push_all(["Z"]);
+    store.extend(["W"]);
+    println!("{} items, Z is at {:?}", store.len(), keys[0]);
 }
@@ -0,0 +1,101 @@
+use std::{fmt::Display, hash::Hash};
+use dashmap::DashMap;
+use crate::{ResultsError, Sensor, SensorStats};
+
+/// `HashSetData` for many threads at once: `add_reading` takes `&self`, so
+/// the store can be shared (with an `Arc`, or borrowed by scoped threads)
+/// without wrapping it in a lock. DashMap locks a shard at a time, so threads
+/// working on different keys rarely wait for each other.
+///
+/// Just the basics: no windows or timestamps.
+pub struct ConcurrentHashSetData<KEY, VALUE>
+where KEY: Eq + Hash + Display, VALUE: Sensor
+{
+    data: DashMap<KEY, Vec<VALUE>>,
+}
+
+impl <KEY, VALUE> ConcurrentHashSetData<KEY, VALUE>
+where KEY: Eq + Hash + Display, VALUE: Sensor
+{
+    pub fn new() -> Self {
+        Self {
+            data: DashMap::new(),
+        }
+    }
+
+    pub fn add_reading(&self, key: KEY, reading: VALUE) {
+        // Entry holds the shard's lock, so two threads adding the first
+        // readings for a key can't both create it
+        self.data.entry(key).or_default().push(reading);
+    }
+
+    /// Summarise the readings for `key`. Fails the same ways `HashSetData::stats` does.
+    pub fn stats(&self, key: &KEY) -> Result<SensorStats, ResultsError> {
+        match self.data.get(key) {
+            Some(readings) => summarise(key, &readings),
+            None => Err(ResultsError::NoReadings(key.to_string())),
+        }
+    }
+
+    pub fn print_results(&self) {
+        for entry in self.data.iter() {
+            // Not self.stats: looking a key up while iter() holds its shard's
+            // lock can deadlock if another thread is waiting to write to it
+            match summarise(entry.key(), entry.value()) {
+                Ok(stats) => println!("{} : {stats}", entry.key()),
+                Err(e) => println!("{e}"),
+            }
+        }
+    }
+}
+
+fn summarise<VALUE: Sensor>(key: &impl Display, readings: &[VALUE]) -> Result<SensorStats, ResultsError> {
+    SensorStats::new(&key.to_string(), readings.iter())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Data;
+
+    #[test]
+    fn many_threads_lose_no_readings() {
+        let store = ConcurrentHashSetData::new();
+        std::thread::scope(|scope| {
+            for thread in 0 .. 8 {
+                let store = &store;
+                scope.spawn(move || {
+                    for i in 0 .. 10_000 {
+                        // Every thread writes to every key, so they collide
+                        store.add_reading(i % 4, Data(thread));
+                    }
+                });
+            }
+        });
+        for key in 0 .. 4 {
+            let stats = store.stats(&key).unwrap();
+            assert_eq!(stats.count, 8 * 2_500);
+            // Each thread added the same number of its own id: 0..8 averages 3.5
+            assert!((stats.mean - 3.5).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn printing_while_others_write() {
+        let store = ConcurrentHashSetData::new();
+        std::thread::scope(|scope| {
+            for thread in 0 .. 4 {
+                let store = &store;
+                scope.spawn(move || {
+                    for i in 0 .. 2_000 {
+                        store.add_reading(i % 4, Data(thread));
+                    }
+                });
+            }
+            for _ in 0 .. 20 {
+                store.print_results();
+            }
+        });
+        assert_eq!(store.stats(&0).unwrap().count, 2_000);
+    }
+}
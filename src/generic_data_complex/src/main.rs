@@ -1,10 +1,21 @@
-use std::{collections::HashMap, hash::Hash, fmt::Debug};
+use std::{collections::{BTreeMap, HashMap, VecDeque}, hash::Hash, fmt::Debug, io::Write, path::Path};
+use std::time::SystemTime;
+use num_traits::ToPrimitive;
+use serde::Serialize;
+use thiserror::Error;
+
+// HashSetData for sharing between threads
+mod concurrent;
+use concurrent::ConcurrentHashSetData;
 
 #[derive(Debug)]
 struct HashSetData<KEY, VALUE> 
 where KEY: Eq + Hash + std::fmt::Display, VALUE: Debug + Sensor
 {
-    data: HashMap<KEY, Vec<VALUE>>
+    /// Each key's readings, and when they were taken, in the order they arrived
+    data: HashMap<KEY, VecDeque<(SystemTime, VALUE)>>,
+    /// Keep at most this many readings per key, dropping the oldest
+    window: Option<usize>,
 }
 
 impl <KEY, VALUE> HashSetData<KEY, VALUE> 
@@ -12,38 +23,396 @@ where KEY: Eq + Hash + std::fmt::Display, VALUE: Debug + Sensor
 {
     fn new() -> Self {
         Self {
-            data: HashMap::new()
+            data: HashMap::new(),
+            window: None,
+        }
+    }
+
+    /// Only keep the latest `window` readings for each key, so a sensor
+    /// that runs forever doesn't use ever more memory.
+    fn with_window(window: usize) -> Self {
+        assert!(window > 0, "a window has to hold at least one reading");
+        Self {
+            data: HashMap::new(),
+            window: Some(window),
         }
     }
 
+    /// Add a reading taken just now.
     fn add_reading(&mut self, key: KEY, reading: VALUE) {
+        self.add_reading_at(key, SystemTime::now(), reading);
+    }
+
+    /// Add a reading taken at `time`.
+    fn add_reading_at(&mut self, key: KEY, time: SystemTime, reading: VALUE) {
         if let Some(entry) = self.data.get_mut(&key) {
-            entry.push(reading);
+            entry.push_back((time, reading));
+            if self.window.is_some_and(|window| entry.len() > window) {
+                entry.pop_front();
+            }
         } else {
-            self.data.insert(key, vec![reading]);
+            self.data.insert(key, VecDeque::from([(time, reading)]));
         }
     }
 
+    /// Every key with readings.
+    fn keys(&self) -> impl Iterator<Item = &KEY> {
+        self.data.keys()
+    }
+
+    /// The readings we're keeping for `key`, oldest first. None at all if
+    /// the key is unknown.
+    fn readings(&self, key: &KEY) -> impl Iterator<Item = &VALUE> {
+        self.timed_readings(key).map(|(_, reading)| reading)
+    }
+
+    /// Like `readings`, with when each was taken.
+    fn timed_readings(&self, key: &KEY) -> impl Iterator<Item = (SystemTime, &VALUE)> {
+        self.data.get(key).into_iter().flatten().map(|(time, reading)| (*time, reading))
+    }
+
+    /// The most recently taken reading for `key`, and when it was taken.
+    fn latest(&self, key: &KEY) -> Option<(SystemTime, &VALUE)> {
+        self.timed_readings(key).max_by_key(|(time, _)| *time)
+    }
+
+    /// Forget `key`, handing back its readings.
+    fn remove_key(&mut self, key: &KEY) -> Option<Vec<VALUE>> {
+        Some(self.data.remove(key)?.into_iter().map(|(_, reading)| reading).collect())
+    }
+
+    /// Forget every key.
+    fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    /// The average of the readings we're keeping for `key`: the latest
+    /// `window` of them, or all of them if there's no window.
+    fn rolling_average(&self, key: &KEY) -> Result<f64, ResultsError> {
+        self.average(key)
+    }
+
+    /// The average of the readings for `key` taken from `start` up to, but
+    /// not including, `end`. `NoReadings` if there weren't any.
+    fn average_between(&self, key: &KEY, start: SystemTime, end: SystemTime) -> Result<f64, ResultsError> {
+        let readings = self.timed_readings(key).filter(|(time, _)| (start .. end).contains(time)).map(|(_, reading)| reading);
+        let name = key.to_string();
+        mean(&name, &to_f64s(&name, readings)?)
+    }
+
+    /// The average of every reading we're keeping for `key`, as an f64 so
+    /// it isn't rounded down to a whole number.
+    fn average(&self, key: &KEY) -> Result<f64, ResultsError> {
+        let name = key.to_string();
+        mean(&name, &to_f64s(&name, self.readings(key))?)
+    }
+
+    /// The average for every key, or the first key we couldn't average.
+    fn results(&self) -> Result<HashMap<KEY, f64>, ResultsError>
+    where KEY: Clone
+    {
+        self.keys().map(|key| Ok((key.clone(), self.average(key)?))).collect()
+    }
+
+    /// Summarise the readings for `key`. Fails the same ways `average` does.
+    fn stats(&self, key: &KEY) -> Result<SensorStats, ResultsError> {
+        SensorStats::new(&key.to_string(), self.readings(key))
+    }
+
+    /// Stats for every key, by the key's name, in order so exports are stable.
+    fn aggregates(&self) -> Result<BTreeMap<String, SensorStats>, ResultsError> {
+        self.keys().map(|key| Ok((key.to_string(), self.stats(key)?))).collect()
+    }
+
+    /// Stats for every key, as a JSON object keyed by name.
+    fn to_json(&self) -> Result<String, ExportError> {
+        Ok(serde_json::to_string_pretty(&self.aggregates()?)?)
+    }
+
+    /// Stats for every key, as a CSV file with a row per key.
+    fn write_csv(&self, path: impl AsRef<Path>) -> Result<(), ExportError> {
+        self.write_csv_to(csv::Writer::from_path(path)?)
+    }
+
+    fn write_csv_to(&self, mut csv: csv::Writer<impl Write>) -> Result<(), ExportError> {
+        // Check every key first, so a bad one doesn't leave half a file
+        let aggregates = self.aggregates()?;
+        csv.write_record(["key", "count", "min", "max", "mean", "std_dev", "median"])?;
+        for (key, stats) in aggregates {
+            csv.write_record([
+                key, stats.count.to_string(), stats.min.to_string(), stats.max.to_string(),
+                stats.mean.to_string(), stats.std_dev.to_string(), stats.median.to_string(),
+            ])?;
+        }
+        csv.flush()?;
+        Ok(())
+    }
+
     fn print_results(&self) {
-        for (key, value) in self.data.iter() {
-            let sum: i32 = value.iter().map(|r| r.reading()).sum();
-            let avg = sum / value.len() as i32;
-            println!("{key} : {avg}");
+        for key in self.keys() {
+            match self.stats(key) {
+                Ok(stats) => println!("{key} : {stats}"),
+                Err(e) => println!("{e}"),
+            }
         }
     }
 }
 
-trait Sensor {
-    fn reading(&self) -> i32;
+/// Why a key couldn't be averaged.
+#[derive(Debug, Error, PartialEq)]
+enum ResultsError {
+    #[error("{0} has no readings")]
+    NoReadings(String),
+
+    #[error("a reading for {0} isn't a number")]
+    NotANumber(String),
+
+    #[error("the readings for {0} average to more than an f64 can hold")]
+    Overflow(String),
+}
+
+/// Why stats couldn't be exported.
+#[derive(Debug, Error)]
+enum ExportError {
+    #[error(transparent)]
+    Results(#[from] ResultsError),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Every reading for `key` as an f64, so big readings can't overflow and
+/// averages aren't rounded down to a whole number.
+fn to_f64s<'a, VALUE: Sensor + 'a>(key: &str, readings: impl Iterator<Item = &'a VALUE>) -> Result<Vec<f64>, ResultsError> {
+    readings
+        .map(|r| r.reading().to_f64().filter(|v| !v.is_nan()).ok_or_else(|| ResultsError::NotANumber(key.to_string())))
+        .collect()
 }
 
+/// The mean of `key`'s readings.
+fn mean(key: &str, values: &[f64]) -> Result<f64, ResultsError> {
+    if values.is_empty() {
+        return Err(ResultsError::NoReadings(key.to_string()));
+    }
+    // Divide before adding up, so readings near f64::MAX don't overflow the sum
+    let count = values.len() as f64;
+    let mean: f64 = values.iter().map(|v| v / count).sum();
+    if !mean.is_finite() {
+        return Err(ResultsError::Overflow(key.to_string()));
+    }
+    Ok(mean)
+}
+
+/// A summary of one key's readings.
+#[derive(Debug, PartialEq, Serialize)]
+struct SensorStats {
+    count: usize,
+    min: f64,
+    max: f64,
+    mean: f64,
+    /// Population standard deviation: how far readings stray from the mean
+    std_dev: f64,
+    median: f64,
+}
+
+impl SensorStats {
+    /// Summarise `key`'s readings, refusing the same readings `average` does.
+    fn new<'a, VALUE: Sensor + 'a>(key: &str, readings: impl Iterator<Item = &'a VALUE>) -> Result<Self, ResultsError> {
+        let mut values = to_f64s(key, readings)?;
+        let mean = mean(key, &values)?;
+        values.sort_by(f64::total_cmp);
+        let count = values.len();
+        let variance: f64 = values.iter().map(|v| (v - mean).powi(2) / count as f64).sum();
+        // The middle value, or halfway between the two middle values
+        let median = if count % 2 == 1 {
+            values[count / 2]
+        } else {
+            values[count / 2 - 1] / 2.0 + values[count / 2] / 2.0
+        };
+        Ok(Self { count, min: values[0], max: values[count - 1], mean, std_dev: variance.sqrt(), median })
+    }
+}
+
+impl std::fmt::Display for SensorStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f, "{} readings, mean {:.2}, min {}, max {}, std dev {:.2}, median {}",
+            self.count, self.mean, self.min, self.max, self.std_dev, self.median
+        )
+    }
+}
+
+/// `Debug` is a supertrait so boxed sensors can still be printed.
+trait Sensor: Debug {
+    /// Any number that can be turned into an f64 for averaging
+    type Reading: ToPrimitive;
+    fn reading(&self) -> Self::Reading;
+    /// What the reading is measured in, such as "°C"
+    fn unit(&self) -> &'static str;
+    /// What kind of sensor this is, such as "temperature"
+    fn label(&self) -> &'static str;
+}
+
+/// A boxed sensor is a sensor too, so HashSetData can store
+/// `Box<dyn Sensor<Reading = f64>>` and mix different kinds.
+impl <S: Sensor + ?Sized> Sensor for Box<S> {
+    type Reading = S::Reading;
+    fn reading(&self) -> S::Reading {
+        (**self).reading()
+    }
+    fn unit(&self) -> &'static str {
+        (**self).unit()
+    }
+    fn label(&self) -> &'static str {
+        (**self).label()
+    }
+}
+
+/// Any kind of sensor with f64 readings. Naming the `Reading` type is what
+/// lets different sensors share a `Vec`: callers need to know what they get back.
+type AnySensor = Box<dyn Sensor<Reading = f64>>;
+
 #[derive(Debug)]
 struct Data(i32);
 
 impl Sensor for Data {
+    type Reading = i32;
     fn reading(&self) -> i32 {
         self.0
     }
+    fn unit(&self) -> &'static str {
+        ""
+    }
+    fn label(&self) -> &'static str {
+        "data"
+    }
+}
+
+#[derive(Debug)]
+struct Temperature(f64);
+
+impl Sensor for Temperature {
+    type Reading = f64;
+    fn reading(&self) -> f64 {
+        self.0
+    }
+    fn unit(&self) -> &'static str {
+        "°C"
+    }
+    fn label(&self) -> &'static str {
+        "temperature"
+    }
+}
+
+#[derive(Debug)]
+struct Counter(i64);
+
+impl Sensor for Counter {
+    type Reading = i64;
+    fn reading(&self) -> i64 {
+        self.0
+    }
+    fn unit(&self) -> &'static str {
+        "packets"
+    }
+    fn label(&self) -> &'static str {
+        "counter"
+    }
+}
+
+/// Hundredths, stored as a whole number: 1.5 is `Centi(150)`. A fixed-point
+/// number has no built-in conversions, so it provides its own.
+#[derive(Debug, Clone, Copy)]
+struct Centi(i32);
+
+impl ToPrimitive for Centi {
+    fn to_i64(&self) -> Option<i64> {
+        Some(i64::from(self.0 / 100))
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        self.to_i64()?.to_u64()
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        Some(f64::from(self.0) / 100.0)
+    }
+}
+
+#[derive(Debug)]
+struct Rainfall(Centi);
+
+impl Sensor for Rainfall {
+    type Reading = Centi;
+    fn reading(&self) -> Centi {
+        self.0
+    }
+    fn unit(&self) -> &'static str {
+        "mm"
+    }
+    fn label(&self) -> &'static str {
+        "rainfall"
+    }
+}
+
+/// Relative humidity, as a percentage.
+#[derive(Debug)]
+struct Humidity(f64);
+
+impl Sensor for Humidity {
+    type Reading = f64;
+    fn reading(&self) -> f64 {
+        self.0
+    }
+    fn unit(&self) -> &'static str {
+        "%"
+    }
+    fn label(&self) -> &'static str {
+        "humidity"
+    }
+}
+
+#[derive(Debug)]
+struct Pressure(f64);
+
+impl Sensor for Pressure {
+    type Reading = f64;
+    fn reading(&self) -> f64 {
+        self.0
+    }
+    fn unit(&self) -> &'static str {
+        "hPa"
+    }
+    fn label(&self) -> &'static str {
+        "pressure"
+    }
+}
+
+/// Static dispatch: a copy of this is compiled for each sensor type it's
+/// called with, and each copy calls that type's methods directly.
+fn describe<S: Sensor>(sensor: &S) -> String {
+    format!("{} {:.1} {}", sensor.label(), sensor.reading().to_f64().unwrap_or(f64::NAN), sensor.unit())
+}
+
+/// Dynamic dispatch: one copy, which looks up each method in the sensor's
+/// vtable while the program runs.
+fn describe_dyn(sensor: &dyn Sensor<Reading = f64>) -> String {
+    format!("{} {:.1} {}", sensor.label(), sensor.reading(), sensor.unit())
+}
+
+/// Store a mixed bag of sensors, each under its label.
+fn by_label(sensors: Vec<AnySensor>) -> HashSetData<&'static str, AnySensor> {
+    let mut readings = HashSetData::new();
+    for sensor in sensors {
+        readings.add_reading(sensor.label(), sensor);
+    }
+    readings
 }
 
 fn main() {
@@ -53,4 +422,277 @@ fn main() {
     readings.add_reading(1, Data(5));
     readings.add_reading(2, Data(1));
     readings.print_results();
+
+    let mut temperatures = HashSetData::<&str, Temperature>::new();
+    temperatures.add_reading("kitchen", Temperature(20.5));
+    temperatures.add_reading("kitchen", Temperature(21.25));
+    temperatures.print_results();
+
+    let mut counters = HashSetData::<&str, Counter>::new();
+    counters.add_reading("packets", Counter(i64::MAX));
+    counters.add_reading("packets", Counter(i64::MAX - 1));
+    counters.print_results();
+
+    let mut rainfall = HashSetData::<&str, Rainfall>::new();
+    rainfall.add_reading("garden", Rainfall(Centi(150)));
+    rainfall.add_reading("garden", Rainfall(Centi(225)));
+    rainfall.print_results();
+
+    // Averages that can fail say why, rather than printing nonsense
+    println!("{:?}", readings.results());
+    let mut huge = HashSetData::<&str, Temperature>::new();
+    huge.add_reading("sun", Temperature(f64::MAX));
+    huge.add_reading("sun", Temperature(f64::MAX));
+    println!("{:?}", huge.results());
+    huge.add_reading("sun", Temperature(f64::INFINITY));
+    match huge.results() {
+        Ok(results) => println!("{results:?}"),
+        Err(e) => println!("Couldn't average: {e}"),
+    }
+
+    // The same summaries, for other programs to read
+    println!("{}", temperatures.to_json().unwrap());
+    let path = std::env::temp_dir().join("temperatures.csv");
+    temperatures.write_csv(&path).unwrap();
+    println!("Wrote {}", path.display());
+
+    // Take a key out and look at what it had
+    let removed = readings.remove_key(&1).unwrap_or_default();
+    println!("Removed key 1, which had {removed:?}");
+    for key in readings.keys() {
+        println!("Key {key} has {:?}", readings.readings(key).collect::<Vec<_>>());
+    }
+    readings.clear();
+    println!("After clearing, {} keys are left", readings.keys().count());
+
+    // Readings from the last hour, some of them older
+    let now = SystemTime::now();
+    let minutes_ago = |minutes: u64| now - std::time::Duration::from_secs(minutes * 60);
+    let mut timed = HashSetData::<&str, Temperature>::new();
+    timed.add_reading_at("porch", minutes_ago(90), Temperature(12.0));
+    timed.add_reading_at("porch", minutes_ago(40), Temperature(15.0));
+    timed.add_reading_at("porch", minutes_ago(5), Temperature(17.0));
+    println!("Porch over the last hour: {:?}", timed.average_between(&"porch", minutes_ago(60), now));
+    if let Some((time, reading)) = timed.latest(&"porch") {
+        let age = now.duration_since(time).unwrap_or_default();
+        println!("Porch was {reading:?}, {} minutes ago", age.as_secs() / 60);
+    }
+
+    // Four threads feeding one store, without a Mutex
+    let shared = ConcurrentHashSetData::<usize, Data>::new();
+    std::thread::scope(|scope| {
+        for thread in 0 .. 4 {
+            let shared = &shared;
+            scope.spawn(move || {
+                for i in 0 .. 1000 {
+                    shared.add_reading(i % 2, Data(thread));
+                }
+            });
+        }
+    });
+    shared.print_results();
+    println!("Key 0 alone: {:?}", shared.stats(&0).map(|stats| stats.count));
+
+    // Every HashSetData so far held one kind of sensor, chosen at compile time.
+    // Boxing them lets one store hold whatever a weather station sends.
+    let station: Vec<AnySensor> = vec![
+        Box::new(Temperature(18.5)),
+        Box::new(Humidity(64.0)),
+        Box::new(Pressure(1013.2)),
+        Box::new(Temperature(19.5)),
+        Box::new(Pressure(1011.8)),
+    ];
+    println!("{} / {}", describe(&Temperature(18.5)), describe_dyn(station[1].as_ref()));
+    let station = by_label(station);
+    for label in station.keys() {
+        let unit = station.readings(label).next().map_or("", |sensor| sensor.unit());
+        println!("{label}: {:.1} {unit}", station.rolling_average(label).unwrap_or_default());
+    }
+
+    let mut recent = HashSetData::<&str, Data>::with_window(3);
+    for reading in [1, 2, 3, 10, 20, 30] {
+        recent.add_reading("sensor", Data(reading));
+        println!("After {reading}, the last 3 average {:?}", recent.rolling_average(&"sensor").unwrap());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn readings(values: &[i32]) -> HashSetData<&'static str, Data> {
+        let mut readings = HashSetData::new();
+        for value in values {
+            readings.add_reading("key", Data(*value));
+        }
+        readings
+    }
+
+    #[test]
+    fn stats_for_a_known_dataset() {
+        // The textbook example: mean 5, standard deviation exactly 2
+        let stats = readings(&[2, 4, 4, 4, 5, 5, 7, 9]).stats(&"key").unwrap();
+        assert_eq!(stats, SensorStats { count: 8, min: 2.0, max: 9.0, mean: 5.0, std_dev: 2.0, median: 4.5 });
+    }
+
+    #[test]
+    fn median_of_an_odd_count_is_the_middle() {
+        let stats = readings(&[9, -3, 1]).stats(&"key").unwrap();
+        assert_eq!(stats.median, 1.0);
+        assert!((stats.mean - 7.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn single_reading() {
+        let stats = readings(&[7]).stats(&"key").unwrap();
+        assert_eq!(stats, SensorStats { count: 1, min: 7.0, max: 7.0, mean: 7.0, std_dev: 0.0, median: 7.0 });
+    }
+
+    #[test]
+    fn windows_keep_the_latest_readings() {
+        let mut readings = HashSetData::with_window(3);
+        for value in 1 ..= 5 {
+            readings.add_reading("key", Data(value));
+        }
+        assert_eq!(readings.data["key"].len(), 3);
+        assert_eq!(readings.rolling_average(&"key"), Ok(4.0));
+        assert_eq!(readings.stats(&"key").unwrap().min, 3.0);
+    }
+
+    #[test]
+    fn without_a_window_everything_is_kept() {
+        let readings = readings(&[1, 2, 3, 4, 5]);
+        assert_eq!(readings.data["key"].len(), 5);
+        assert_eq!(readings.rolling_average(&"key"), Ok(3.0));
+    }
+
+    #[test]
+    fn keys_and_readings() {
+        let mut readings = readings(&[1, 2]);
+        readings.add_reading("other", Data(3));
+        let mut keys: Vec<_> = readings.keys().copied().collect();
+        keys.sort();
+        assert_eq!(keys, ["key", "other"]);
+        assert_eq!(readings.readings(&"key").map(|r| r.0).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(readings.readings(&"missing").count(), 0);
+    }
+
+    #[test]
+    fn removing_and_clearing() {
+        let mut readings = readings(&[1, 2]);
+        readings.add_reading("other", Data(3));
+        assert_eq!(readings.remove_key(&"key").unwrap().iter().map(|r| r.0).collect::<Vec<_>>(), [1, 2]);
+        assert!(readings.remove_key(&"key").is_none());
+        assert_eq!(readings.stats(&"key"), Err(ResultsError::NoReadings("key".to_string())));
+
+        readings.clear();
+        assert_eq!(readings.keys().count(), 0);
+    }
+
+    #[test]
+    fn exports_json() {
+        let mut readings = readings(&[1, 3]);
+        readings.add_reading("other", Data(5));
+        let json: serde_json::Value = serde_json::from_str(&readings.to_json().unwrap()).unwrap();
+        assert_eq!(json["key"]["mean"], 2.0);
+        assert_eq!(json["other"]["count"], 1);
+    }
+
+    #[test]
+    fn exports_csv() {
+        let mut readings = readings(&[1, 3]);
+        readings.add_reading("a, b", Data(5));
+        let mut out = Vec::new();
+        readings.write_csv_to(csv::Writer::from_writer(&mut out)).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\
+key,count,min,max,mean,std_dev,median
+\"a, b\",1,5,5,5,0,5
+key,2,1,3,2,1,2
+");
+    }
+
+    /// `secs` seconds after the epoch
+    fn at(secs: u64) -> SystemTime {
+        std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn averages_between_times() {
+        let mut readings = HashSetData::new();
+        readings.add_reading_at("key", at(10), Data(1));
+        readings.add_reading_at("key", at(20), Data(3));
+        readings.add_reading_at("key", at(30), Data(8));
+
+        assert_eq!(readings.average_between(&"key", at(10), at(30)), Ok(2.0));
+        assert_eq!(readings.average_between(&"key", at(15), at(31)), Ok(5.5));
+        assert_eq!(readings.average_between(&"key", at(31), at(40)), Err(ResultsError::NoReadings("key".to_string())));
+    }
+
+    #[test]
+    fn latest_goes_by_time_not_arrival() {
+        let mut readings = HashSetData::new();
+        readings.add_reading_at("key", at(20), Data(2));
+        readings.add_reading_at("key", at(10), Data(1));
+        assert_eq!(readings.latest(&"key").map(|(time, r)| (time, r.0)), Some((at(20), 2)));
+        assert!(readings.latest(&"missing").is_none());
+    }
+
+    #[test]
+    fn averages_are_not_truncated() {
+        let mut readings = readings(&[1, 2]);
+        readings.add_reading("other", Data(-3));
+        let results = readings.results().unwrap();
+        assert_eq!(results["key"], 1.5);
+        assert_eq!(results["other"], -3.0);
+    }
+
+    #[test]
+    fn huge_readings_average_without_overflowing() {
+        let mut readings = HashSetData::new();
+        readings.add_reading("key", Temperature(f64::MAX));
+        readings.add_reading("key", Temperature(f64::MAX));
+        assert_eq!(readings.average(&"key"), Ok(f64::MAX));
+        assert_eq!(readings.rolling_average(&"key"), Ok(f64::MAX));
+        let stats = readings.stats(&"key").unwrap();
+        assert_eq!((stats.mean, stats.median, stats.std_dev), (f64::MAX, f64::MAX, 0.0));
+    }
+
+    #[test]
+    fn averages_that_cannot_be_computed() {
+        let mut readings = HashSetData::new();
+        readings.add_reading("hot", Temperature(f64::INFINITY));
+        readings.add_reading("broken", Temperature(f64::NAN));
+        assert_eq!(readings.average(&"hot"), Err(ResultsError::Overflow("hot".to_string())));
+        assert_eq!(readings.average(&"broken"), Err(ResultsError::NotANumber("broken".to_string())));
+        assert_eq!(readings.average(&"missing"), Err(ResultsError::NoReadings("missing".to_string())));
+        assert!(readings.results().is_err());
+        // Stats refuse the same readings, rather than quietly leaving them out
+        assert_eq!(readings.stats(&"broken"), Err(ResultsError::NotANumber("broken".to_string())));
+        assert_eq!(readings.stats(&"hot"), Err(ResultsError::Overflow("hot".to_string())));
+        assert!(readings.to_json().is_err());
+    }
+
+    #[test]
+    fn mixed_sensors_are_averaged_by_label() {
+        let station = by_label(vec![
+            Box::new(Temperature(18.0)),
+            Box::new(Humidity(60.0)),
+            Box::new(Temperature(20.0)),
+        ]);
+        assert_eq!(station.rolling_average(&"temperature"), Ok(19.0));
+        assert_eq!(station.rolling_average(&"humidity"), Ok(60.0));
+        assert_eq!(station.readings(&"humidity").next().unwrap().unit(), "%");
+    }
+
+    #[test]
+    fn both_dispatches_describe_alike() {
+        let sensor = Pressure(1000.0);
+        assert_eq!(describe(&sensor), describe_dyn(&sensor));
+        assert_eq!(describe(&sensor), "pressure 1000.0 hPa");
+    }
+
+    #[test]
+    fn no_stats_for_unknown_keys() {
+        assert_eq!(readings(&[1]).stats(&"other"), Err(ResultsError::NoReadings("other".to_string())));
+    }
 }
@@ -1,24 +1,71 @@
-use std::time::Duration;
+use std::{sync::Mutex, time::{Duration, Instant}};
 
-use tokio::{join, spawn, task::spawn_blocking};
+use tokio::{join, spawn, task::{spawn_blocking, JoinSet}, time::sleep};
+use tokio_util::sync::CancellationToken;
 
-async fn hello(n: u32) {
+/// Children go in the `JoinSet`, so main can wait for them - and see what they return.
+async fn hello(n: u32, children: &Mutex<JoinSet<Duration>>) {
     println!("Hello {n}");
     if n < 10 {
-        spawn(hello_child(n*10));
+        children.lock().unwrap().spawn(hello_child(n*10));
     }
 }
 
-async fn hello_child(n: u32) {
+/// Returns how long the child took.
+async fn hello_child(n: u32) -> Duration {
+    let start = Instant::now();
     println!("Hello again {n}");
     let _ = spawn_blocking(|| std::thread::sleep(Duration::from_secs(1))).await;
     tokio::time::sleep(Duration::from_secs(1)).await;
+    start.elapsed()
+}
+
+/// Do some "work" that takes `work` to finish, unless it takes longer than
+/// `timeout` or someone cancels it first. Whichever happens first wins.
+async fn race(name: &str, work: Duration, timeout: Duration, cancel: CancellationToken) {
+    tokio::select! {
+        _ = sleep(work) => println!("{name}: finished"),
+        _ = sleep(timeout) => println!("{name}: timed out"),
+        _ = cancel.cancelled() => println!("{name}: cancelled"),
+    }
+    // The branches that lost were dropped, which stops them in their tracks
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let children = Mutex::new(JoinSet::new());
     join!(
-        hello(1), hello(2), hello(3), hello(4)
+        hello(1, &children), hello(2, &children), hello(3, &children), hello(4, &children)
     );
+
+    // Without this, main would return - and drop the children - before they finish
+    let mut children = children.into_inner().unwrap();
+    let (mut finished, mut failed, mut slowest) = (0, 0, Duration::ZERO);
+    while let Some(result) = children.join_next().await {
+        match result {
+            Ok(took) => {
+                finished += 1;
+                slowest = slowest.max(took);
+            }
+            Err(..) => failed += 1,
+        }
+    }
+    println!("{finished} children finished, {failed} failed; the slowest took {}ms", slowest.as_millis());
+
+    let cancel = CancellationToken::new();
+    let races = spawn({
+        let cancel = cancel.clone();
+        async move {
+            join!(
+                race("Quick job", Duration::from_millis(100), Duration::from_secs(2), cancel.clone()),
+                race("Slow job", Duration::from_secs(5), Duration::from_millis(300), cancel.clone()),
+                race("Unwanted job", Duration::from_secs(5), Duration::from_secs(5), cancel),
+            )
+        }
+    });
+    sleep(Duration::from_millis(500)).await;
+    cancel.cancel();
+    races.await?;
+
     Ok(())
 }
@@ -0,0 +1,29 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use primes::fast;
+
+/// Where each batch of 1,000 numbers starts.
+const STARTS: [u64; 3] = [100_000, 10_000_000, 1_000_000_000_000];
+
+fn is_prime(c: &mut Criterion) {
+    let mut group = c.benchmark_group("is_prime");
+    for start in STARTS {
+        let numbers = start .. start + 1_000;
+        // The examples' version takes minutes on anything bigger
+        if start == STARTS[0] {
+            let start = start as u32;
+            group.bench_with_input(BenchmarkId::new("examples", start), &start, |b, &start| {
+                b.iter(|| (start .. start + 1_000).filter(|n| primes::is_prime(*n)).count())
+            });
+        }
+        group.bench_with_input(BenchmarkId::new("trial_division", start), &numbers, |b, numbers| {
+            b.iter(|| numbers.clone().filter(|n| fast::trial_division(*n)).count())
+        });
+        group.bench_with_input(BenchmarkId::new("miller_rabin", start), &numbers, |b, numbers| {
+            b.iter(|| numbers.clone().filter(|n| fast::miller_rabin(*n)).count())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, is_prime);
+criterion_main!(benches);
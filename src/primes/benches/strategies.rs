@@ -0,0 +1,44 @@
+use std::time::Duration;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use primes::strategies;
+
+const SIZES: [u32; 2] = [10_000, 50_000];
+const THREADS: [u32; 4] = [1, 2, 4, 8];
+
+/// Counts the primes below `max` using `threads` threads.
+type Counter = fn(u32, u32) -> usize;
+
+fn single_threaded(c: &mut Criterion) {
+    let mut group = c.benchmark_group("single_threaded");
+    for max in SIZES {
+        group.bench_with_input(BenchmarkId::new("trial_division", max), &max, |b, &max| b.iter(|| strategies::sequential(max)));
+        group.bench_with_input(BenchmarkId::new("sieve", max), &max, |b, &max| b.iter(|| strategies::sieve(max)));
+    }
+    group.finish();
+}
+
+fn threaded(c: &mut Criterion) {
+    let counters: [(&str, Counter); 4] = [
+        ("mutex", strategies::mutex),
+        ("atomic", strategies::atomic),
+        ("channel", strategies::channel),
+        ("rayon", strategies::rayon),
+    ];
+    for max in SIZES {
+        let mut group = c.benchmark_group(format!("threaded/{max}"));
+        for (name, count) in counters {
+            for threads in THREADS {
+                group.bench_with_input(BenchmarkId::new(name, threads), &threads, |b, &threads| b.iter(|| count(max, threads)));
+            }
+        }
+        group.finish();
+    }
+}
+
+criterion_group! {
+    name = benches;
+    // Trial division is slow, so take fewer, shorter samples than usual
+    config = Criterion::default().sample_size(10).warm_up_time(Duration::from_secs(1)).measurement_time(Duration::from_secs(3));
+    targets = single_threaded, threaded
+}
+criterion_main!(benches);
@@ -0,0 +1,115 @@
+//! Primality tests that don't take all day.
+//!
+//! The examples test `2..=n/2`, which is easy to read and very slow: almost
+//! all of those divisions are wasted. These are what you'd use for real.
+
+/// Trial division, done properly. A composite `n` has a factor no bigger than
+/// `sqrt(n)`, so stop there. Every prime above 3 is one either side of a
+/// multiple of 6 (the rest are divisible by 2 or 3), so only try those.
+pub fn trial_division(n: u64) -> bool {
+    if n < 4 {
+        return n >= 2;
+    }
+    if n.is_multiple_of(2) || n.is_multiple_of(3) {
+        return false;
+    }
+    let mut i = 5;
+    while i <= n / i {
+        if n.is_multiple_of(i) || n.is_multiple_of(i + 2) {
+            return false;
+        }
+        i += 6;
+    }
+    true
+}
+
+/// `a * b % m`, without overflowing.
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (u128::from(a) * u128::from(b) % u128::from(m)) as u64
+}
+
+/// `base.pow(exp) % m`, by repeated squaring.
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+/// The Miller-Rabin test. On its own it's probabilistic, but checking these
+/// twelve bases is known to give the right answer for every `u64`.
+pub fn miller_rabin(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    for p in BASES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+
+    // n - 1 = d * 2^s, with d odd
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    BASES.iter().all(|&a| {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        for _ in 1 .. s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+/// Whichever of the two is quicker for `n`: trial division wins while
+/// `sqrt(n)` is small, Miller-Rabin after that. They break even around 10^7.
+pub fn is_prime(n: u64) -> bool {
+    if n < 1 << 24 {
+        trial_division(n)
+    } else {
+        miller_rabin(n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn agree_with_the_examples() {
+        for n in 0 .. 20_000u32 {
+            let expected = crate::is_prime(n) && n >= 2;
+            assert_eq!(trial_division(n.into()), expected, "{n}");
+            assert_eq!(miller_rabin(n.into()), expected, "{n}");
+        }
+    }
+
+    #[test]
+    fn agree_with_each_other_on_big_numbers() {
+        for n in (1 << 40) .. (1 << 40) + 5_000 {
+            assert_eq!(miller_rabin(n), trial_division(n), "{n}");
+        }
+    }
+
+    #[test]
+    fn known_large_values() {
+        // The largest u64 prime, a Carmichael number and a strong pseudoprime to bases 2, 3, 5 and 7
+        assert!(miller_rabin(18_446_744_073_709_551_557));
+        assert!(!miller_rabin(u64::MAX));
+        assert!(!miller_rabin(561));
+        assert!(!miller_rabin(3_215_031_751));
+        assert!(is_prime(1_000_000_007));
+    }
+}
@@ -0,0 +1,99 @@
+//! Helpers shared by the prime counting examples.
+
+use std::ops::Range;
+
+// Every counting strategy, for benchmarking
+pub mod strategies;
+
+// Counting primes beyond u32 with a segmented sieve
+pub mod sieve;
+
+// Quicker primality tests: a 6k±1 wheel and Miller-Rabin
+pub mod fast;
+
+// A progress bar for the threaded counters
+pub mod progress;
+
+// Timing every thread count, for plotting
+pub mod sweep;
+
+// How long each thread was busy
+pub mod timing;
+
+/// Trial division, as in the course's examples: slow, but obviously correct.
+/// See [`fast`] for tests that finish sooner.
+pub fn is_prime(n: u32) -> bool {
+    (2 ..= n/2).all(|i| !n.is_multiple_of(i))
+}
+
+/// Split `range` into `parts` contiguous pieces, in order, that between them
+/// cover every number exactly once. Sizes differ by at most one; if there are
+/// more parts than numbers, the extra parts are empty. Works on `u32` and
+/// `u64` ranges.
+///
+/// ```
+/// assert_eq!(primes::partition(2u32..12, 3), vec![2..6, 6..9, 9..12]);
+/// assert_eq!(primes::partition(0..10_000_000_000u64, 2)[1], 5_000_000_000..10_000_000_000);
+/// ```
+pub fn partition<T: Into<u64> + TryFrom<u64>>(range: Range<T>, parts: u32) -> Vec<Range<T>> {
+    assert!(parts > 0, "can't split a range into no parts");
+    let (range_start, range_end): (u64, u64) = (range.start.into(), range.end.into());
+    let parts = u64::from(parts);
+    let len = range_end.saturating_sub(range_start);
+    let (size, extra) = (len / parts, len % parts);
+
+    // Every bound lies between range.start and range.end, so it fits in T
+    let bound = |n: u64| T::try_from(n).unwrap_or_else(|_| unreachable!());
+    let mut start = range_start;
+    (0 .. parts)
+        .map(|i| {
+            // The first `extra` parts take one of the leftovers each
+            let end = start + size + u64::from(i < extra);
+            let part = bound(start) .. bound(end);
+            start = end;
+            part
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn covers_everything_once() {
+        for max in [2, 3, 10, 97, 100, 1000, 1001] {
+            for parts in 1 ..= 9 {
+                let pieces = partition(2 .. max, parts);
+                assert_eq!(pieces.len(), parts as usize);
+                let numbers: Vec<u32> = pieces.into_iter().flatten().collect();
+                assert_eq!(numbers, (2 .. max).collect::<Vec<_>>(), "2..{max} in {parts} parts");
+            }
+        }
+    }
+
+    #[test]
+    fn sizes_are_balanced() {
+        let sizes: Vec<usize> = partition(0u32 .. 200_000, 7).iter().map(|r| r.len()).collect();
+        assert!(sizes.iter().max().unwrap() - sizes.iter().min().unwrap() <= 1);
+    }
+
+    #[test]
+    fn threaded_counts_match_single_threaded() {
+        let expected = (2 .. 5000).filter(|n| is_prime(*n)).count();
+        for parts in [1, 2, 3, 7, 8, 16] {
+            let count: usize = partition(2u32 .. 5000, parts)
+                .into_iter()
+                .map(|range| range.filter(|n| is_prime(*n)).count())
+                .sum();
+            assert_eq!(count, expected, "{parts} parts");
+        }
+    }
+
+    #[test]
+    fn empty_ranges_stay_empty() {
+        // What `--max 1` asks for
+        let max: u32 = 1;
+        assert!(partition(2 .. max, 4).iter().all(|r| r.is_empty()));
+    }
+}
@@ -0,0 +1,58 @@
+//! A progress bar for long runs, so they don't look hung.
+//!
+//! Workers bump a shared `AtomicUsize` for every number they check; a
+//! background thread copies it to the bar a few times a second. Workers never
+//! wait on the terminal, and the bar hides itself when output isn't one.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use indicatif::{ProgressBar, ProgressStyle};
+
+pub struct Progress {
+    done: Arc<AtomicUsize>,
+    finished: Arc<AtomicBool>,
+    reporter: Option<JoinHandle<()>>,
+}
+
+impl Progress {
+    /// Start showing progress towards checking `total` numbers.
+    pub fn new(total: u64) -> Self {
+        let done = Arc::new(AtomicUsize::new(0));
+        let finished = Arc::new(AtomicBool::new(false));
+        let bar = ProgressBar::new(total).with_style(
+            ProgressStyle::with_template("{bar:40} {pos}/{len} numbers checked, {eta} left").unwrap(),
+        );
+        let reporter = {
+            let (done, finished) = (done.clone(), finished.clone());
+            std::thread::spawn(move || {
+                while !finished.load(Ordering::Relaxed) {
+                    bar.set_position(done.load(Ordering::Relaxed) as u64);
+                    // Woken early when we finish
+                    std::thread::park_timeout(Duration::from_millis(100));
+                }
+                bar.finish_and_clear();
+            })
+        };
+        Self { done, finished, reporter: Some(reporter) }
+    }
+
+    /// The counter for workers to add to as they go.
+    pub fn counter(&self) -> Arc<AtomicUsize> {
+        self.done.clone()
+    }
+
+    /// Take the bar down, before printing results.
+    pub fn finish(self) {}
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.finished.store(true, Ordering::Relaxed);
+        if let Some(reporter) = self.reporter.take() {
+            reporter.thread().unpark();
+            let _ = reporter.join();
+        }
+    }
+}
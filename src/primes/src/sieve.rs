@@ -0,0 +1,106 @@
+//! A segmented Sieve of Eratosthenes, for counting far past `u32::MAX`.
+//!
+//! A plain sieve needs a byte for every number, so counting the primes below
+//! 10^10 would need 10GB. Every composite below `max` has a prime factor no
+//! bigger than `sqrt(max)`, so we only need those primes up front. Then we can
+//! sieve one small segment at a time, reusing the same buffer, and ranges can
+//! be split between threads just like trial division.
+
+use std::ops::Range;
+use crate::partition;
+
+/// How many numbers to sieve at once. Small enough to stay in cache.
+const SEGMENT: u64 = 1 << 18;
+
+/// Every prime up to and including `limit`, with an ordinary sieve.
+pub fn primes_up_to(limit: u64) -> Vec<u64> {
+    let limit = limit as usize;
+    let mut is_prime = vec![true; limit + 1];
+    let mut primes = Vec::new();
+    for i in 2 ..= limit {
+        if is_prime[i] {
+            primes.push(i as u64);
+            for multiple in (i * i ..= limit).step_by(i) {
+                is_prime[multiple] = false;
+            }
+        }
+    }
+    primes
+}
+
+/// The primes needed to sieve any range ending at or before `max`.
+pub fn base_primes(max: u64) -> Vec<u64> {
+    primes_up_to(max.isqrt())
+}
+
+/// Count the primes in `range`, a segment at a time. `base` has to hold every
+/// prime up to `sqrt(range.end)`: see [`base_primes`].
+pub fn count_in(range: Range<u64>, base: &[u64]) -> usize {
+    let mut composite = vec![false; SEGMENT as usize];
+    let mut count = 0;
+    let mut low = range.start.max(2);
+    while low < range.end {
+        let high = (low + SEGMENT).min(range.end);
+        let segment = &mut composite[.. (high - low) as usize];
+        segment.fill(false);
+        for &p in base.iter().take_while(|&&p| p * p < high) {
+            // Smaller multiples were crossed off by smaller primes
+            let first = (p * p).max(low.div_ceil(p) * p);
+            for multiple in (first .. high).step_by(p as usize) {
+                segment[(multiple - low) as usize] = true;
+            }
+        }
+        count += segment.iter().filter(|c| !**c).count();
+        low = high;
+    }
+    count
+}
+
+/// Count the primes below `max`, splitting the range between `threads` threads.
+pub fn count_below(max: u64, threads: u32) -> usize {
+    let base = base_primes(max);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = partition(2 .. max.max(2), threads)
+            .into_iter()
+            .map(|range| {
+                let base = &base;
+                scope.spawn(move || count_in(range, base))
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{fast, is_prime};
+
+    #[test]
+    fn agrees_with_trial_division() {
+        for max in [0, 1, 2, 3, 4, 10, 100, 7919, 7920, 20_000] {
+            let expected = (2 .. max).filter(|n| is_prime(*n)).count();
+            for threads in [1, 3, 8] {
+                assert_eq!(count_below(max as u64, threads), expected, "below {max} on {threads} threads");
+            }
+        }
+    }
+
+    #[test]
+    fn spans_many_segments() {
+        // 78,498 primes below a million
+        assert_eq!(primes_up_to(999_999).len(), 78_498);
+        for threads in [1, 3] {
+            assert_eq!(count_below(1_000_000, threads), 78_498);
+        }
+    }
+
+    #[test]
+    fn counts_past_u32() {
+        // Numbers either side of 2^32, which a u32 counter can't reach
+        let max = 1 << 32;
+        let base = base_primes(max + 1_000_000);
+        let expected = (max - 1_000 .. max + 1_000).filter(|n| fast::trial_division(*n)).count();
+        assert_eq!(count_in(max - 1_000 .. max + 1_000, &base), expected);
+    }
+}
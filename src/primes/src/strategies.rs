@@ -0,0 +1,101 @@
+//! Every way the course counts primes, as functions, so they can be
+//! benchmarked against each other. Each counts the primes below `max`.
+
+use std::sync::{atomic::{AtomicUsize, Ordering}, mpsc, Mutex};
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use crate::{is_prime, partition};
+
+/// One thread, trial division.
+pub fn sequential(max: u32) -> usize {
+    (2 .. max).filter(|n| is_prime(*n)).count()
+}
+
+/// Threads add their primes to a shared `Mutex<Vec>`.
+pub fn mutex(max: u32, threads: u32) -> usize {
+    let primes = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for range in partition(2 .. max, threads) {
+            let primes = &primes;
+            scope.spawn(move || {
+                let mine: Vec<u32> = range.filter(|n| is_prime(*n)).collect();
+                primes.lock().unwrap().extend(mine);
+            });
+        }
+    });
+    primes.into_inner().unwrap().len()
+}
+
+/// Threads add their counts to a shared atomic.
+pub fn atomic(max: u32, threads: u32) -> usize {
+    let counter = AtomicUsize::new(0);
+    std::thread::scope(|scope| {
+        for range in partition(2 .. max, threads) {
+            let counter = &counter;
+            scope.spawn(move || counter.fetch_add(range.filter(|n| is_prime(*n)).count(), Ordering::Relaxed));
+        }
+    });
+    counter.into_inner()
+}
+
+/// Threads send each prime they find down a channel to a collector.
+pub fn channel(max: u32, threads: u32) -> usize {
+    let (tx, rx) = mpsc::channel();
+    std::thread::scope(|scope| {
+        for range in partition(2 .. max, threads) {
+            let tx = tx.clone();
+            scope.spawn(move || {
+                for n in range.filter(|n| is_prime(*n)) {
+                    tx.send(n).unwrap();
+                }
+            });
+        }
+        drop(tx);
+        rx.iter().count()
+    })
+}
+
+/// Rayon's `into_par_iter`, on a pool of `threads` threads.
+pub fn rayon(max: u32, threads: u32) -> usize {
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(threads as usize).build().unwrap();
+    pool.install(|| (2 .. max).into_par_iter().filter(|n| is_prime(*n)).count())
+}
+
+/// One thread, Sieve of Eratosthenes: cross off the multiples of each prime,
+/// and whatever is left is prime. No division at all.
+pub fn sieve(max: u32) -> usize {
+    let max = max as usize;
+    if max < 3 {
+        return 0;
+    }
+    let mut is_prime = vec![true; max];
+    is_prime[0] = false;
+    is_prime[1] = false;
+    let mut i = 2;
+    while i * i < max {
+        if is_prime[i] {
+            // Smaller multiples were crossed off by smaller primes
+            for multiple in (i * i .. max).step_by(i) {
+                is_prime[multiple] = false;
+            }
+        }
+        i += 1;
+    }
+    is_prime.iter().filter(|p| **p).count()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn strategies_agree() {
+        let expected = sequential(3000);
+        for threads in [1, 3, 8] {
+            assert_eq!(mutex(3000, threads), expected);
+            assert_eq!(atomic(3000, threads), expected);
+            assert_eq!(channel(3000, threads), expected);
+            assert_eq!(rayon(3000, threads), expected);
+        }
+        assert_eq!(sieve(3000), expected);
+    }
+}
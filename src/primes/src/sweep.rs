@@ -0,0 +1,69 @@
+//! Rerun a computation at every thread count, and write the times as CSV for
+//! plotting. Speedup flattens out as threads are added - Amdahl's law - and a
+//! chart shows where far better than a table.
+
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+use std::time::Instant;
+
+/// Parse a `--sweep` argument: `FIRST..LAST`, both included, so `1..32`
+/// runs everything from 1 to 32 threads.
+pub fn parse_range(s: &str) -> Result<RangeInclusive<u32>, String> {
+    let (first, last) = s.split_once("..").ok_or("expected FIRST..LAST, like 1..32")?;
+    let first: u32 = first.trim().parse().map_err(|e| format!("{first:?}: {e}"))?;
+    let last: u32 = last.trim().parse().map_err(|e| format!("{last:?}: {e}"))?;
+    if first == 0 || first > last {
+        return Err(format!("{first}..{last} isn't a range of thread counts"));
+    }
+    Ok(first ..= last)
+}
+
+/// Time each of `strategies` at every thread count in `threads`, writing a
+/// `threads,<name>,...` header then one row of seconds per thread count.
+/// Rows are echoed to stdout as they finish, since a sweep takes a while.
+pub fn sweep(threads: RangeInclusive<u32>, strategies: &[(&str, &dyn Fn(u32))], mut out: impl Write) -> io::Result<()> {
+    let names: Vec<&str> = strategies.iter().map(|(name, _)| *name).collect();
+    let header = format!("threads,{}", names.join(","));
+    println!("{header}");
+    writeln!(out, "{header}")?;
+
+    for n in threads {
+        let mut row = n.to_string();
+        for (_, run) in strategies {
+            let now = Instant::now();
+            run(n);
+            row += &format!(",{:.6}", now.elapsed().as_secs_f64());
+        }
+        println!("{row}");
+        writeln!(out, "{row}")?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(parse_range("1..32"), Ok(1 ..= 32));
+        assert_eq!(parse_range("4..4"), Ok(4 ..= 4));
+        assert!(parse_range("0..8").is_err());
+        assert!(parse_range("8..2").is_err());
+        assert!(parse_range("8").is_err());
+        assert!(parse_range("a..b").is_err());
+    }
+
+    #[test]
+    fn writes_a_row_per_thread_count() {
+        let mut csv = Vec::new();
+        sweep(1 ..= 3, &[("a", &|_| ()), ("b", &|_| ())], &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "threads,a,b");
+        assert_eq!(lines.len(), 4);
+        assert!(lines[3].starts_with("3,"));
+        assert_eq!(lines[3].split(',').count(), 3);
+    }
+}
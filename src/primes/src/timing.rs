@@ -0,0 +1,23 @@
+//! How long each thread of a threaded counter spent working.
+
+use std::time::Duration;
+
+/// When one thread started and stopped, measured from when we started
+/// spawning, and how many primes it found.
+pub struct Timing {
+    pub start: Duration,
+    pub stop: Duration,
+    pub primes: usize,
+}
+
+/// Show how long each thread was busy. With contiguous chunks, the threads
+/// with the biggest numbers finish last.
+pub fn print_timings(timings: &[Timing]) {
+    println!("{:>8}{:>12}{:>12}{:>12}{:>10}", "Thread", "Start (s)", "Stop (s)", "Busy (s)", "Primes");
+    for (i, t) in timings.iter().enumerate() {
+        println!(
+            "{i:>8}{:>12.4}{:>12.4}{:>12.4}{:>10}",
+            t.start.as_secs_f32(), t.stop.as_secs_f32(), (t.stop - t.start).as_secs_f32(), t.primes
+        );
+    }
+}
@@ -0,0 +1,46 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rc_bench::{atomic_rc_cat, cat_arena, cat_slotmap, cat_store, cat_vec, rc_cat, threaded_store};
+
+/// Enough cats to spill out of the CPU caches, few enough to set up quickly.
+const NUMBER_OF_CATS: usize = 100_000;
+const NUMBER_OF_THREADS: usize = 4;
+
+/// Sets up this many cats, returning a function that feeds them.
+type Feeder = fn(usize) -> Box<dyn FnMut()>;
+
+fn single_threaded(c: &mut Criterion) {
+    let mut group = c.benchmark_group("feed_cats");
+    // Report cats per second, so sizes can be compared
+    group.throughput(Throughput::Elements(NUMBER_OF_CATS as u64));
+
+    let feeders: [(&str, Feeder); 6] = [
+        ("vec", |n| Box::new(cat_vec::cat_feeder(n))),
+        ("rc", |n| Box::new(rc_cat::cat_feeder(n))),
+        ("arc", |n| Box::new(atomic_rc_cat::cat_feeder(n))),
+        ("store", |n| Box::new(cat_store::cat_feeder(n))),
+        ("slotmap", |n| Box::new(cat_slotmap::cat_feeder(n))),
+        ("arena", |n| Box::new(cat_arena::cat_feeder(n))),
+    ];
+    for (name, feeder) in feeders {
+        // Cats are set up once; each iteration feeds them all again
+        let mut feed_cats = feeder(NUMBER_OF_CATS);
+        group.bench_function(name, |b| b.iter(&mut feed_cats));
+    }
+    group.finish();
+}
+
+fn threaded(c: &mut Criterion) {
+    let mut group = c.benchmark_group("feed_cats_threaded");
+    group.throughput(Throughput::Elements(NUMBER_OF_CATS as u64));
+
+    let mut feed_cats = threaded_store::mutex_feeder(NUMBER_OF_CATS, NUMBER_OF_THREADS);
+    group.bench_function(BenchmarkId::new("mutex", NUMBER_OF_THREADS), |b| b.iter(&mut feed_cats));
+    let mut feed_cats = threaded_store::rwlock_feeder(NUMBER_OF_CATS, NUMBER_OF_THREADS);
+    group.bench_function(BenchmarkId::new("rwlock", NUMBER_OF_THREADS), |b| b.iter(&mut feed_cats));
+    let mut feed_cats = threaded_store::dashmap_feeder(NUMBER_OF_CATS, NUMBER_OF_THREADS);
+    group.bench_function(BenchmarkId::new("dashmap", NUMBER_OF_THREADS), |b| b.iter(&mut feed_cats));
+    group.finish();
+}
+
+criterion_group!(benches, single_threaded, threaded);
+criterion_main!(benches);
@@ -25,10 +25,14 @@ impl CatOwner {
     }
 }
 
-pub fn feed_cats(n_cats: usize) {
+/// Make `n_cats` cats and their owners, and return a function that feeds
+/// every cat.
+pub fn cat_feeder(n_cats: usize) -> impl FnMut() {
     let mut owners = Vec::new();
     for i in 0 .. n_cats {
-        // Make a cat
+        // Make a cat. Cat isn't Sync, so this Arc buys nothing over an Rc
+        // except the atomic counting we're here to measure.
+        #[allow(clippy::arc_with_non_send_sync)]
         let new_cat = Arc::new(Cat{ 
             name: format!("Fuzzy Friend {}", i+1),
             status: RefCell::new(String::new()),
@@ -40,12 +44,8 @@ pub fn feed_cats(n_cats: usize) {
         );
     }
 
-    // Start the timer
-    let now = std::time::Instant::now();
-    owners
+    // Feeding time
+    move || owners
         .iter()
-        .for_each(|owner| owner.feed_cat());
-    let duration = now.elapsed();
-
-    super::print_result("ARC Cats", duration);
+        .for_each(|owner| owner.feed_cat())
 }
\ No newline at end of file
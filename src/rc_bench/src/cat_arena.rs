@@ -0,0 +1,132 @@
+use std::fmt::Display;
+
+struct Cat {
+    name: String,
+    status: String,
+}
+
+impl Display for Cat {
+    /// Print service for cats
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.name, self.status)
+    }
+}
+
+/// A cat's position in the arena, and which occupant of that position it was.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct CatId {
+    index: usize,
+    generation: u32,
+}
+
+struct CatOwner {
+    cat_id: CatId,
+}
+
+struct Slot {
+    generation: u32,
+    cat: Option<Cat>,
+}
+
+/// A vector of cats that reuses the slots of removed cats. Each slot counts
+/// how many times it has been reused, so an old ID can't find a new cat.
+struct CatArena {
+    slots: Vec<Slot>,
+    free: Vec<usize>,
+}
+
+impl CatArena {
+    /// Creates a new CatArena
+    fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    /// Add a new cat, and return its ID
+    fn add_cat(&mut self, cat: Cat) -> CatId {
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index];
+            slot.cat = Some(cat);
+            CatId { index, generation: slot.generation }
+        } else {
+            self.slots.push(Slot { generation: 0, cat: Some(cat) });
+            CatId { index: self.slots.len() - 1, generation: 0 }
+        }
+    }
+
+    /// Remove a cat, freeing its slot for the next one
+    #[allow(dead_code)] // The benchmark never removes cats
+    fn remove_cat(&mut self, id: CatId) -> Option<Cat> {
+        let slot = self.slots.get_mut(id.index)?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        let cat = slot.cat.take()?;
+        slot.generation += 1;
+        self.free.push(id.index);
+        Some(cat)
+    }
+
+    /// Find cat by id, set status to "purring"
+    fn feed_cat(&mut self, id: CatId) {
+        if let Some(Slot { generation, cat: Some(cat) }) = self.slots.get_mut(id.index) {
+            if *generation == id.generation {
+                cat.status = "Purring".to_string();
+            }
+        }
+    }
+}
+
+/// Make `n_cats` cats and their owners, and return a function that feeds
+/// every cat.
+pub fn cat_feeder(n_cats: usize) -> impl FnMut() {
+    let mut store = CatArena::new();
+    let mut owners = Vec::new();
+    for i in 0 .. n_cats {
+        // Make a cat
+        let new_cat = Cat{ 
+            name: format!("Fuzzy Friend {}", i+1),
+            status: String::new(),
+        };
+        // Add it to the arena and get ID
+        let new_id = store.add_cat(new_cat);
+
+        // Associate the owner with the ID
+        owners.push(
+            CatOwner { cat_id: new_id }
+        );
+    }
+
+    // Feeding time
+    move || owners
+        .iter()
+        .for_each(|owner| store.feed_cat(owner.cat_id))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cat(name: &str) -> Cat {
+        Cat { name: name.to_string(), status: String::new() }
+    }
+
+    #[test]
+    fn stale_ids_miss_the_new_cat() {
+        let mut arena = CatArena::new();
+        let tiddles = arena.add_cat(cat("Tiddles"));
+        assert_eq!(arena.remove_cat(tiddles).unwrap().name, "Tiddles");
+
+        // The slot is reused, but Tiddles' old ID doesn't reach the new cat
+        let felix = arena.add_cat(cat("Felix"));
+        assert_eq!(felix.index, tiddles.index);
+        arena.feed_cat(tiddles);
+        assert_eq!(arena.slots[felix.index].cat.as_ref().unwrap().status, "");
+        assert!(arena.remove_cat(tiddles).is_none());
+
+        arena.feed_cat(felix);
+        assert_eq!(arena.slots[felix.index].cat.as_ref().unwrap().status, "Purring");
+    }
+}
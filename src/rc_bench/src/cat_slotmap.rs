@@ -0,0 +1,48 @@
+use std::fmt::Display;
+use slotmap::{DefaultKey, SlotMap};
+
+struct Cat {
+    name: String,
+    status: String,
+}
+
+impl Display for Cat {
+    /// Print service for cats
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.name, self.status)
+    }
+}
+
+struct CatOwner {
+    cat_key: DefaultKey,
+}
+
+/// Make `n_cats` cats and their owners, and return a function that feeds
+/// every cat.
+pub fn cat_feeder(n_cats: usize) -> impl FnMut() {
+    let mut store = SlotMap::with_capacity(n_cats);
+    let mut owners = Vec::new();
+    for i in 0 .. n_cats {
+        // Make a cat
+        let new_cat = Cat{ 
+            name: format!("Fuzzy Friend {}", i+1),
+            status: String::new(),
+        };
+        // Add it to the slot map and get a key
+        let new_key = store.insert(new_cat);
+
+        // Associate the owner with the key
+        owners.push(
+            CatOwner { cat_key: new_key }
+        );
+    }
+
+    // Feeding time
+    move || owners
+        .iter()
+        .for_each(|owner| {
+            if let Some(cat) = store.get_mut(owner.cat_key) {
+                cat.status = "Purring".to_string();
+            }
+        })
+}
@@ -43,13 +43,15 @@ impl CatStore {
 
     /// Find cat by id, set status to "purring"
     fn feed_cat(&mut self, id: usize) {
-        if let Some(mut cat) = self.cats.get_mut(&id) {
+        if let Some(cat) = self.cats.get_mut(&id) {
             cat.status = "Purring".to_string();
         }
     }
 }
 
-pub fn feed_cats_by_id(n_cats: usize) {
+/// Make `n_cats` cats and their owners, and return a function that feeds
+/// every cat.
+pub fn cat_feeder(n_cats: usize) -> impl FnMut() {
     let mut store = CatStore::new();
     let mut owners = Vec::new();
     for i in 0 .. n_cats {
@@ -67,12 +69,8 @@ pub fn feed_cats_by_id(n_cats: usize) {
         );
     }
 
-    // Start the timer
-    let now = std::time::Instant::now();
-    owners
+    // Feeding time
+    move || owners
         .iter()
-        .for_each(|owner| store.feed_cat(owner.cat_id));
-    let duration = now.elapsed();
-
-    super::print_result("Cat Store", duration);
+        .for_each(|owner| store.feed_cat(owner.cat_id))
 }
\ No newline at end of file
@@ -42,7 +42,9 @@ impl CatStore {
     }
 }
 
-pub fn feed_cats_by_id(n_cats: usize) {
+/// Make `n_cats` cats and their owners, and return a function that feeds
+/// every cat.
+pub fn cat_feeder(n_cats: usize) -> impl FnMut() {
     let mut store = CatStore::new();
     let mut owners = Vec::new();
     for i in 0 .. n_cats {
@@ -60,12 +62,8 @@ pub fn feed_cats_by_id(n_cats: usize) {
         );
     }
 
-    // Start the timer
-    let now = std::time::Instant::now();
-    owners
+    // Feeding time
+    move || owners
         .iter()
-        .for_each(|owner| store.feed_cat(owner.cat_idx));
-    let duration = now.elapsed();
-
-    super::print_result("Vector of Cats", duration);
+        .for_each(|owner| store.feed_cat(owner.cat_idx))
 }
\ No newline at end of file
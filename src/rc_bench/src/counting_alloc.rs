@@ -0,0 +1,71 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
+
+/// Only count while `measure` runs. Every thread counting updates the same two
+/// atomics, so leaving it on would make the threaded stores fight over them
+/// and the timings would include that. Checking this flag only reads it,
+/// which threads can share without fighting.
+static COUNTING: AtomicBool = AtomicBool::new(false);
+
+/// Bytes allocated while counting, less those freed while counting. Memory
+/// allocated before counting started can be freed during it, so this can go
+/// negative.
+static CURRENT: AtomicIsize = AtomicIsize::new(0);
+
+/// The highest `CURRENT` has been since `measure` started.
+static PEAK: AtomicIsize = AtomicIsize::new(0);
+
+/// Hands every request to the system allocator, keeping count as it goes
+/// while `measure` runs.
+pub struct CountingAllocator;
+
+fn grew(bytes: usize) {
+    if COUNTING.load(Ordering::Relaxed) {
+        let now = CURRENT.fetch_add(bytes as isize, Ordering::Relaxed) + bytes as isize;
+        PEAK.fetch_max(now, Ordering::Relaxed);
+    }
+}
+
+fn shrank(bytes: usize) {
+    if COUNTING.load(Ordering::Relaxed) {
+        CURRENT.fetch_sub(bytes as isize, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            grew(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        shrank(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            if new_size > layout.size() {
+                grew(new_size - layout.size());
+            } else {
+                shrank(layout.size() - new_size);
+            }
+        }
+        new_ptr
+    }
+}
+
+/// Run `f` with counting on, and return the most bytes it had allocated at
+/// once. Don't time anything in here; counting slows every allocation down.
+pub fn measure(f: impl FnOnce()) -> usize {
+    CURRENT.store(0, Ordering::SeqCst);
+    PEAK.store(0, Ordering::SeqCst);
+    COUNTING.store(true, Ordering::SeqCst);
+    f();
+    COUNTING.store(false, Ordering::SeqCst);
+    PEAK.load(Ordering::SeqCst).max(0) as usize
+}
@@ -0,0 +1,33 @@
+//! The cat stores rc_bench compares, each as a function that sets up cats and
+//! their owners and returns a function feeding every cat. The benchmark binary
+//! and the criterion benches both time those.
+
+// Store cats in a simple vector and index by vector position.
+// VERY fast, has the downside that you can't insert or delete cats
+// without ruining every index
+pub mod cat_vec;
+
+// Store cats in a HashMap, giving each cat a stable ID number.
+// Slower.
+pub mod cat_store;
+
+// Store each cat as a reference counted pointer, and hand a
+// reference to the cat to each owner. Simpler, there's no
+// cat store at all.
+pub mod rc_cat;
+
+// Reference counted cats, but using thread-safe reference
+// counting.
+pub mod atomic_rc_cat;
+
+// Store cats in the slotmap crate's SlotMap. Keys stay valid as other
+// cats come and go, without hashing.
+pub mod cat_slotmap;
+
+// The same idea by hand: a vector whose slots are reused, with a
+// generation count so an old ID can't find the slot's new cat.
+pub mod cat_arena;
+
+// A HashMap cat store shared between threads, behind a Mutex, an
+// RwLock or as a DashMap. Shows what contention costs.
+pub mod threaded_store;
@@ -1,32 +1,143 @@
-// Store cats in a simple vector and index by vector position.
-// VERY fast, has the downside that you can't insert or delete cats
-// without ruining every index
-mod cat_vec;
-
-// Store cats in a HashMap, giving each cat a stable ID number.
-// Slower.
-mod cat_store;
-
-// Store each cat as a reference counted pointer, and hand a
-// reference to the cat to each owner. Simpler, there's no
-// cat store at all.
-mod rc_cat;
-
-// Reference counted cats, but using thread-safe reference
-// counting.
-mod atomic_rc_cat;
-
-const NUMBER_OF_CATS: usize = 10_000_000;
-
-fn print_result(method: &str, time: std::time::Duration) {
-    let usecs = format!("{} μsecs", time.as_micros());
-    let nanos_per_cat = format!("{} nanos per cat", time.as_nanos() as usize / NUMBER_OF_CATS);
-    println!("{method:<30}{usecs:<20}{nanos_per_cat:<20}");
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use clap::{Parser, ValueEnum};
+use rc_bench::{atomic_rc_cat, cat_arena, cat_slotmap, cat_store, cat_vec, rc_cat, threaded_store};
+
+// Counts allocations during an untimed run, so we can see how much memory
+// each strategy needs as well as how fast it is.
+mod counting_alloc;
+
+// Writing the results table to a file.
+mod report;
+use report::{Format, Measurement};
+
+#[global_allocator]
+static ALLOCATOR: counting_alloc::CountingAllocator = counting_alloc::CountingAllocator;
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Strategy {
+    Vec,
+    Rc,
+    Arc,
+    Store,
+    #[value(name = "slotmap")]
+    SlotMap,
+    Arena,
+    Mutex,
+    #[value(name = "rwlock")]
+    RwLock,
+    #[value(name = "dashmap")]
+    DashMap,
+}
+
+impl Strategy {
+    fn name(self, n_threads: usize) -> String {
+        match self {
+            Strategy::Vec => "Vector of Cats".to_string(),
+            Strategy::Rc => "RC Cats".to_string(),
+            Strategy::Arc => "ARC Cats".to_string(),
+            Strategy::Store => "Cat Store".to_string(),
+            Strategy::SlotMap => "Slot Map".to_string(),
+            Strategy::Arena => "Generational Arena".to_string(),
+            Strategy::Mutex => format!("Mutex Store ({n_threads} threads)"),
+            Strategy::RwLock => format!("RwLock Store ({n_threads} threads)"),
+            Strategy::DashMap => format!("DashMap Store ({n_threads} threads)"),
+        }
+    }
+
+    /// Set up `n_cats` cats and time feeding them all, once.
+    fn run(self, n_cats: usize, n_threads: usize) -> Duration {
+        match self {
+            Strategy::Vec => time(cat_vec::cat_feeder(n_cats)),
+            Strategy::Rc => time(rc_cat::cat_feeder(n_cats)),
+            Strategy::Arc => time(atomic_rc_cat::cat_feeder(n_cats)),
+            Strategy::Store => time(cat_store::cat_feeder(n_cats)),
+            Strategy::SlotMap => time(cat_slotmap::cat_feeder(n_cats)),
+            Strategy::Arena => time(cat_arena::cat_feeder(n_cats)),
+            Strategy::Mutex => time(threaded_store::mutex_feeder(n_cats, n_threads)),
+            Strategy::RwLock => time(threaded_store::rwlock_feeder(n_cats, n_threads)),
+            Strategy::DashMap => time(threaded_store::dashmap_feeder(n_cats, n_threads)),
+        }
+    }
+}
+
+fn time(mut feed_cats: impl FnMut()) -> Duration {
+    let now = Instant::now();
+    feed_cats();
+    now.elapsed()
+}
+
+#[derive(Parser)]
+struct Args {
+    /// How many cats to feed
+    #[arg(long, default_value_t = 10_000_000, value_parser = clap::value_parser!(u64).range(1..))]
+    cats: u64,
+
+    /// Time each strategy this many times, and report the average
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    iterations: u32,
+
+    /// Which strategies to run, separated by commas
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = Strategy::value_variants().to_vec())]
+    strategies: Vec<Strategy>,
+
+    /// How many threads the Mutex, RwLock and DashMap stores feed from
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u64).range(1..))]
+    threads: u64,
+
+    /// Skip the untimed run of each strategy before timing it. Memory is then
+    /// measured in an extra run afterwards
+    #[arg(long)]
+    no_warmup: bool,
+
+    /// Also write the results table to a file, in this format
+    #[arg(long, value_enum)]
+    report: Option<Format>,
+
+    /// Where --report writes to. Defaults to rc_bench.md or rc_bench.csv
+    #[arg(long)]
+    output: Option<PathBuf>,
 }
 
-fn main() {
-    cat_vec::feed_cats_by_id(NUMBER_OF_CATS);
-    rc_cat::feed_cats(NUMBER_OF_CATS);
-    atomic_rc_cat::feed_cats(NUMBER_OF_CATS);
-    cat_store::feed_cats_by_id(NUMBER_OF_CATS);
+fn print_result(result: &Measurement, n_cats: usize) {
+    let method = &result.strategy;
+    let usecs = format!("{} μsecs", result.time.as_micros());
+    let nanos_per_cat = format!("{} nanos per cat", result.nanos_per_cat(n_cats));
+    let bytes_per_cat = format!("{} bytes per cat", result.bytes_per_cat(n_cats));
+    println!("{method:<30}{usecs:<20}{nanos_per_cat:<20}{bytes_per_cat:<20}");
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let (n_cats, n_threads) = (args.cats as usize, args.threads as usize);
+
+    let mut results = Vec::with_capacity(args.strategies.len());
+    for strategy in args.strategies {
+        // Counting allocations slows them down, so memory is measured in a
+        // run of its own. Everything a run allocates is freed when it
+        // returns, so its peak is what the cats and their store took
+        let measure_memory = || counting_alloc::measure(|| {
+            strategy.run(n_cats, n_threads);
+        });
+
+        // The first run pays for page faults and growing the allocator's
+        // heap, which would make whichever strategy goes first look slow.
+        // It isn't timed, so it's where memory is measured
+        let warmup_bytes = (!args.no_warmup).then(measure_memory);
+        let total: Duration = (0 .. args.iterations).map(|_| strategy.run(n_cats, n_threads)).sum();
+        let result = Measurement {
+            strategy: strategy.name(n_threads),
+            time: total / args.iterations,
+            peak_bytes: warmup_bytes.unwrap_or_else(measure_memory),
+        };
+        print_result(&result, n_cats);
+        results.push(result);
+    }
+
+    if let Some(format) = args.report {
+        let path = args.output.unwrap_or_else(|| format.default_path().into());
+        std::fs::write(&path, report::render(format, &results, n_cats))?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
 }
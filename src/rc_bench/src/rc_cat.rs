@@ -25,7 +25,9 @@ impl CatOwner {
     }
 }
 
-pub fn feed_cats(n_cats: usize) {
+/// Make `n_cats` cats and their owners, and return a function that feeds
+/// every cat.
+pub fn cat_feeder(n_cats: usize) -> impl FnMut() {
     let mut owners = Vec::new();
     for i in 0 .. n_cats {
         // Make a cat
@@ -40,12 +42,8 @@ pub fn feed_cats(n_cats: usize) {
         );
     }
 
-    // Start the timer
-    let now = std::time::Instant::now();
-    owners
+    // Feeding time
+    move || owners
         .iter()
-        .for_each(|owner| owner.feed_cat());
-    let duration = now.elapsed();
-
-    super::print_result("RC Cats", duration);
+        .for_each(|owner| owner.feed_cat())
 }
\ No newline at end of file
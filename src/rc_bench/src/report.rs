@@ -0,0 +1,86 @@
+use std::time::Duration;
+use clap::ValueEnum;
+
+/// How one strategy did.
+pub struct Measurement {
+    pub strategy: String,
+    /// Average time to feed every cat once
+    pub time: Duration,
+    /// The most memory the cats and their store took at once
+    pub peak_bytes: usize,
+}
+
+impl Measurement {
+    pub fn nanos_per_cat(&self, n_cats: usize) -> usize {
+        self.time.as_nanos() as usize / n_cats
+    }
+
+    pub fn bytes_per_cat(&self, n_cats: usize) -> usize {
+        self.peak_bytes / n_cats
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    /// A Markdown table, for the course notes
+    Md,
+    /// CSV, for spreadsheets and tracking regressions
+    Csv,
+}
+
+impl Format {
+    pub fn default_path(self) -> &'static str {
+        match self {
+            Format::Md => "rc_bench.md",
+            Format::Csv => "rc_bench.csv",
+        }
+    }
+}
+
+/// The results table, one row per strategy in the order they ran. The
+/// columns don't change, so reports from different runs can be diffed.
+pub fn render(format: Format, results: &[Measurement], n_cats: usize) -> String {
+    let mut out = match format {
+        Format::Md => "| Strategy | Time (μs) | Nanos per cat | Bytes per cat |\n|---|---:|---:|---:|\n".to_string(),
+        Format::Csv => "strategy,time_us,nanos_per_cat,bytes_per_cat\n".to_string(),
+    };
+    for result in results {
+        let (time, nanos, bytes) = (result.time.as_micros(), result.nanos_per_cat(n_cats), result.bytes_per_cat(n_cats));
+        out += &match format {
+            Format::Md => format!("| {} | {time} | {nanos} | {bytes} |\n", result.strategy),
+            Format::Csv => format!("{},{time},{nanos},{bytes}\n", result.strategy),
+        };
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn results() -> Vec<Measurement> {
+        vec![
+            Measurement { strategy: "Vector of Cats".to_string(), time: Duration::from_micros(500), peak_bytes: 9_100 },
+            Measurement { strategy: "Cat Store".to_string(), time: Duration::from_micros(2_200), peak_bytes: 21_100 },
+        ]
+    }
+
+    #[test]
+    fn renders_markdown() {
+        assert_eq!(render(Format::Md, &results(), 100), "\
+| Strategy | Time (μs) | Nanos per cat | Bytes per cat |
+|---|---:|---:|---:|
+| Vector of Cats | 500 | 5000 | 91 |
+| Cat Store | 2200 | 22000 | 211 |
+");
+    }
+
+    #[test]
+    fn renders_csv() {
+        assert_eq!(render(Format::Csv, &results(), 100), "\
+strategy,time_us,nanos_per_cat,bytes_per_cat
+Vector of Cats,500,5000,91
+Cat Store,2200,22000,211
+");
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use dashmap::DashMap;
+
+struct Cat {
+    #[allow(dead_code)] // Cats need names, even if the benchmark never reads them
+    name: String,
+    status: String,
+}
+
+fn new_cat(i: usize) -> Cat {
+    Cat {
+        name: format!("Fuzzy Friend {}", i+1),
+        status: String::new(),
+    }
+}
+
+/// Return a function that splits the owners' cat IDs between `n_threads`
+/// threads, and has each thread feed its share.
+fn feed_from_threads(n_cats: usize, n_threads: usize, feed_cat: impl Fn(usize) + Sync) -> impl FnMut() {
+    let ids: Vec<usize> = (0 .. n_cats).collect();
+    let chunk_size = n_cats.div_ceil(n_threads).max(1);
+
+    // Feeding time
+    move || std::thread::scope(|scope| {
+        for chunk in ids.chunks(chunk_size) {
+            let feed_cat = &feed_cat;
+            scope.spawn(move || chunk.iter().for_each(|id| feed_cat(*id)));
+        }
+    })
+}
+
+/// The whole store behind one lock: every feeding waits for every other.
+pub fn mutex_feeder(n_cats: usize, n_threads: usize) -> impl FnMut() {
+    let store: Arc<Mutex<HashMap<usize, Cat>>> = Arc::new(Mutex::new((0 .. n_cats).map(|i| (i, new_cat(i))).collect()));
+    feed_from_threads(n_cats, n_threads, move |id| {
+        if let Some(cat) = store.lock().unwrap().get_mut(&id) {
+            cat.status = "Purring".to_string();
+        }
+    })
+}
+
+/// Feeding doesn't add or remove cats, so the map only needs a read lock,
+/// which threads can share. Each cat's status has its own lock instead.
+pub fn rwlock_feeder(n_cats: usize, n_threads: usize) -> impl FnMut() {
+    let store: Arc<RwLock<HashMap<usize, Mutex<Cat>>>> = Arc::new(RwLock::new((0 .. n_cats).map(|i| (i, Mutex::new(new_cat(i)))).collect()));
+    feed_from_threads(n_cats, n_threads, move |id| {
+        if let Some(cat) = store.read().unwrap().get(&id) {
+            cat.lock().unwrap().status = "Purring".to_string();
+        }
+    })
+}
+
+/// DashMap splits the map into shards with a lock each, so threads only wait
+/// when they want cats in the same shard.
+pub fn dashmap_feeder(n_cats: usize, n_threads: usize) -> impl FnMut() {
+    let store: Arc<DashMap<usize, Cat>> = Arc::new((0 .. n_cats).map(|i| (i, new_cat(i))).collect());
+    feed_from_threads(n_cats, n_threads, move |id| {
+        if let Some(mut cat) = store.get_mut(&id) {
+            cat.status = "Purring".to_string();
+        }
+    })
+}
@@ -0,0 +1,125 @@
+use rocket::http::Status;
+use rocket::response::stream::{Event as SseEvent, EventStream};
+use rocket::serde::{json::Json, Deserialize};
+use rocket::{Route, Shutdown, State};
+use auth_json::{LoginAction, Role};
+use auth_server::{policy, protocol::{AdminCommand, AdminResponse, Request, Response, UserInfo}};
+use crate::{backend::Backend, errors::backend_down, guards::AdminUser, RegisterResponse};
+
+pub fn routes() -> Vec<Route> {
+    routes![list_users, create_user, delete_user, set_role, reset_password, events]
+}
+
+/// Run an admin command as the logged-in admin. The `AdminUser` guard has
+/// already turned away everyone else, but the auth server has the last word.
+async fn run(admin: AdminUser, backend: &Backend, command: AdminCommand) -> Result<AdminResponse, Status> {
+    let mut client = backend.get().await.map_err(backend_down)?;
+    match client.call(&Request::Admin { token: admin.0.token, command }).await.map_err(backend_down)? {
+        Response::Admin(AdminResponse::NotAuthorized) => Err(Status::Forbidden),
+        Response::Admin(AdminResponse::UserExists) => Err(Status::Conflict),
+        Response::Admin(AdminResponse::UnknownUser) => Err(Status::NotFound),
+        Response::Admin(response) => Ok(response),
+        _ => Err(Status::InternalServerError),
+    }
+}
+
+/// Either a bare status for the catchers to explain, or a refused password,
+/// listing every problem in the same body as `register` does.
+#[derive(Responder)]
+pub enum Refusal {
+    Status(Status),
+    Rejected((Status, Json<RegisterResponse>)),
+}
+
+impl From<Status> for Refusal {
+    fn from(status: Status) -> Self {
+        Refusal::Status(status)
+    }
+}
+
+fn check_password(username: &str, password: &str) -> Result<(), Refusal> {
+    let problems = policy::password_problems(username, password);
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(Refusal::Rejected(RegisterResponse::rejected(problems.into_iter().map(String::from).collect())))
+}
+
+#[get("/api/admin/users")]
+pub async fn list_users(admin: AdminUser, backend: &State<Backend>) -> Result<Json<Vec<UserInfo>>, Status> {
+    match run(admin, backend, AdminCommand::ListUsers).await? {
+        AdminResponse::Users(users) => Ok(Json(users)),
+        _ => Err(Status::InternalServerError),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct NewUser {
+    username: String,
+    password: String,
+    role: Role,
+}
+
+#[post("/api/admin/users", data = "<user>")]
+pub async fn create_user(user: Json<NewUser>, admin: AdminUser, backend: &State<Backend>) -> Result<Status, Refusal> {
+    let NewUser { username, password, role } = user.0;
+    check_password(&username, &password)?;
+    run(admin, backend, AdminCommand::AddUser { username, password, action: LoginAction::Accept(role) }).await?;
+    Ok(Status::Created)
+}
+
+#[delete("/api/admin/users/<username>")]
+pub async fn delete_user(username: String, admin: AdminUser, backend: &State<Backend>) -> Result<Status, Status> {
+    run(admin, backend, AdminCommand::DeleteUser(username)).await?;
+    Ok(Status::NoContent)
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct NewRole {
+    role: Role,
+}
+
+#[put("/api/admin/users/<username>/role", data = "<role>")]
+pub async fn set_role(username: String, role: Json<NewRole>, admin: AdminUser, backend: &State<Backend>) -> Result<Status, Status> {
+    run(admin, backend, AdminCommand::SetAction { username, action: LoginAction::Accept(role.0.role) }).await?;
+    Ok(Status::NoContent)
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct NewPassword {
+    password: String,
+}
+
+#[put("/api/admin/users/<username>/password", data = "<password>")]
+pub async fn reset_password(username: String, password: Json<NewPassword>, admin: AdminUser, backend: &State<Backend>) -> Result<Status, Refusal> {
+    let password = password.0.password;
+    check_password(&username, &password)?;
+    run(admin, backend, AdminCommand::SetPassword { username, password }).await?;
+    Ok(Status::NoContent)
+}
+
+/// Server-sent events for a live dashboard: every login attempt and user
+/// change, as it happens. The stream ends when the auth server goes away.
+#[get("/api/events")]
+pub async fn events(admin: AdminUser, backend: &State<Backend>, mut shutdown: Shutdown) -> Result<EventStream![], Status> {
+    let mut subscription = match backend.subscribe(&admin.0.token).await {
+        Ok(Some(subscription)) => subscription,
+        Ok(None) => return Err(Status::Forbidden),
+        Err(..) => return Err(Status::ServiceUnavailable),
+    };
+    Ok(EventStream! {
+        loop {
+            let event = rocket::tokio::select! {
+                event = subscription.next() => event,
+                _ = &mut shutdown => break,
+            };
+            match event {
+                Ok(event) => yield SseEvent::json(&event),
+                Err(..) => break,
+            }
+        }
+    })
+}
@@ -0,0 +1,59 @@
+use std::time::Duration;
+use rocket::fairing::AdHoc;
+use rocket::serde::Deserialize;
+use auth_server::pool::{Pool, PoolConfig};
+
+/// The pool of connections to the auth server. Handlers check connections out of it.
+pub type Backend = Pool;
+
+/// Where the auth server is and how patient to be with it, from Rocket.toml
+/// (or `ROCKET_BACKEND_*` environment variables).
+#[derive(Deserialize, Clone, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct BackendConfig {
+    #[serde(rename = "backend_address")]
+    address: String,
+    #[serde(rename = "backend_pool_size")]
+    pool_size: usize,
+    #[serde(rename = "backend_checkout_timeout_ms")]
+    checkout_timeout_ms: u64,
+    #[serde(rename = "backend_connect_timeout_ms")]
+    connect_timeout_ms: u64,
+    #[serde(rename = "backend_read_timeout_ms")]
+    read_timeout_ms: u64,
+    #[serde(rename = "backend_connect_retries")]
+    connect_retries: u32,
+}
+
+impl From<BackendConfig> for PoolConfig {
+    fn from(config: BackendConfig) -> Self {
+        Self {
+            address: config.address,
+            size: config.pool_size,
+            checkout_timeout: Duration::from_millis(config.checkout_timeout_ms),
+            connect_timeout: Duration::from_millis(config.connect_timeout_ms),
+            read_timeout: Duration::from_millis(config.read_timeout_ms),
+            connect_retries: config.connect_retries,
+            ..Default::default()
+        }
+    }
+}
+
+/// Read the backend settings and set up the pool at launch. Bad settings stop
+/// the launch.
+pub fn stage() -> AdHoc {
+    AdHoc::try_on_ignite("Auth backend", |rocket| async {
+        let config: PoolConfig = match rocket.figment().extract::<BackendConfig>() {
+            Ok(config) => config.into(),
+            Err(e) => {
+                rocket::config::pretty_print_error(e);
+                return Err(rocket);
+            }
+        };
+        if let Err(e) = config.validate() {
+            println!("Invalid backend configuration: {e}");
+            return Err(rocket);
+        }
+        Ok(rocket.manage(Pool::new(config)))
+    })
+}
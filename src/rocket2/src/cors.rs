@@ -0,0 +1,107 @@
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::http::{Header, Status};
+use rocket::serde::Deserialize;
+use rocket::{Build, Request, Response, Rocket};
+use crate::csrf;
+
+/// Which other sites may call the API, from Rocket.toml.
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct CorsConfig {
+    #[serde(rename = "cors_allowed_origins", default)]
+    origins: Vec<String>,
+    #[serde(rename = "cors_allowed_methods", default)]
+    methods: Vec<String>,
+}
+
+/// How an origin got past the allow list.
+#[derive(Debug, PartialEq)]
+enum Allowed {
+    /// Listed by name, so it may send the user's cookies
+    Listed,
+    /// Only matched `"*"`, so anyone may call, but never with credentials
+    Anyone,
+}
+
+impl CorsConfig {
+    fn allows(&self, origin: &str) -> Option<Allowed> {
+        if self.origins.iter().any(|allowed| allowed == origin) {
+            Some(Allowed::Listed)
+        } else if self.origins.iter().any(|allowed| allowed == "*") {
+            Some(Allowed::Anyone)
+        } else {
+            None
+        }
+    }
+}
+
+/// Browsers ask before sending most cross-origin requests, with an `OPTIONS`
+/// request to the same path. The fairing adds the answer's headers.
+#[options("/<_..>")]
+pub fn preflight() -> Status {
+    Status::NoContent
+}
+
+/// Adds CORS headers to responses for allowed origins, so a frontend hosted
+/// somewhere else can call the API.
+pub struct Cors;
+
+#[rocket::async_trait]
+impl Fairing for Cors {
+    fn info(&self) -> Info {
+        Info { name: "CORS", kind: Kind::Ignite | Kind::Response }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        match rocket.figment().extract::<CorsConfig>() {
+            Ok(config) => Ok(rocket.manage(config).mount("/", routes![preflight])),
+            Err(e) => {
+                rocket::config::pretty_print_error(e);
+                Err(rocket)
+            }
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let config = request.rocket().state::<CorsConfig>().expect("CorsConfig is managed at launch");
+        let Some(origin) = request.headers().get_one("Origin") else { return };
+        match config.allows(origin) {
+            None => return,
+            // Echo the origin rather than sending "*", which browsers reject alongside credentials
+            Some(Allowed::Listed) => {
+                response.set_header(Header::new("Access-Control-Allow-Origin", origin.to_string()));
+                response.set_header(Header::new("Access-Control-Allow-Credentials", "true"));
+            }
+            Some(Allowed::Anyone) => {
+                response.set_header(Header::new("Access-Control-Allow-Origin", "*"));
+            }
+        }
+        response.set_header(Header::new("Access-Control-Allow-Methods", config.methods.join(", ")));
+        response.set_header(Header::new("Access-Control-Allow-Headers", format!("Content-Type, Authorization, {}", csrf::HEADER)));
+        response.set_header(Header::new("Access-Control-Expose-Headers", "X-Request-Id, Retry-After"));
+        response.set_header(Header::new("Vary", "Origin"));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config(origins: &[&str]) -> CorsConfig {
+        CorsConfig { origins: origins.iter().map(|o| o.to_string()).collect(), methods: Vec::new() }
+    }
+
+    #[test]
+    fn listed_origins_only() {
+        let config = config(&["http://localhost:3000"]);
+        assert_eq!(config.allows("http://localhost:3000"), Some(Allowed::Listed));
+        assert_eq!(config.allows("https://evil.example.com"), None);
+    }
+
+    #[test]
+    fn wildcard_never_allows_credentials() {
+        let config = config(&["http://localhost:3000", "*"]);
+        assert_eq!(config.allows("http://localhost:3000"), Some(Allowed::Listed));
+        assert_eq!(config.allows("https://evil.example.com"), Some(Allowed::Anyone));
+    }
+}
@@ -0,0 +1,98 @@
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::http::{Cookie, CookieJar, Method, Status};
+use rocket::serde::{json::Json, uuid::Uuid, Serialize};
+use rocket::{Build, Data, Request, Rocket};
+
+/// The private cookie holding this browser's token.
+const COOKIE: &str = "csrf";
+
+/// Pages send the token back in this header. Scripts on other sites can't read
+/// it from our pages, so they can't forge it.
+pub const HEADER: &str = "X-CSRF-Token";
+
+/// This browser's token, creating one if it doesn't have one yet.
+pub fn token(cookies: &CookieJar<'_>) -> String {
+    if let Some(cookie) = cookies.get_private(COOKIE) {
+        return cookie.value().to_string();
+    }
+    let token = Uuid::new_v4().to_string();
+    cookies.add_private(Cookie::new(COOKIE, token.clone()));
+    token
+}
+
+/// Whether a request may go ahead. Reading is always fine, and so is anything
+/// carrying a bearer token, since browsers never add those by themselves.
+/// Everything else has to send back the token from its cookie.
+fn accepts(method: Method, bearer: bool, sent: Option<&str>, expected: Option<&str>) -> bool {
+    if matches!(method, Method::Get | Method::Head | Method::Options) || bearer {
+        return true;
+    }
+    matches!((sent, expected), (Some(sent), Some(expected)) if sent == expected)
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct CsrfToken {
+    token: String,
+}
+
+/// For scripts that aren't loaded from one of our pages.
+#[get("/api/csrf")]
+pub fn csrf_token(cookies: &CookieJar<'_>) -> Json<CsrfToken> {
+    Json(CsrfToken { token: token(cookies) })
+}
+
+/// Requests without a valid token are sent here instead of where they were going.
+#[post("/api/csrf-rejected")]
+pub fn rejected() -> Status {
+    Status::Forbidden
+}
+
+/// Turns away state-changing requests that don't carry this browser's token,
+/// before they reach a handler.
+pub struct Csrf;
+
+#[rocket::async_trait]
+impl Fairing for Csrf {
+    fn info(&self) -> Info {
+        Info { name: "CSRF protection", kind: Kind::Ignite | Kind::Request }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        Ok(rocket.mount("/", routes![csrf_token, rejected]))
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut Data<'_>) {
+        let bearer = request.headers().get_one("Authorization").is_some_and(|h| h.starts_with("Bearer "));
+        let expected = request.cookies().get_private(COOKIE).map(|cookie| cookie.value().to_string());
+        if accepts(request.method(), bearer, request.headers().get_one(HEADER), expected.as_deref()) {
+            return;
+        }
+        // Fairings can't answer a request themselves, so reroute it to one that refuses
+        request.set_method(Method::Post);
+        request.set_uri(uri!(rejected));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_need_no_token() {
+        assert!(accepts(Method::Get, false, None, None));
+    }
+
+    #[test]
+    fn writes_need_the_matching_token() {
+        assert!(!accepts(Method::Post, false, None, Some("abc")));
+        assert!(!accepts(Method::Post, false, Some("abc"), None));
+        assert!(!accepts(Method::Delete, false, Some("abc"), Some("xyz")));
+        assert!(accepts(Method::Post, false, Some("abc"), Some("abc")));
+    }
+
+    #[test]
+    fn bearer_tokens_are_exempt() {
+        assert!(accepts(Method::Post, true, None, None));
+    }
+}
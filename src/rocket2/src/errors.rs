@@ -0,0 +1,84 @@
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{Header, Status};
+use rocket::serde::{json::Json, uuid::Uuid, Serialize};
+use rocket::{Catcher, Request, Response};
+
+/// A unique ID for each request, so a failure the caller reports can be found
+/// in the server's logs. Made on first use, then cached for the rest of the request.
+pub struct RequestId(pub String);
+
+impl RequestId {
+    pub fn of<'r>(request: &'r Request<'_>) -> &'r str {
+        &request.local_cache(|| RequestId(Uuid::new_v4().to_string())).0
+    }
+}
+
+/// Sends every request's ID back in an `X-Request-Id` header.
+pub struct RequestIds;
+
+#[rocket::async_trait]
+impl Fairing for RequestIds {
+    fn info(&self) -> Info {
+        Info { name: "Request IDs", kind: Kind::Response }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        response.set_header(Header::new("X-Request-Id", RequestId::of(request).to_string()));
+    }
+}
+
+/// For `map_err` on calls to the auth server: the only way those fail is the
+/// server being unreachable, which the 503 catcher explains to the caller.
+pub fn backend_down(e: anyhow::Error) -> Status {
+    tracing::warn!("auth server unavailable: {e}");
+    Status::ServiceUnavailable
+}
+
+/// What API callers get back instead of Rocket's HTML error pages.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ApiError {
+    code: u16,
+    message: String,
+    request_id: String,
+}
+
+fn error(status: Status, request: &Request<'_>, message: &str) -> Json<ApiError> {
+    Json(ApiError { code: status.code, message: message.to_string(), request_id: RequestId::of(request).to_string() })
+}
+
+#[catch(401)]
+fn unauthorized(request: &Request<'_>) -> Json<ApiError> {
+    error(Status::Unauthorized, request, "You need to log in first")
+}
+
+#[catch(404)]
+fn not_found(request: &Request<'_>) -> Json<ApiError> {
+    error(Status::NotFound, request, "There's nothing here")
+}
+
+#[catch(422)]
+fn unprocessable(request: &Request<'_>) -> Json<ApiError> {
+    error(Status::UnprocessableEntity, request, "The request was understood, but isn't valid")
+}
+
+#[catch(503)]
+fn unavailable(request: &Request<'_>) -> Json<ApiError> {
+    error(Status::ServiceUnavailable, request, "The login service isn't available right now")
+}
+
+#[catch(500)]
+fn internal_error(request: &Request<'_>) -> Json<ApiError> {
+    error(Status::InternalServerError, request, "Something went wrong on our side")
+}
+
+/// Everything else gets the standard description of its status code.
+#[catch(default)]
+fn other(status: Status, request: &Request<'_>) -> Json<ApiError> {
+    error(status, request, status.reason().unwrap_or("Unknown error"))
+}
+
+/// Register these under `/api`, so pages meant for browsers keep the HTML ones.
+pub fn catchers() -> Vec<Catcher> {
+    catchers![unauthorized, not_found, unprocessable, unavailable, internal_error, other]
+}
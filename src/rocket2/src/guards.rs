@@ -0,0 +1,47 @@
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use auth_json::Role;
+use auth_server::jwt::JwtConfig;
+use crate::session::{CookieSession, Identity};
+
+/// Anyone who is logged in: either with an `Authorization: Bearer` token from
+/// `/api/login`, or with the session cookie. Routes taking one answer 401 to
+/// everyone else.
+pub struct AnyUser(pub Identity);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AnyUser {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
+        let identity = match request.headers().get_one("Authorization") {
+            Some(header) => {
+                let jwt = request.rocket().state::<JwtConfig>().expect("JwtConfig is attached at launch");
+                header.strip_prefix("Bearer ").and_then(|token| jwt.verify(token))
+            }
+            None => Identity::from_cookies(request.cookies()),
+        };
+        match identity {
+            Some(identity) => Outcome::Success(AnyUser(identity)),
+            None => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
+
+/// A logged-in admin. Routes taking one answer 401 to anyone not logged in,
+/// and 403 to users who aren't admins.
+pub struct AdminUser(pub Identity);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminUser {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
+        match request.guard::<AnyUser>().await {
+            Outcome::Success(AnyUser(identity)) if identity.role == Role::Admin => Outcome::Success(AdminUser(identity)),
+            Outcome::Success(..) => Outcome::Error((Status::Forbidden, ())),
+            Outcome::Error(e) => Outcome::Error(e),
+            Outcome::Forward(status) => Outcome::Forward(status),
+        }
+    }
+}
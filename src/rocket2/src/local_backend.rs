@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use rocket::fairing::AdHoc;
+use rocket::serde::json;
+use rocket::tokio::sync::broadcast;
+use auth_json::{DeniedReason, LoginAction, Role, User};
+use auth_server::pool::CircuitState;
+use auth_server::{InProcess, service::Call, protocol::{Event, Request, Response}};
+
+/// Answers the same requests as the auth server, with the auth server's own
+/// logic, but in-process, against users loaded at launch. Changes only last
+/// until rocket2 stops.
+pub struct Backend {
+    server: InProcess,
+}
+
+/// Load the users named by `users_file` in Rocket.toml, or a few demo users if
+/// there's no such file. A file that won't parse stops the launch.
+pub fn stage() -> AdHoc {
+    AdHoc::try_on_ignite("Local auth backend", |rocket| async {
+        let path: String = rocket.figment().extract_inner("users_file").unwrap_or_else(|_| "users.json".to_string());
+        let users = match std::fs::read_to_string(&path) {
+            Ok(text) => match json::from_str(&text) {
+                Ok(users) => users,
+                Err(e) => {
+                    println!("Unable to load {path}: {e}");
+                    return Err(rocket);
+                }
+            },
+            Err(..) => demo_users(),
+        };
+        Ok(rocket.manage(Backend { server: InProcess::new(users) }))
+    })
+}
+
+fn demo_users() -> HashMap<String, User> {
+    [
+        User::new("herbert", "password", LoginAction::Accept(Role::Admin)),
+        User::new("bob", "password", LoginAction::Accept(Role::User)),
+        User::new("fred", "password", LoginAction::Denied(DeniedReason::PasswordExpired)),
+    ]
+    .into_iter()
+    .map(|user| (user.username.clone(), user))
+    .collect()
+}
+
+impl Backend {
+    /// There's nothing to connect to, so this never fails; it's here so handlers
+    /// don't care which backend they're using.
+    pub async fn get(&self) -> anyhow::Result<Connection<'_>> {
+        Ok(Connection { backend: self })
+    }
+
+    /// Follow login attempts. Only admins may.
+    pub async fn subscribe(&self, token: &str) -> anyhow::Result<Option<Subscription>> {
+        Ok(self.server.subscribe(token).map(|events| Subscription { events }))
+    }
+
+    /// Always closed: there's no server to be down.
+    pub fn circuit(&self) -> CircuitState {
+        CircuitState::Closed
+    }
+}
+
+/// Stands in for a pooled connection.
+pub struct Connection<'a> {
+    backend: &'a Backend,
+}
+
+impl Connection<'_> {
+    pub async fn call(&mut self, request: &Request) -> anyhow::Result<Response> {
+        Ok(self.backend.server.call(request.clone()).await)
+    }
+}
+
+impl Call for Connection<'_> {
+    async fn call(&mut self, request: &Request) -> anyhow::Result<Response> {
+        Connection::call(self, request).await
+    }
+}
+
+/// Stands in for a subscribed connection.
+pub struct Subscription {
+    events: broadcast::Receiver<Event>,
+}
+
+impl Subscription {
+    pub async fn next(&mut self) -> anyhow::Result<Event> {
+        Ok(self.events.recv().await?)
+    }
+}
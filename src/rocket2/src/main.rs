@@ -1,10 +1,84 @@
 #[macro_use] extern crate rocket;
-use rocket::fs::NamedFile;
+use rocket::fairing::AdHoc;
+use rocket::fs::FileServer;
+use rocket::http::{CookieJar, Status};
+use rocket::response::Redirect;
 use rocket::serde::{json::Json, Deserialize, Serialize};
+use rocket::{Either, State};
+use rocket_dyn_templates::{context, Template};
+use auth_json::{DeniedReason, LoginAction, Role};
+use auth_server::{policy, protocol::{AdminCommand, AdminResponse, PasswordChange, Request, Response, Session}};
+use admin::Refusal;
+use backend::Backend;
+use errors::backend_down;
+use guards::AnyUser;
+use metrics::{LoginOutcome, Metrics};
+use rate_limit::LoginAllowed;
+use auth_server::{jwt::JwtConfig, service::ServiceAccount};
+use session::{CookieSession, Identity};
+
+// Remembering who is logged in, with a private cookie
+mod session;
+
+// Where logins are checked: a pool of connections to the auth server...
+#[cfg(not(feature = "local-auth"))]
+mod backend;
+
+// ...or, with the local-auth feature, right here in-process
+#[cfg(feature = "local-auth")]
+#[path = "local_backend.rs"]
+mod backend;
+
+// Request guards for routes that need someone logged in
+mod guards;
+
+// Request IDs, and JSON error bodies for the API
+mod errors;
+
+// Slowing down clients that try to log in too often
+mod rate_limit;
+
+// User management, for admins. Public, so the URI macros Rocket generates for
+// each route count as used
+pub mod admin;
+
+// Letting frontends hosted elsewhere call the API. Public for the same reason as admin
+pub mod cors;
+
+// Logging each request with tracing
+mod request_log;
+
+// Tokens that prove a form post came from one of our own pages. Public for the same reason as admin
+pub mod csrf;
+
+// /healthz and /metrics, so the web tier can be watched. Public for the same reason as admin
+pub mod metrics;
 
 #[get("/")]
-pub async fn login_page<'a>() -> NamedFile {
-  NamedFile::open("login.html").await.unwrap()
+pub fn login_page(cookies: &CookieJar<'_>) -> Either<Template, Redirect> {
+    match Identity::from_cookies(cookies) {
+        Some(..) => Either::Right(Redirect::to(uri!(welcome_page))),
+        None => Either::Left(Template::render("login", context! { csrf: csrf::token(cookies) })),
+    }
+}
+
+#[get("/welcome")]
+pub fn welcome_page(cookies: &CookieJar<'_>) -> Either<Template, Redirect> {
+    match Identity::from_cookies(cookies) {
+        Some(identity) => Either::Left(Template::render("welcome", context! {
+            username: identity.username,
+            role: format!("{:?}", identity.role),
+            csrf: csrf::token(cookies),
+        })),
+        None => Either::Right(Redirect::to(uri!(login_page))),
+    }
+}
+
+/// Where the login page sends users whose password was right, but who still can't come in.
+#[get("/denied?<reason>")]
+pub fn denied_page(reason: Option<&str>) -> Template {
+    let reason = reason.unwrap_or("You aren't allowed to see that page.");
+    Template::render("denied", context! { reason })
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -14,26 +88,162 @@ pub struct Login {
     password: String,
 }
 
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct LoginResponse {
+    success: bool,
+    role: Option<Role>,
+    /// Why a correct password still didn't let you in
+    reason: Option<DeniedReason>,
+    /// Send this as `Authorization: Bearer <token>` instead of using the session cookie
+    token: Option<String>,
+}
+
+/// 200 with the user's role if they're in, 423 (Locked) with the reason if the
+/// password was right but the account can't be used, and 401 otherwise. 429 if
+/// this client has tried too often lately.
 #[post("/api/login", data = "<user>")]
-pub async fn login(user: Json<Login>) {
-    use rocket::tokio::io::{AsyncWriteExt, AsyncReadExt};
-    use rocket::tokio::net::TcpStream;
+pub async fn login(_allowed: LoginAllowed, user: Json<Login>, cookies: &CookieJar<'_>, backend: &State<Backend>, jwt: &State<JwtConfig>, metrics: &State<Metrics>) -> Result<(Status, Json<LoginResponse>), Status> {
+    let username = user.0.username;
+    let login_attempt = Request::Login {
+        username: username.clone(),
+        password: user.0.password,
+    };
 
-    use auth_json::*;
-    let login_attempt = user.0;
+    let mut client = backend.get().await.map_err(backend_down)?;
+    let response = client.call(&login_attempt).await.map_err(backend_down)?;
 
-    let mut stream = TcpStream::connect("127.0.0.1:8123").await.unwrap();
-    let message = bincode::serialize(&login_attempt).unwrap();
-    stream.write_all(&message).await.unwrap();
+    let (status, response) = match response {
+        Response::Login { action: Some(LoginAction::Accept(role)), token: Some(token) } => {
+            metrics.login(LoginOutcome::Accepted);
+            let identity = Identity { username, role: role.clone(), token };
+            identity.save(cookies);
+            (Status::Ok, LoginResponse { success: true, role: Some(role), reason: None, token: Some(jwt.issue(&identity)) })
+        }
+        Response::Login { action: Some(LoginAction::Denied(reason)), .. } => {
+            metrics.login(LoginOutcome::Denied);
+            (Status::Locked, LoginResponse { success: false, role: None, reason: Some(reason), token: None })
+        }
+        _ => {
+            metrics.login(LoginOutcome::Failed);
+            (Status::Unauthorized, LoginResponse { success: false, role: None, reason: None, token: None })
+        }
+    };
+    Ok((status, Json(response)))
+}
+
+#[post("/api/logout")]
+pub fn logout(cookies: &CookieJar<'_>) -> Status {
+    Identity::forget(cookies);
+    Status::NoContent
+}
+
+/// Who your token or session cookie says you are - as long as the auth server
+/// agrees the session is still live.
+#[get("/api/me")]
+pub async fn me(user: AnyUser, cookies: &CookieJar<'_>, backend: &State<Backend>) -> Result<Json<Session>, Status> {
+    let mut client = backend.get().await.map_err(backend_down)?;
+    match client.call(&Request::ValidateToken(user.0.token)).await.map_err(backend_down)? {
+        Response::Session(Some(session)) => Ok(Json(session)),
+        _ => {
+            // The session expired, or the server forgot it; the cookie is no use now
+            Identity::forget(cookies);
+            Err(Status::Unauthorized)
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct RegisterResponse {
+    success: bool,
+    /// Why the registration was refused
+    problems: Vec<String>,
+}
+
+impl RegisterResponse {
+    fn refused(problem: &str) -> Json<Self> {
+        Json(Self { success: false, problems: vec![problem.to_string()] })
+    }
 
-    let mut buf = vec![0; 1024];
-    let n = stream.read(&mut buf).await.unwrap();
-    let response: Option<LoginAction> = bincode::deserialize(&buf[0..n]).unwrap();
+    /// A 422 listing every way a password breaks the policy.
+    pub fn rejected(problems: Vec<String>) -> (Status, Json<Self>) {
+        (Status::UnprocessableEntity, Json(Self { success: false, problems }))
+    }
+}
+
+/// Create a new user account. 201 if it was created, 422 if the password isn't
+/// good enough, and 409 if the username is taken.
+#[post("/api/register", data = "<user>")]
+pub async fn register(user: Json<Login>, service: &State<ServiceAccount>, backend: &State<Backend>) -> Result<(Status, Json<RegisterResponse>), Status> {
+    let Login { username, password } = user.0;
+    let problems = policy::password_problems(&username, &password);
+    if !problems.is_empty() {
+        return Ok(RegisterResponse::rejected(problems.into_iter().map(String::from).collect()));
+    }
 
-    println!("{response:?}");
+    let mut client = backend.get().await.map_err(backend_down)?;
+    let Ok(token) = service.login(&mut client).await else {
+        return Ok((Status::InternalServerError, RegisterResponse::refused("Registration isn't available right now")));
+    };
+    let command = AdminCommand::AddUser { username, password, action: LoginAction::Accept(Role::User) };
+    match client.call(&Request::Admin { token, command }).await.map_err(backend_down)? {
+        Response::Admin(AdminResponse::Done) => Ok((Status::Created, Json(RegisterResponse { success: true, problems: Vec::new() }))),
+        Response::Admin(AdminResponse::UserExists) => Ok((Status::Conflict, RegisterResponse::refused("That username is taken"))),
+        _ => Ok((Status::InternalServerError, RegisterResponse::refused("Registration isn't available right now"))),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct NewPassword {
+    current_password: String,
+    new_password: String,
+}
+
+/// Change your own password, which also signs you out everywhere else. 204 if
+/// it worked, 422 if the new password isn't good enough, 403 if the current
+/// password is wrong and 401 if your session has ended. Problems are reported
+/// the same way as for `register`.
+#[post("/api/change-password", data = "<change>")]
+pub async fn change_password(user: AnyUser, change: Json<NewPassword>, backend: &State<Backend>) -> Result<Status, Refusal> {
+    let NewPassword { current_password, new_password } = change.0;
+    // The auth server enforces the policy; checking here just saves a round trip
+    let problems = policy::password_problems(&user.0.username, &new_password);
+    if !problems.is_empty() {
+        return Err(Refusal::Rejected(RegisterResponse::rejected(problems.into_iter().map(String::from).collect())));
+    }
+
+    let mut client = backend.get().await.map_err(backend_down)?;
+    let request = Request::ChangePassword { token: user.0.token, current_password, new_password };
+    match client.call(&request).await.map_err(backend_down)? {
+        Response::PasswordChange(PasswordChange::Done) => Ok(Status::NoContent),
+        Response::PasswordChange(PasswordChange::WrongPassword) => Err(Refusal::Rejected((Status::Forbidden, RegisterResponse::refused("The current password is wrong")))),
+        Response::PasswordChange(PasswordChange::NotLoggedIn) => Err(Refusal::Rejected((Status::Unauthorized, RegisterResponse::refused("Your session has ended")))),
+        Response::PasswordChange(PasswordChange::Rejected(problems)) => Err(Refusal::Rejected(RegisterResponse::rejected(problems))),
+        _ => Err(Refusal::Rejected((Status::InternalServerError, RegisterResponse::refused("Passwords can't be changed right now")))),
+    }
 }
 
 #[launch]
 fn rocket() -> _ {
-    rocket::build().mount("/", routes![login_page, login])
+    // Rocket logs its own messages; this is for ours
+    tracing_subscriber::fmt().with_target(false).init();
+
+    rocket::build()
+        .mount("/", routes![login_page, welcome_page, denied_page, login, logout, me, register, change_password])
+        .mount("/static", FileServer::from("static"))
+        .mount("/", admin::routes())
+        .mount("/", metrics::routes())
+        .register("/api", errors::catchers())
+        .attach(backend::stage())
+        .attach(AdHoc::config::<ServiceAccount>())
+        .attach(AdHoc::config::<JwtConfig>())
+        .attach(Template::fairing())
+        .attach(errors::RequestIds)
+        .attach(rate_limit::LoginRateLimit)
+        .attach(cors::Cors)
+        .attach(csrf::Csrf)
+        .attach(metrics::RequestMetrics)
+        .attach(request_log::RequestLog)
 }
\ No newline at end of file
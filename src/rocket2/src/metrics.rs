@@ -0,0 +1,187 @@
+use std::{sync::atomic::{AtomicU64, Ordering}, time::Instant};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Status;
+use rocket::serde::{json::Json, Serialize};
+use rocket::{Build, Data, Request, Response, Rocket, Route, State};
+use auth_server::{pool::CircuitState, protocol::{Request as AuthRequest, Response as AuthResponse}};
+use crate::backend::Backend;
+
+pub fn routes() -> Vec<Route> {
+    routes![healthz, metrics]
+}
+
+/// Upper bounds of the latency histogram's buckets, in milliseconds. Anything
+/// slower goes in one last bucket.
+const LATENCY_BUCKETS_MS: [u64; 7] = [1, 5, 10, 50, 100, 500, 1000];
+
+/// How logins turned out.
+#[derive(Clone, Copy)]
+pub enum LoginOutcome {
+    /// Correct password, and the user was let in
+    Accepted,
+    /// Correct password, but the account is expired or locked
+    Denied,
+    /// Unknown user or wrong password
+    Failed,
+    /// Refused because the client tried too often
+    Throttled,
+}
+
+/// Counters for everything rocket2 does, shared by all requests.
+pub struct Metrics {
+    started: Instant,
+    requests: AtomicU64,
+    /// Responses by status class: 1xx, 2xx, 3xx, 4xx, 5xx
+    responses: [AtomicU64; 5],
+    latency: [AtomicU64; LATENCY_BUCKETS_MS.len() + 1],
+    logins: [AtomicU64; 4],
+}
+
+impl Metrics {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            requests: AtomicU64::new(0),
+            responses: Default::default(),
+            latency: Default::default(),
+            logins: Default::default(),
+        }
+    }
+
+    pub fn login(&self, outcome: LoginOutcome) {
+        self.logins[outcome as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn response(&self, status: Status, started: Instant) {
+        let class = (status.code as usize / 100).clamp(1, 5) - 1;
+        self.responses[class].fetch_add(1, Ordering::Relaxed);
+
+        let ms = started.elapsed().as_millis() as u64;
+        let bucket = LATENCY_BUCKETS_MS.iter().position(|&limit| ms <= limit).unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.latency[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> MetricsSnapshot {
+        let count = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let latency = self.latency
+            .iter()
+            .enumerate()
+            .map(|(i, counter)| LatencyBucket { le_ms: LATENCY_BUCKETS_MS.get(i).copied(), count: count(counter) })
+            .collect();
+        MetricsSnapshot {
+            uptime_secs: self.started.elapsed().as_secs(),
+            requests: count(&self.requests),
+            responses: ResponseCounts {
+                informational: count(&self.responses[0]),
+                success: count(&self.responses[1]),
+                redirect: count(&self.responses[2]),
+                client_error: count(&self.responses[3]),
+                server_error: count(&self.responses[4]),
+            },
+            latency,
+            logins: LoginCounts {
+                accepted: count(&self.logins[LoginOutcome::Accepted as usize]),
+                denied: count(&self.logins[LoginOutcome::Denied as usize]),
+                failed: count(&self.logins[LoginOutcome::Failed as usize]),
+                throttled: count(&self.logins[LoginOutcome::Throttled as usize]),
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct MetricsSnapshot {
+    uptime_secs: u64,
+    requests: u64,
+    responses: ResponseCounts,
+    latency: Vec<LatencyBucket>,
+    logins: LoginCounts,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ResponseCounts {
+    informational: u64,
+    success: u64,
+    redirect: u64,
+    client_error: u64,
+    server_error: u64,
+}
+
+/// How many requests took at most `le_ms` milliseconds (and more than the
+/// previous bucket's limit). The last bucket has no limit.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct LatencyBucket {
+    le_ms: Option<u64>,
+    count: u64,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct LoginCounts {
+    accepted: u64,
+    denied: u64,
+    failed: u64,
+    throttled: u64,
+}
+
+/// When the current request arrived.
+struct Started(Instant);
+
+/// Sets up `Metrics` at launch, and times every request.
+pub struct RequestMetrics;
+
+#[rocket::async_trait]
+impl Fairing for RequestMetrics {
+    fn info(&self) -> Info {
+        Info { name: "Request metrics", kind: Kind::Ignite | Kind::Request | Kind::Response }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> rocket::fairing::Result {
+        Ok(rocket.manage(Metrics::new()))
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut Data<'_>) {
+        request.local_cache(|| Started(Instant::now()));
+        if let Some(metrics) = request.rocket().state::<Metrics>() {
+            metrics.requests.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let Started(started) = request.local_cache(|| Started(Instant::now()));
+        if let Some(metrics) = request.rocket().state::<Metrics>() {
+            metrics.response(response.status(), *started);
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Health {
+    backend: &'static str,
+    /// Whether the pool is trying to reach the auth server at all
+    circuit: CircuitState,
+}
+
+/// 200 if the auth backend is answering, 503 if it isn't.
+#[get("/healthz")]
+pub async fn healthz(backend: &State<Backend>) -> (Status, Json<Health>) {
+    let answering = match backend.get().await {
+        Ok(mut connection) => matches!(connection.call(&AuthRequest::Ping).await, Ok(AuthResponse::Pong)),
+        Err(..) => false,
+    };
+    let circuit = backend.circuit();
+    if answering {
+        (Status::Ok, Json(Health { backend: "up", circuit }))
+    } else {
+        (Status::ServiceUnavailable, Json(Health { backend: "down", circuit }))
+    }
+}
+
+#[get("/metrics")]
+pub fn metrics(metrics: &State<Metrics>) -> Json<MetricsSnapshot> {
+    Json(metrics.snapshot())
+}
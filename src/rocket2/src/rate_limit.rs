@@ -0,0 +1,174 @@
+use std::{collections::HashMap, net::{IpAddr, Ipv4Addr}, sync::Mutex, time::{Duration, Instant}};
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::http::{Header, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::serde::Deserialize;
+use rocket::{Build, Request, Response, Rocket};
+use crate::metrics::{LoginOutcome, Metrics};
+
+/// How hard login attempts are throttled, from Rocket.toml.
+#[derive(Deserialize, Clone, Copy)]
+#[serde(crate = "rocket::serde")]
+pub struct RateLimitConfig {
+    #[serde(rename = "login_burst")]
+    burst: u32,
+    #[serde(rename = "login_refill_secs")]
+    refill_secs: u64,
+}
+
+/// A token bucket: each attempt takes a token, and tokens trickle back in over time.
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Login attempts per client IP.
+pub struct LoginLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl LoginLimiter {
+    fn new(config: RateLimitConfig) -> Self {
+        Self { config, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    /// Take a token for `ip`, or say how long until one is available.
+    fn check(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        let burst = self.config.burst as f64;
+        let refill = Duration::from_secs(self.config.refill_secs).as_secs_f64();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        // Forget clients whose buckets have filled up again, so the map doesn't grow forever
+        if buckets.len() > 10_000 {
+            buckets.retain(|_, bucket| now.duration_since(bucket.updated).as_secs_f64() < refill * burst);
+        }
+
+        let bucket = buckets.entry(ip).or_insert(Bucket { tokens: burst, updated: now });
+        let refilled = now.duration_since(bucket.updated).as_secs_f64() / refill;
+        bucket.tokens = (bucket.tokens + refilled).min(burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) * refill))
+        }
+    }
+}
+
+/// Set on throttled requests, so the fairing can say when to come back.
+struct RetryAfter(Option<u64>);
+
+/// Routes taking a `LoginAllowed` answer 429 to clients that have tried too
+/// many times lately.
+pub struct LoginAllowed;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LoginAllowed {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
+        let limiter = request.rocket().state::<LoginLimiter>().expect("the rate limit fairing is attached");
+        // The peer's address, not client_ip(): that believes whatever X-Real-IP header the client sends
+        let ip = request.remote().map(|addr| addr.ip()).unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        match limiter.check(ip, Instant::now()) {
+            Ok(()) => Outcome::Success(LoginAllowed),
+            Err(wait) => {
+                if let Some(metrics) = request.rocket().state::<Metrics>() {
+                    metrics.login(LoginOutcome::Throttled);
+                }
+                request.local_cache(|| RetryAfter(Some(wait.as_secs_f64().ceil() as u64)));
+                Outcome::Error((Status::TooManyRequests, ()))
+            }
+        }
+    }
+}
+
+/// Sets up the `LoginLimiter` at launch, and adds `Retry-After` to throttled responses.
+pub struct LoginRateLimit;
+
+#[rocket::async_trait]
+impl Fairing for LoginRateLimit {
+    fn info(&self) -> Info {
+        Info { name: "Login rate limit", kind: Kind::Ignite | Kind::Response }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        match rocket.figment().extract::<RateLimitConfig>() {
+            Ok(config) => Ok(rocket.manage(LoginLimiter::new(config))),
+            Err(e) => {
+                rocket::config::pretty_print_error(e);
+                Err(rocket)
+            }
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        if let RetryAfter(Some(secs)) = request.local_cache(|| RetryAfter(None)) {
+            response.set_header(Header::new("Retry-After", secs.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn limiter() -> LoginLimiter {
+        LoginLimiter::new(RateLimitConfig { burst: 3, refill_secs: 10 })
+    }
+
+    #[test]
+    fn burst_then_throttle() {
+        let limiter = limiter();
+        let (ip, now) = (IpAddr::V4(Ipv4Addr::LOCALHOST), Instant::now());
+        for _ in 0..3 {
+            assert!(limiter.check(ip, now).is_ok());
+        }
+        assert_eq!(limiter.check(ip, now), Err(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn tokens_refill() {
+        let limiter = limiter();
+        let (ip, now) = (IpAddr::V4(Ipv4Addr::LOCALHOST), Instant::now());
+        for _ in 0..3 {
+            limiter.check(ip, now).unwrap();
+        }
+        assert!(limiter.check(ip, now + Duration::from_secs(10)).is_ok());
+        assert!(limiter.check(ip, now + Duration::from_secs(10)).is_err());
+    }
+
+    #[test]
+    fn clients_are_limited_separately() {
+        let limiter = limiter();
+        let now = Instant::now();
+        for _ in 0..3 {
+            limiter.check(IpAddr::V4(Ipv4Addr::LOCALHOST), now).unwrap();
+        }
+        assert!(limiter.check(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), now).is_ok());
+    }
+
+    #[rocket::post("/login")]
+    fn login(_allowed: LoginAllowed) {}
+
+    #[test]
+    fn forged_real_ip_is_still_limited() {
+        use rocket::local::blocking::Client;
+        let figment = rocket::Config::figment()
+            .merge(("login_burst", 1))
+            .merge(("login_refill_secs", 60));
+        let rocket = rocket::custom(figment)
+            .attach(LoginRateLimit)
+            .mount("/", rocket::routes![login]);
+        let client = Client::untracked(rocket).unwrap();
+        let peer = "192.0.2.1:4000".parse().unwrap();
+        let attempt = |real_ip: &'static str| {
+            client.post("/login").remote(peer).header(Header::new("X-Real-IP", real_ip)).dispatch().status()
+        };
+        assert_eq!(attempt("10.0.0.1"), Status::Ok);
+        assert_eq!(attempt("10.0.0.2"), Status::TooManyRequests);
+    }
+}
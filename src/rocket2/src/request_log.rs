@@ -0,0 +1,102 @@
+use std::{sync::atomic::{AtomicU64, Ordering}, time::Instant};
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::http::Status;
+use rocket::request::Outcome;
+use rocket::serde::Deserialize;
+use rocket::{Build, Data, Request, Response, Rocket};
+use crate::guards::AnyUser;
+
+/// How much to log, from Rocket.toml.
+#[derive(Deserialize, Clone, Copy)]
+#[serde(crate = "rocket::serde")]
+pub struct RequestLogConfig {
+    /// Log one in this many successful requests. Errors are always logged.
+    #[serde(rename = "request_log_every")]
+    every: u64,
+}
+
+/// Decides which requests get logged, so a busy server doesn't drown its logs.
+pub struct Sampler {
+    every: u64,
+    seen: AtomicU64,
+}
+
+impl Sampler {
+    fn new(config: RequestLogConfig) -> Self {
+        Self { every: config.every.max(1), seen: AtomicU64::new(0) }
+    }
+
+    fn should_log(&self, status: Status) -> bool {
+        status.code >= 400 || self.seen.fetch_add(1, Ordering::Relaxed).is_multiple_of(self.every)
+    }
+}
+
+/// When the current request arrived.
+struct Started(Instant);
+
+/// Logs every request (or a sample of them) with tracing: method, path,
+/// status, who made it and how long it took.
+pub struct RequestLog;
+
+#[rocket::async_trait]
+impl Fairing for RequestLog {
+    fn info(&self) -> Info {
+        Info { name: "Request log", kind: Kind::Ignite | Kind::Request | Kind::Response }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        match rocket.figment().extract::<RequestLogConfig>() {
+            Ok(config) => Ok(rocket.manage(Sampler::new(config))),
+            Err(e) => {
+                rocket::config::pretty_print_error(e);
+                Err(rocket)
+            }
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut Data<'_>) {
+        request.local_cache(|| Started(Instant::now()));
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let sampler = request.rocket().state::<Sampler>().expect("the request log fairing is attached");
+        let status = response.status();
+        if !sampler.should_log(status) {
+            return;
+        }
+
+        let Started(started) = request.local_cache(|| Started(Instant::now()));
+        let user = match request.guard::<AnyUser>().await {
+            Outcome::Success(AnyUser(identity)) => identity.username,
+            _ => "-".to_string(),
+        };
+        tracing::info!(
+            method = %request.method(),
+            path = %request.uri().path(),
+            status = status.code,
+            %user,
+            elapsed_us = started.elapsed().as_micros() as u64,
+            "request"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn samples_successes() {
+        let sampler = Sampler::new(RequestLogConfig { every: 3 });
+        let logged = (0..9).filter(|_| sampler.should_log(Status::Ok)).count();
+        assert_eq!(logged, 3);
+    }
+
+    #[test]
+    fn always_logs_errors() {
+        let sampler = Sampler::new(RequestLogConfig { every: 100 });
+        sampler.should_log(Status::Ok);
+        assert!(sampler.should_log(Status::InternalServerError));
+        assert!(sampler.should_log(Status::NotFound));
+    }
+}
@@ -0,0 +1,31 @@
+use rocket::http::{Cookie, CookieJar};
+use rocket::serde::json;
+pub use auth_server::jwt::Identity;
+
+/// The private cookie that remembers who is logged in.
+const COOKIE: &str = "session";
+
+/// Who is logged in, kept in a private cookie. Rocket encrypts and signs it, so
+/// the browser can neither read nor forge it. A trait, because `Identity` is
+/// shared with the other frontends and cookies are Rocket's business.
+pub trait CookieSession: Sized {
+    fn from_cookies(cookies: &CookieJar<'_>) -> Option<Self>;
+    fn save(&self, cookies: &CookieJar<'_>);
+    fn forget(cookies: &CookieJar<'_>);
+}
+
+impl CookieSession for Identity {
+    fn from_cookies(cookies: &CookieJar<'_>) -> Option<Self> {
+        cookies
+            .get_private(COOKIE)
+            .and_then(|cookie| json::from_str(cookie.value()).ok())
+    }
+
+    fn save(&self, cookies: &CookieJar<'_>) {
+        cookies.add_private(Cookie::new(COOKIE, json::to_string(self).unwrap()));
+    }
+
+    fn forget(cookies: &CookieJar<'_>) {
+        cookies.remove_private(COOKIE);
+    }
+}
@@ -0,0 +1,22 @@
+$("#doLogin").on('click', () => {
+    let newUser = {
+        username: $("#username").val(),
+        password: $("#password").val()
+    };
+    $.ajax({
+        type: "POST",
+        url: "/api/login",
+        data: JSON.stringify(newUser),
+        success: () => {
+            window.location.href = "/welcome";
+        },
+        error: (xhr) => {
+            let reason = xhr.responseJSON && xhr.responseJSON.reason;
+            if (xhr.status == 423 && reason) {
+                window.location.href = "/denied?reason=" + encodeURIComponent(JSON.stringify(reason));
+            } else {
+                $("#error").text("Invalid login");
+            }
+        }
+    })
+})
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use stable_vec::StableVec;
+
+const ITEMS: usize = 10_000;
+
+fn push(c: &mut Criterion) {
+    let mut group = c.benchmark_group("push");
+    group.bench_function("vec", |b| b.iter(|| (0 .. ITEMS).collect::<Vec<_>>()));
+    group.bench_function("hashmap", |b| b.iter(|| (0 .. ITEMS).map(|i| (i, i)).collect::<HashMap<_, _>>()));
+    group.bench_function("stable_vec", |b| b.iter(|| (0 .. ITEMS).collect::<StableVec<_>>()));
+    group.finish();
+}
+
+fn get(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get");
+    let vec: Vec<usize> = (0 .. ITEMS).collect();
+    group.bench_function("vec", |b| b.iter(|| (0 .. ITEMS).map(|i| vec[black_box(i)]).sum::<usize>()));
+
+    let map: HashMap<usize, usize> = (0 .. ITEMS).map(|i| (i, i)).collect();
+    group.bench_function("hashmap", |b| b.iter(|| (0 .. ITEMS).map(|i| map[&black_box(i)]).sum::<usize>()));
+
+    let mut store = StableVec::new();
+    let keys = store.push_all(0 .. ITEMS);
+    group.bench_function("stable_vec", |b| b.iter(|| keys.iter().map(|key| store[black_box(*key)]).sum::<usize>()));
+    group.finish();
+}
+
+/// Remove every other item and put new ones in. A Vec can't do this without
+/// shifting everything after the removed item, and invalidating its index.
+fn churn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("churn");
+    group.bench_function("hashmap", |b| b.iter_batched_ref(
+        || (0 .. ITEMS).map(|i| (i, i)).collect::<HashMap<_, _>>(),
+        |map| {
+            for i in (0 .. ITEMS).step_by(2) {
+                map.remove(&i);
+                map.insert(ITEMS + i, i);
+            }
+        },
+        BatchSize::SmallInput,
+    ));
+    group.bench_function("stable_vec", |b| b.iter_batched_ref(
+        || {
+            let mut store = StableVec::new();
+            let keys = store.push_all(0 .. ITEMS);
+            (store, keys)
+        },
+        |(store, keys)| {
+            for key in keys.iter().step_by(2) {
+                let item = store.remove(*key).unwrap();
+                store.push(item);
+            }
+        },
+        BatchSize::SmallInput,
+    ));
+    group.finish();
+}
+
+criterion_group!(benches, push, get, churn);
+criterion_main!(benches);
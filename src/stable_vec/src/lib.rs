@@ -0,0 +1,374 @@
+//! `StableVec`: a vector whose keys stay valid as other items come and go.
+//!
+//! Items live in slots. Removing an item empties its slot for the next `push`
+//! to reuse, and bumps the slot's generation, so the removed item's key can't
+//! reach whatever moves in next.
+//!
+//! ```
+//! use stable_vec::StableVec;
+//!
+//! let mut cats = StableVec::new();
+//! let tiddles = cats.push("Tiddles");
+//! let felix = cats.push("Felix");
+//! cats.remove(tiddles);
+//! let whiskers = cats.push("Whiskers");
+//!
+//! assert_eq!(cats[felix], "Felix");
+//! assert_eq!(cats.get(tiddles), None);
+//! assert_eq!(cats[whiskers], "Whiskers");
+//! ```
+
+use std::ops::{Index, IndexMut};
+
+/// A handle to an item in a `StableVec`. Slots are reused, so the key also
+/// records which occupant of the slot it was handed out for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    index: usize,
+    generation: u32,
+}
+
+impl Key {
+    /// The slot this key points at.
+    pub fn index(self) -> usize {
+        self.index
+    }
+}
+
+#[derive(Debug)]
+struct Slot<T> {
+    /// How many times this slot has been emptied
+    generation: u32,
+    item: Option<T>,
+}
+
+#[derive(Debug)]
+pub struct StableVec<T> {
+    data: Vec<Slot<T>>,
+    /// Removed slots, for `push` to reuse
+    free: Vec<usize>,
+    /// How many items haven't been removed
+    len: usize,
+}
+
+impl <T> StableVec<T> {
+    pub fn new() -> Self {
+        Self {
+            data: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// How many items haven't been removed.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// How many items fit, counting removed slots, before we need to allocate.
+    pub fn capacity(&self) -> usize {
+        self.data.capacity()
+    }
+
+    /// Remove every item. Slots are kept for reuse, and every key goes stale.
+    pub fn clear(&mut self) {
+        self.retain(|_, _| false);
+    }
+
+    /// Remove every item for which `keep` returns false.
+    pub fn retain(&mut self, mut keep: impl FnMut(Key, &mut T) -> bool) {
+        for index in 0 .. self.data.len() {
+            let slot = &mut self.data[index];
+            let key = Key { index, generation: slot.generation };
+            if let Some(item) = &mut slot.item {
+                if !keep(key, item) {
+                    self.remove(key);
+                }
+            }
+        }
+    }
+
+    pub fn push(&mut self, item: T) -> Key {
+        self.len += 1;
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.data[index];
+            slot.item = Some(item);
+            return Key { index, generation: slot.generation };
+        }
+        self.data.push(Slot { generation: 0, item: Some(item) });
+        Key { index: self.data.len() - 1, generation: 0 }
+    }
+
+    /// Push every item, returning their keys in the same order. `extend` and
+    /// `collect` do the same, but throw the keys away.
+    pub fn push_all(&mut self, items: impl IntoIterator<Item = T>) -> Vec<Key> {
+        items.into_iter().map(|item| self.push(item)).collect()
+    }
+
+    /// Take the item out, if `key` still refers to it, like `Option::take`.
+    /// Its slot's generation moves on, so `key` and any copies of it go stale.
+    /// Generations wrap around rather than overflow, so a key that has seen
+    /// its slot reused `u32::MAX + 1` times would find the new item.
+    pub fn remove(&mut self, key: Key) -> Option<T> {
+        let slot = self.data.get_mut(key.index)?;
+        if slot.generation != key.generation {
+            return None;
+        }
+        let item = slot.item.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(key.index);
+        self.len -= 1;
+        Some(item)
+    }
+
+    /// The item for `key`. None if it was removed, or if `key` was never
+    /// handed out, rather than panicking like indexing does.
+    pub fn get(&self, key: Key) -> Option<&T> {
+        let slot = self.data.get(key.index)?;
+        if slot.generation != key.generation {
+            return None;
+        }
+        slot.item.as_ref()
+    }
+
+    /// The item for `key` to change in place, or None like `get`.
+    pub fn get_mut(&mut self, key: Key) -> Option<&mut T> {
+        let slot = self.data.get_mut(key.index)?;
+        if slot.generation != key.generation {
+            return None;
+        }
+        slot.item.as_mut()
+    }
+
+    /// Every item that hasn't been removed, with its key.
+    pub fn iter(&self) -> impl Iterator<Item = (Key, &T)> {
+        self.data.iter().enumerate().filter_map(|(index, slot)| {
+            Some((Key { index, generation: slot.generation }, slot.item.as_ref()?))
+        })
+    }
+
+    /// Every item that hasn't been removed, with its key, to change in place.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Key, &mut T)> {
+        self.data.iter_mut().enumerate().filter_map(|(index, slot)| {
+            Some((Key { index, generation: slot.generation }, slot.item.as_mut()?))
+        })
+    }
+
+    /// The keys of every item that hasn't been removed.
+    pub fn keys(&self) -> impl Iterator<Item = Key> + '_ {
+        self.iter().map(|(key, _)| key)
+    }
+}
+
+/// Panics if the item was removed; use `get` to find out instead.
+impl<T> Index<Key> for StableVec<T> {
+    type Output = T;
+    fn index(&self, key: Key) -> &Self::Output {
+        self.get(key).expect("no item for this key: it was removed")
+    }
+}
+
+impl<T> IndexMut<Key> for StableVec<T> {
+    fn index_mut(&mut self, key: Key) -> &mut Self::Output {
+        self.get_mut(key).expect("no item for this key: it was removed")
+    }
+}
+
+impl<T> Default for StableVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Extend<T> for StableVec<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, items: I) {
+        for item in items {
+            self.push(item);
+        }
+    }
+}
+
+/// A fresh store's keys come out of `keys()` in the order the items went in.
+impl<T> FromIterator<T> for StableVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(items: I) -> Self {
+        let mut store = Self::new();
+        store.extend(items);
+        store
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn index_mut_replaces_items() {
+        let mut store = StableVec::new();
+        let a = store.push(1);
+        store[a] = 2;
+        assert_eq!(store[a], 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_mut_panics_for_removed_items() {
+        let mut store = StableVec::new();
+        let a = store.push(1);
+        store.remove(a);
+        store[a] = 2;
+    }
+
+    #[test]
+    fn get_mut_changes_in_place() {
+        let mut store = StableVec::new();
+        let a = store.push(1);
+        *store.get_mut(a).unwrap() += 1;
+        assert_eq!(store[a], 2);
+    }
+
+    #[test]
+    fn iterators_skip_removed_items() {
+        let mut store = StableVec::new();
+        let keys: Vec<Key> = (0 .. 5).map(|i| store.push(i * 10)).collect();
+        store.remove(keys[1]);
+        store.remove(keys[4]);
+
+        assert_eq!(store.iter().collect::<Vec<_>>(), [(keys[0], &0), (keys[2], &20), (keys[3], &30)]);
+        assert_eq!(store.keys().collect::<Vec<_>>(), [keys[0], keys[2], keys[3]]);
+        for (key, item) in store.iter_mut() {
+            *item += key.index;
+        }
+        assert_eq!(store.iter().map(|(_, item)| *item).collect::<Vec<_>>(), [0, 22, 33]);
+    }
+
+    #[test]
+    fn remove_hands_back_the_item() {
+        let mut store = StableVec::new();
+        let a = store.push("a".to_string());
+        assert_eq!(store.remove(a), Some("a".to_string()));
+        assert_eq!(store.remove(a), None);
+    }
+
+    #[test]
+    fn push_reuses_removed_slots() {
+        let mut store = StableVec::new();
+        let a = store.push("a");
+        store.push("b");
+        store.remove(a);
+        let c = store.push("c");
+        assert_eq!(c.index, a.index);
+        assert_eq!(store[c], "c");
+    }
+
+    #[test]
+    fn churn_doesnt_grow_the_store() {
+        let mut store = StableVec::new();
+        let mut live = Vec::new();
+        for i in 0 .. 10_000 {
+            live.push(store.push(i));
+            if live.len() > 10 {
+                store.remove(live.remove(0));
+            }
+        }
+        assert!(store.data.len() <= 11, "grew to {} slots", store.data.len());
+    }
+
+    #[test]
+    fn stale_keys_miss_the_new_item() {
+        let mut store = StableVec::new();
+        let a = store.push("a");
+        store.remove(a);
+        let b = store.push("b");
+        assert_eq!(b.index, a.index);
+
+        assert_eq!(store.get(a), None);
+        assert_eq!(store.get_mut(a), None);
+        // Removing through the stale key leaves the new item alone
+        assert_eq!(store.remove(a), None);
+        assert_eq!(store.get(b), Some(&"b"));
+    }
+
+    #[test]
+    fn generations_wrap_around() {
+        let mut store = StableVec::new();
+        let a = store.push("a");
+        // As if the slot had already been reused u32::MAX times
+        store.data[a.index].generation = u32::MAX;
+        let a = Key { index: a.index, generation: u32::MAX };
+        assert_eq!(store.remove(a), Some("a"));
+        let b = store.push("b");
+        assert_eq!(b.generation, 0);
+        assert_eq!(store.get(a), None);
+    }
+
+    #[test]
+    fn len_counts_remaining_items() {
+        let mut store = StableVec::new();
+        assert!(store.is_empty());
+        let a = store.push(1);
+        store.push(2);
+        assert_eq!(store.len(), 2);
+        store.remove(a);
+        store.remove(a);
+        assert_eq!(store.len(), 1);
+        store.push(3);
+        assert_eq!(store.len(), 2);
+        assert!(store.capacity() >= 2);
+    }
+
+    #[test]
+    fn retain_removes_the_rest() {
+        let mut store = StableVec::new();
+        let keys: Vec<Key> = (0 .. 6).map(|i| store.push(i)).collect();
+        store.retain(|_, item| *item % 2 == 0);
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.get(keys[1]), None);
+        assert_eq!(store.get(keys[2]), Some(&2));
+    }
+
+    #[test]
+    fn clear_makes_every_key_stale() {
+        let mut store = StableVec::new();
+        let a = store.push("a");
+        store.clear();
+        assert!(store.is_empty());
+        // The slot is reused, but the old key doesn't find the new item
+        let b = store.push("b");
+        assert_eq!(b.index, a.index);
+        assert_eq!(store.get(a), None);
+    }
+
+    #[test]
+    fn collects_and_extends() {
+        let mut store: StableVec<i32> = (1 ..= 3).collect();
+        assert_eq!(store.iter().map(|(_, item)| *item).collect::<Vec<_>>(), [1, 2, 3]);
+
+        let first = store.keys().next().unwrap();
+        store.remove(first);
+        store.extend([4, 5]);
+        assert_eq!(store.len(), 4);
+    }
+
+    #[test]
+    fn push_all_returns_keys_in_order() {
+        let mut store = StableVec::new();
+        let a = store.push("a");
+        store.remove(a);
+        let keys = store.push_all(["b", "c"]);
+        assert_eq!(keys.iter().map(|key| store[*key]).collect::<Vec<_>>(), ["b", "c"]);
+    }
+
+    #[test]
+    fn get_is_none_for_removed_or_unknown_keys() {
+        let mut store = StableVec::new();
+        let a = store.push(1);
+        store.remove(a);
+        assert_eq!(store.get(a), None);
+        assert_eq!(store.get_mut(a), None);
+        assert_eq!(store.get(Key { index: a.index + 1, generation: 0 }), None);
+    }
+}
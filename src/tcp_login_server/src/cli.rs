@@ -0,0 +1,102 @@
+use auth_server::{Client, DEFAULT_ADDRESS, framing::Format, protocol::*, udp};
+
+/// Make a single request, over a fresh TCP connection or as a UDP datagram.
+async fn call_once(format: Format, use_udp: bool, request: &Request) -> anyhow::Result<Response> {
+    if use_udp {
+        udp::call(DEFAULT_ADDRESS, format, request).await
+    } else {
+        Client::connect(DEFAULT_ADDRESS, format).await?.call(request).await
+    }
+}
+
+fn read_credentials() -> anyhow::Result<(String, String)> {
+    println!("Enter your username:");
+    let mut username = String::new();
+    let mut password = String::new();
+    let stdin = std::io::stdin();
+    stdin.read_line(&mut username)?;
+    println!("Enter your password:");
+    stdin.read_line(&mut password)?;
+    Ok((username, password))
+}
+
+pub async fn rpc_client(format: Format, use_udp: bool) -> anyhow::Result<()> {
+    println!("Welcome to the (Not Very) Secure Server");
+    let (username, password) = read_credentials()?;
+
+    let login_attempt = Request::Login {
+        username: username.clone(), password
+    };
+
+    let response = call_once(format, use_udp, &login_attempt).await?;
+
+    match response {
+        Response::Login { action: None, .. } => {
+            println!("{} is not a known user.", username.trim());
+            println!("This is where we handle new users.");
+        }
+        Response::Login { action: Some(login_action), token } => {
+            login_action.do_login(
+                |user| println!("Welcome {user:?}"), 
+                |reason| {
+                    println!("Access denied");
+                    println!("{reason:?}");
+                }
+            );
+            if let Some(token) = token {
+                println!("Your session token is {token}");
+            }
+        }
+        _ => println!("Unexpected response from server"),
+    }
+
+    Ok(())
+}
+
+pub async fn healthcheck(format: Format, use_udp: bool) -> anyhow::Result<()> {
+    match call_once(format, use_udp, &Request::Ping).await? {
+        Response::Pong => {
+            println!("OK");
+            Ok(())
+        }
+        _ => Err(anyhow::Error::msg("Server did not answer the ping")),
+    }
+}
+
+/// Log in as an admin and print user database changes and login attempts as the
+/// server pushes them.
+pub async fn subscribe(format: Format) -> anyhow::Result<()> {
+    let (username, password) = read_credentials()?;
+    let mut connection = Client::connect(DEFAULT_ADDRESS, format).await?;
+    let token = match connection.call(&Request::Login { username, password }).await? {
+        Response::Login { token: Some(token), .. } => token,
+        _ => return Err(anyhow::Error::msg("Login failed")),
+    };
+    match connection.call(&Request::Subscribe { token }).await? {
+        Response::Subscribed => println!("Watching for user changes and logins. Press Ctrl-C to stop."),
+        _ => return Err(anyhow::Error::msg("Only admins may subscribe")),
+    }
+
+    loop {
+        if let Response::Event(event) = connection.receive().await? {
+            println!("{event:?}");
+        }
+    }
+}
+
+/// Print a snapshot of the server's counters.
+pub async fn stats(format: Format, use_udp: bool) -> anyhow::Result<()> {
+    match call_once(format, use_udp, &Request::Stats).await? {
+        Response::Stats(stats) => {
+            println!("{:<24}{}", "Uptime (seconds)", stats.uptime_secs);
+            println!("{:<24}{}", "Active connections", stats.active_connections);
+            println!("{:<24}{}", "Total connections", stats.total_connections);
+            println!("{:<24}{}", "Logins accepted", stats.logins_accepted);
+            println!("{:<24}{}", "Logins denied", stats.logins_denied);
+            println!("{:<24}{}", "Logins failed", stats.logins_failed);
+            println!("{:<24}{}", "Logins throttled", stats.logins_throttled);
+            Ok(())
+        }
+        _ => Err(anyhow::Error::msg("Server did not send its stats")),
+    }
+}
@@ -0,0 +1,64 @@
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::{io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader}, net::{TcpListener, TcpStream}};
+use crate::{Job, Outcome, generate};
+
+// The leader and its workers exchange single lines of JSON: the leader sends
+// a `Job`, and the worker runs it and answers with its `Outcome`. Everyone
+// starts as soon as the job arrives, so the runs overlap to within a network
+// round trip.
+
+async fn send<T: Serialize>(stream: &mut (impl AsyncWrite + Unpin), message: &T) -> anyhow::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    stream.write_all(&line).await?;
+    Ok(())
+}
+
+async fn receive<T: DeserializeOwned>(stream: &mut (impl AsyncBufRead + Unpin)) -> anyhow::Result<T> {
+    let mut line = String::new();
+    if stream.read_line(&mut line).await? == 0 {
+        return Err(anyhow::Error::msg("connection closed"));
+    }
+    Ok(serde_json::from_str(&line)?)
+}
+
+/// Hand the job to every worker, run it here as well, and merge what everyone measured.
+pub async fn lead(job: &Job, workers: &[String]) -> anyhow::Result<Outcome> {
+    let mut remote = Vec::new();
+    for address in workers {
+        let mut stream = TcpStream::connect(address)
+            .await
+            .map_err(|e| anyhow::Error::msg(format!("Unable to reach worker {address}: {e}")))?;
+        send(&mut stream, job).await?;
+        remote.push(tokio::spawn(async move { receive::<Outcome>(&mut BufReader::new(stream)).await }));
+    }
+
+    let mut outcome = generate(job).await?;
+    for (address, handle) in workers.iter().zip(remote) {
+        let theirs = handle
+            .await?
+            .map_err(|e| anyhow::Error::msg(format!("Worker {address} failed: {e}")))?;
+        outcome.merge(theirs);
+    }
+    Ok(outcome)
+}
+
+/// Run jobs for whichever leader connects, one at a time, until killed.
+pub async fn work(address: &str) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(address).await?;
+    println!("Waiting for a leader on {}", listener.local_addr()?);
+    loop {
+        let (stream, leader) = listener.accept().await?;
+        if let Err(e) = run_job(stream).await {
+            println!("Job from {leader} failed: {e}");
+        }
+    }
+}
+
+async fn run_job(stream: TcpStream) -> anyhow::Result<()> {
+    let mut stream = BufReader::new(stream);
+    let job: Job = receive(&mut stream).await?;
+    println!("Running {} connections against {}", job.connections, job.targets.join(", "));
+    let outcome = generate(&job).await?;
+    send(stream.get_mut(), &outcome).await
+}
@@ -0,0 +1,124 @@
+use std::{path::Path, time::{SystemTime, UNIX_EPOCH}};
+use serde::{Serialize, Deserialize};
+use auth_server::framing::Format;
+use crate::{Args, Mode, bytes_per_login, report::Summary};
+
+/// One benchmark run: what was asked for, and what came back.
+#[derive(Serialize, Deserialize)]
+pub struct RunRecord {
+    pub timestamp: u64,
+    pub target: String,
+    pub mode: String,
+    pub format: String,
+    pub bytes_per_login: usize,
+    pub processes: usize,
+    pub connections: usize,
+    pub requests_per_connection: usize,
+    pub duration_secs: Option<u64>,
+    pub warmup_secs: u64,
+    pub rate: Option<u64>,
+    pub requests: usize,
+    pub errors: usize,
+    pub connect_errors: usize,
+    pub timeouts: usize,
+    pub decode_errors: usize,
+    #[serde(default)]
+    pub refused: usize,
+    pub other_errors: usize,
+    pub elapsed_secs: f64,
+    pub requests_per_sec: f64,
+    pub mean_us: u128,
+    pub p50_us: u128,
+    pub p90_us: u128,
+    pub p99_us: u128,
+    pub max_us: u128,
+}
+
+const CSV_HEADER: &str = "timestamp,target,mode,format,bytes_per_login,processes,connections,requests_per_connection,duration_secs,warmup_secs,rate,requests,errors,connect_errors,timeouts,decode_errors,refused,other_errors,elapsed_secs,requests_per_sec,mean_us,p50_us,p90_us,p99_us,max_us";
+
+impl RunRecord {
+    pub fn new(args: &Args, mode: Mode, format: Format, connections: usize, summary: &Summary) -> anyhow::Result<Self> {
+        Ok(Self {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            // Semicolons, so several targets stay in one CSV column
+            target: args.target.join(";"),
+            mode: mode.name().to_string(),
+            format: format.to_string(),
+            bytes_per_login: bytes_per_login(format)?,
+            processes: 1 + args.workers.len(),
+            connections,
+            requests_per_connection: args.requests_per_connection,
+            duration_secs: args.duration,
+            warmup_secs: args.warmup,
+            rate: args.rate,
+            requests: summary.requests,
+            errors: summary.errors.total(),
+            connect_errors: summary.errors.connect,
+            timeouts: summary.errors.timeout,
+            decode_errors: summary.errors.decode,
+            refused: summary.errors.refused,
+            other_errors: summary.errors.other,
+            elapsed_secs: summary.elapsed.as_secs_f64(),
+            requests_per_sec: summary.throughput(),
+            mean_us: summary.mean.as_micros(),
+            p50_us: summary.p50.as_micros(),
+            p90_us: summary.p90.as_micros(),
+            p99_us: summary.p99.as_micros(),
+            max_us: summary.max.as_micros(),
+        })
+    }
+
+    fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{:.3},{:.1},{},{},{},{},{}",
+            self.timestamp,
+            self.target,
+            self.mode,
+            self.format,
+            self.bytes_per_login,
+            self.processes,
+            self.connections,
+            self.requests_per_connection,
+            self.duration_secs.map(|d| d.to_string()).unwrap_or_default(),
+            self.warmup_secs,
+            self.rate.map(|r| r.to_string()).unwrap_or_default(),
+            self.requests,
+            self.errors,
+            self.connect_errors,
+            self.timeouts,
+            self.decode_errors,
+            self.refused,
+            self.other_errors,
+            self.elapsed_secs,
+            self.requests_per_sec,
+            self.mean_us,
+            self.p50_us,
+            self.p90_us,
+            self.p99_us,
+            self.max_us,
+        )
+    }
+}
+
+/// Add a run to the results file, creating it if need be. Files ending in
+/// `.json` hold an array of runs; anything else gets a CSV row.
+pub fn append(path: &Path, record: RunRecord) -> anyhow::Result<()> {
+    if path.extension().is_some_and(|ext| ext == "json") {
+        let mut records: Vec<RunRecord> = match std::fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        records.push(record);
+        std::fs::write(path, serde_json::to_string_pretty(&records)?)?;
+    } else {
+        use std::io::Write;
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        // A new file needs a header before its first row
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{CSV_HEADER}")?;
+        }
+        writeln!(file, "{}", record.csv_row())?;
+    }
+    Ok(())
+}
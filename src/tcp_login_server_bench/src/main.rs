@@ -1,143 +1,453 @@
-use std::collections::HashMap;
-use once_cell::sync::Lazy;
-use parking_lot::RwLock;
+use std::{path::PathBuf, time::{Duration, Instant}};
+use clap::{ArgGroup, Parser};
 use serde::{Serialize, Deserialize};
-use tokio::{net::{TcpListener, TcpStream}, spawn, io::{AsyncReadExt, AsyncWriteExt}};
+use tokio::time::timeout;
 use auth_json::*;
+use auth_server::{Client, DEFAULT_ADDRESS, Server, framing::Format, protocol::{Request, Response}};
 
-static USERS: Lazy<RwLock<HashMap<String, User>>> = Lazy::new(|| RwLock::new(get_users()));
+// Turning latency samples into something worth reading
+mod report;
+use report::{Errors, Failure};
 
-#[derive(Serialize, Deserialize)]
-struct LoginRequest {
-    username: String,
-    password: String,
-}
-
-async fn rpc_server() -> anyhow::Result<()> {
-    let listener = TcpListener::bind("127.0.0.1:8123").await?;
-
-    loop {
-        let (mut socket, address) = listener.accept().await?;
-        spawn(async move {
-            let mut buf = vec![0; 1024];
-            loop {
-                let n = socket
-                    .read(&mut buf)
-                    .await
-                    .expect("failed to read data from socket");
-                
-                if n == 0 {
-                    return;
-                }
+// Saving results, so runs can be compared over time
+mod export;
 
-                let mut response = None;
-                if let Ok(request) = bincode::deserialize::<LoginRequest>(&buf[0..n]) {
-                    response = login(&USERS.read(), &request.username, &request.password);
+// Requests on a fixed schedule, however slowly the server answers
+mod open_loop;
 
-                }
+// Sharing the load between several client processes
+mod distributed;
 
-                let bytes = bincode::serialize(&response).unwrap();
-                socket
-                    .write_all(&bytes)
-                    .await
-                    .expect("failed to write data to socket");
-            }
-        });
-    }
-    Ok(())
-}
+#[derive(Parser)]
+#[command(group(
+    ArgGroup::new("role")
+        .required(true)
+        .args(["server", "client", "worker"])
+))]
+struct Args {
+    /// Run a login server to benchmark against
+    #[arg(long)]
+    server: bool,
 
-async fn request_login(username: &str, password: &str) -> anyhow::Result<LoginAction> {
-    let login_attempt = LoginRequest {
-        username: username.to_string(), 
-        password: password.to_string(),
-    };
+    /// Run the load generator
+    #[arg(long)]
+    client: bool,
 
+    /// Wait for a leader (a --client run with --workers) on this address, and generate load for it
+    #[arg(long)]
+    worker: Option<String>,
 
-    let mut stream = TcpStream::connect("127.0.0.1:8123").await?;
-    let message = bincode::serialize(&login_attempt)?;
-    stream.write_all(&message).await?;
+    /// Addresses of the servers to load, comma separated. Connections are shared
+    /// between them in turn. With --server, the first is listened on.
+    #[arg(long, value_delimiter = ',', default_value = DEFAULT_ADDRESS)]
+    target: Vec<String>,
 
-    let mut buf = vec![0; 1024];
-    let n = stream.read(&mut buf).await?;
-    let response: Option<LoginAction> = bincode::deserialize(&buf[0..n])?;
+    /// Worker processes to share the load with, comma separated. Each runs
+    /// --connections (and --rate) of its own alongside this one.
+    #[arg(long, value_delimiter = ',')]
+    workers: Vec<String>,
 
+    /// How many connections make requests at the same time
+    #[arg(long, default_value_t = 100)]
+    connections: usize,
 
-    match response {
-        None => {
-            Err(anyhow::Error::msg("Unknown User"))
+    /// Logins each connection makes before hanging up
+    #[arg(long, default_value_t = 10)]
+    requests_per_connection: usize,
+
+    /// Keep every connection busy for this many seconds, instead of making a fixed number of requests
+    #[arg(long)]
+    duration: Option<u64>,
+
+    /// Seconds to make requests before measuring, so connection setup and cold caches don't skew the results
+    #[arg(long, default_value_t = 0)]
+    warmup: u64,
+
+    /// Append a summary of the run to this file (JSON if it ends in .json, CSV otherwise)
+    #[arg(long)]
+    out: Option<PathBuf>,
+
+    /// Run in stages, adding this many connections each time up to --connections, and
+    /// print throughput against concurrency. Each stage runs for --duration seconds.
+    #[arg(long)]
+    ramp: Option<usize>,
+
+    /// Milliseconds to wait for each login (including connecting) before counting it as timed out
+    #[arg(long, default_value_t = 5000)]
+    timeout: u64,
+
+    /// Keep each connection open for all its logins, or connect afresh for every
+    /// login. Give both (comma separated) to compare them.
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "persistent")]
+    mode: Vec<Mode>,
+
+    /// Wire format for requests: bincode, json or postcard. Give several (comma separated) to compare them.
+    #[arg(long, value_delimiter = ',', default_value = "bincode")]
+    format: Vec<Format>,
+
+    /// Start this many requests per second on a fixed schedule, however slowly the server
+    /// answers, instead of waiting for each answer. --connections caps how many are in flight.
+    #[arg(long, conflicts_with = "ramp")]
+    rate: Option<u64>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+enum Mode {
+    Persistent,
+    Oneshot,
+}
+
+impl Mode {
+    fn name(self) -> &'static str {
+        match self {
+            Mode::Persistent => "persistent",
+            Mode::Oneshot => "oneshot",
         }
-        Some(login_action) => {
-            Ok(login_action)
+    }
+}
+
+/// How long each ramp stage runs when no --duration is given.
+const RAMP_STAGE_SECS: u64 = 5;
+
+/// How long each connection keeps going.
+#[derive(Clone, Copy)]
+enum Load {
+    Requests(usize),
+    Until(Instant),
+}
+
+impl Load {
+    fn done(self, made: usize) -> bool {
+        match self {
+            Load::Requests(count) => made >= count,
+            Load::Until(deadline) => Instant::now() >= deadline,
         }
-        _ => Ok(LoginAction::Denied(DeniedReason::AccountLocked { reason: "Unknown User".to_string() }))
     }
 }
 
-struct LoginClient(TcpStream);
+fn login_result(response: Response) -> Result<LoginAction, Failure> {
+    match response {
+        Response::Login { action: Some(LoginAction::Accept(role)), .. } => Ok(LoginAction::Accept(role)),
+        // The server is up and answering, but won't let us in
+        Response::Login { action: Some(LoginAction::Denied(..)), .. } => Err(Failure::Refused),
+        // Unknown user, or something that isn't a login response at all
+        _ => Err(Failure::Other),
+    }
+}
+
+fn login_request(username: &str, password: &str) -> Request {
+    Request::Login {
+        username: username.to_string(),
+        password: password.to_string(),
+    }
+}
+
+/// Sort an error from a call into one of the causes we count.
+fn classify(e: anyhow::Error) -> Failure {
+    if e.is::<bincode::Error>() || e.is::<serde_json::Error>() || e.is::<postcard::Error>() {
+        Failure::Decode
+    } else {
+        Failure::Other
+    }
+}
+
+async fn connect(target: &str, format: Format) -> Result<Client, Failure> {
+    Client::connect(target, format).await.map_err(|_| Failure::Connect)
+}
+
+/// Log in over a brand new connection.
+async fn request_login(target: &str, format: Format, username: &str, password: &str) -> Result<LoginAction, Failure> {
+    let mut client = connect(target, format).await?;
+    login_result(client.call(&login_request(username, password)).await.map_err(classify)?)
+}
+
+/// A connection that is kept open for many logins.
+struct LoginClient(Client);
 
 impl LoginClient {
-    async fn new() -> Self {
-        let stream = TcpStream::connect("127.0.0.1:8123").await.unwrap();
-        Self(stream)
+    async fn new(target: &str, format: Format) -> Result<Self, Failure> {
+        Ok(Self(connect(target, format).await?))
+    }
+
+    async fn login(&mut self, username: &str, password: &str) -> Result<LoginAction, Failure> {
+        login_result(self.0.call(&login_request(username, password)).await.map_err(classify)?)
     }
+}
+
+/// Logs in over one long-lived connection, or a new connection for every login.
+/// Every attempt (including connecting) must finish within `timeout`.
+struct Connection {
+    kind: ConnectionKind,
+    format: Format,
+    timeout: Duration,
+}
 
-    async fn login(&mut self, username: &str, password: &str) -> anyhow::Result<LoginAction> {
-        let login_attempt = LoginRequest {
-            username: username.to_string(), 
-            password: password.to_string(),
+enum ConnectionKind {
+    Persistent(LoginClient),
+    Oneshot(String),
+}
+
+impl Connection {
+    async fn open(mode: Mode, format: Format, target: String, limit: Duration) -> Result<Self, Failure> {
+        let kind = match mode {
+            Mode::Persistent => {
+                let client = timeout(limit, LoginClient::new(&target, format)).await.map_err(|_| Failure::Timeout)??;
+                ConnectionKind::Persistent(client)
+            }
+            Mode::Oneshot => ConnectionKind::Oneshot(target),
         };
-        let message = bincode::serialize(&login_attempt)?;
-        self.0.write_all(&message).await?;
+        Ok(Self { kind, format, timeout: limit })
+    }
 
-        let mut buf = vec![0; 1024];
-        let n = self.0.read(&mut buf).await?;
-        let response: Option<LoginAction> = bincode::deserialize(&buf[0..n])?;
+    async fn login(&mut self, username: &str, password: &str) -> Result<LoginAction, Failure> {
+        let format = self.format;
+        let attempt = async {
+            match &mut self.kind {
+                ConnectionKind::Persistent(client) => client.login(username, password).await,
+                ConnectionKind::Oneshot(target) => request_login(target, format, username, password).await,
+            }
+        };
+        timeout(self.timeout, attempt).await.map_err(|_| Failure::Timeout)?
+    }
 
+    /// After a failure a persistent connection may be broken, or out of step
+    /// with the server, so it can't carry on. A oneshot connection can, and
+    /// so can any connection whose login was refused: the server still answered.
+    fn survives_failure(&self, failure: Failure) -> bool {
+        failure == Failure::Refused || matches!(self.kind, ConnectionKind::Oneshot(..))
+    }
+}
 
-        match response {
-            None => {
-                Err(anyhow::Error::msg("Unknown User"))
+/// What one connection managed before it finished or failed.
+struct ConnectionResult {
+    samples: Vec<Duration>,
+    errors: Errors,
+}
+
+/// Log in repeatedly over one connection, recording how long each login took
+/// once the warmup is over, and why any failed.
+async fn run_connection(mode: Mode, format: Format, target: String, limit: Duration, warmup_until: Instant, load: Load) -> ConnectionResult {
+    let mut result = ConnectionResult { samples: Vec::new(), errors: Errors::default() };
+    let mut client = match Connection::open(mode, format, target, limit).await {
+        Ok(client) => client,
+        Err(failure) => {
+            result.errors.record(failure);
+            return result;
+        }
+    };
+    // Warm up, throwing the timings away. Failures only count if they end the connection.
+    while Instant::now() < warmup_until {
+        if let Err(failure) = client.login("herbert", "password").await {
+            if !client.survives_failure(failure) {
+                result.errors.record(failure);
+                return result;
             }
-            Some(login_action) => {
-                Ok(login_action)
+        }
+    }
+    while !load.done(result.samples.len() + result.errors.total()) {
+        let now = Instant::now();
+        match client.login("herbert", "password").await {
+            Ok(..) => result.samples.push(now.elapsed()),
+            Err(failure) => {
+                result.errors.record(failure);
+                if !client.survives_failure(failure) {
+                    break;
+                }
             }
-            _ => Ok(LoginAction::Denied(DeniedReason::AccountLocked { reason: "Unknown User".to_string() }))
         }
     }
+    result
 }
 
-async fn rpc_client() -> anyhow::Result<()> {
+/// Run `job.connections` connections, each sending its next request as soon as the last is answered.
+async fn closed_loop(job: &Job, measure_from: Instant, load: Load) -> anyhow::Result<(Vec<Duration>, Errors)> {
     let mut handles = Vec::new();
-    for _ in 0..100_000 {
-        handles.push(tokio::spawn(async {
-            let mut client = LoginClient::new().await;
-            for _ in 0..10 {
-                let now = std::time::Instant::now();
-                let _result = client.login("herbert", "password").await.unwrap();
-                let duration = now.elapsed();
-                println!("Login session took: {} usecs", duration.as_micros());
-            }
-        }));
+    for i in 0..job.connections {
+        let target = job.targets[i % job.targets.len()].clone();
+        handles.push(tokio::spawn(run_connection(job.mode, job.format, target, job.timeout, measure_from, load)));
     }
+    let mut samples = Vec::new();
+    let mut errors = Errors::default();
     for handle in handles {
-        handle.await;
+        let result = handle.await?;
+        samples.extend(result.samples);
+        errors.add(&result.errors);
+    }
+    Ok((samples, errors))
+}
+
+/// One process's share of a load test. Workers are sent theirs by the leader.
+#[derive(Clone, Serialize, Deserialize)]
+struct Job {
+    mode: Mode,
+    format: Format,
+    targets: Vec<String>,
+    connections: usize,
+    requests_per_connection: usize,
+    duration: Option<Duration>,
+    warmup: Duration,
+    timeout: Duration,
+    rate: Option<u64>,
+}
+
+/// What a job measured.
+#[derive(Serialize, Deserialize)]
+struct Outcome {
+    samples: Vec<Duration>,
+    errors: Errors,
+    elapsed: Duration,
+}
+
+impl Outcome {
+    /// Fold another process's results into these.
+    fn merge(&mut self, other: Outcome) {
+        self.samples.extend(other.samples);
+        self.errors.add(&other.errors);
+        self.elapsed = self.elapsed.max(other.elapsed);
+    }
+}
+
+/// Generate the load a job describes, from this process.
+async fn generate(job: &Job) -> anyhow::Result<Outcome> {
+    // Only the steady state after the warmup is measured
+    let measure_from = Instant::now() + job.warmup;
+    let (samples, errors) = match job.rate {
+        Some(rate) => {
+            let load = match job.duration {
+                Some(duration) => Load::Until(measure_from + duration),
+                None => Load::Requests(job.requests_per_connection * job.connections),
+            };
+            open_loop::run(job, rate, measure_from, load).await?
+        }
+        None => {
+            let load = match job.duration {
+                Some(duration) => Load::Until(measure_from + duration),
+                None => Load::Requests(job.requests_per_connection),
+            };
+            closed_loop(job, measure_from, load).await?
+        }
+    };
+    Ok(Outcome { samples, errors, elapsed: measure_from.elapsed() })
+}
+
+/// Run one load test with this many connections, sharing it with any workers.
+async fn run(args: &Args, mode: Mode, format: Format, connections: usize, duration: Option<Duration>) -> anyhow::Result<report::Summary> {
+    let job = Job {
+        mode,
+        format,
+        targets: args.target.clone(),
+        connections,
+        requests_per_connection: args.requests_per_connection,
+        duration,
+        warmup: Duration::from_secs(args.warmup),
+        timeout: Duration::from_millis(args.timeout),
+        rate: args.rate,
+    };
+    let outcome = if args.workers.is_empty() {
+        generate(&job).await?
+    } else {
+        distributed::lead(&job, &args.workers).await?
+    };
+
+    let summary = report::Summary::new(outcome.samples, outcome.errors, outcome.elapsed);
+    if let Some(path) = &args.out {
+        export::append(path, export::RunRecord::new(args, mode, format, connections, &summary)?)?;
+    }
+    Ok(summary)
+}
+
+/// The length prefix and format byte in front of every message.
+const FRAME_OVERHEAD: usize = 5;
+
+/// Bytes on the wire for one login in this format, counting both the request and the response.
+fn bytes_per_login(format: Format) -> anyhow::Result<usize> {
+    let request = login_request("herbert", "password");
+    let response = Response::Login {
+        action: Some(LoginAction::Accept(Role::Admin)),
+        // Every session token is a UUID of this length
+        token: Some("0".repeat(36)),
+    };
+    Ok(2 * FRAME_OVERHEAD + format.serialize(&request)?.len() + format.serialize(&response)?.len())
+}
+
+async fn rpc_client(args: &Args) -> anyhow::Result<()> {
+    let runs: Vec<(Mode, Format)> = args.mode
+        .iter()
+        .flat_map(|&mode| args.format.iter().map(move |&format| (mode, format)))
+        .collect();
+    let mut comparison = report::Comparison::new();
+    for &(mode, format) in &runs {
+        let bytes = bytes_per_login(format)?;
+        let label = format!("{}, {format}", mode.name());
+        if runs.len() > 1 {
+            println!("== {label} ==");
+        }
+        match args.ramp {
+            Some(step) => ramp(args, mode, format, step).await?,
+            None => {
+                let summary = run(args, mode, format, args.connections, args.duration.map(Duration::from_secs)).await?;
+                summary.print();
+                println!("{:<16}{}", "Bytes/login", bytes);
+                comparison.add(label, bytes, summary);
+            }
+        }
+    }
+    if runs.len() > 1 && args.ramp.is_none() {
+        comparison.print();
     }
+    Ok(())
+}
 
+/// Step the connection count up to --connections, to see where throughput stops growing.
+async fn ramp(args: &Args, mode: Mode, format: Format, step: usize) -> anyhow::Result<()> {
+    let stage = Duration::from_secs(args.duration.unwrap_or(RAMP_STAGE_SECS));
+    let mut table = report::RampTable::new();
+    for connections in ramp_stages(step, args.connections) {
+        table.add(connections, run(args, mode, format, connections, Some(stage)).await?);
+    }
+    table.print();
     Ok(())
 }
 
+/// `step`, `2 * step`, and so on, finishing with exactly `max`.
+fn ramp_stages(step: usize, max: usize) -> Vec<usize> {
+    let step = step.max(1);
+    let mut stages: Vec<usize> = (1..).map(|n| n * step).take_while(|&c| c < max).collect();
+    stages.push(max);
+    stages
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() != 2 {
-        println!("You must run with either --server or --client");
+    let args = Args::parse();
+    if args.server {
+        Server::builder().bind(&args.target[0]).serve().await?;
+    } else if let Some(address) = &args.worker {
+        distributed::work(address).await?;
     } else {
-        match args[1].as_str() {
-            "--server" => rpc_server().await?,
-            "--client" => rpc_client().await?,
-            _ => println!("You must run with either --server or --client"),
-        }
+        rpc_client(&args).await?;
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ramp_ends_at_the_maximum() {
+        assert_eq!(ramp_stages(10, 35), vec![10, 20, 30, 35]);
+        assert_eq!(ramp_stages(10, 30), vec![10, 20, 30]);
+        assert_eq!(ramp_stages(50, 30), vec![30]);
+    }
+
+    #[test]
+    fn only_accepted_logins_succeed() {
+        let accepted = Response::Login { action: Some(LoginAction::Accept(Role::User)), token: None };
+        assert!(matches!(login_result(accepted), Ok(LoginAction::Accept(Role::User))));
+        let denied = Response::Login { action: Some(LoginAction::Denied(DeniedReason::TooManyAttempts)), token: None };
+        assert_eq!(login_result(denied).unwrap_err(), Failure::Refused);
+        let unknown = Response::Login { action: None, token: None };
+        assert_eq!(login_result(unknown).unwrap_err(), Failure::Other);
+    }
+}
@@ -0,0 +1,85 @@
+use std::{sync::Arc, time::Duration};
+use tokio::{sync::{Mutex, mpsc}, time::{Instant, MissedTickBehavior, interval_at}};
+use auth_server::framing::Format;
+use crate::{Connection, Job, Load, Mode, report::Errors};
+
+/// Intended start times, waiting for a connection to pick them up.
+type Schedule = Arc<Mutex<mpsc::UnboundedReceiver<Instant>>>;
+
+/// Start requests at `rate` per second until `load` is done, whether or not the
+/// server keeps up, and time each one from when it *should* have started. A
+/// closed loop only sends once the last answer is in, so a stalled server
+/// quietly slows the client down and the stall never shows up in the latency
+/// ("coordinated omission").
+pub async fn run(job: &Job, rate: u64, measure_from: std::time::Instant, load: Load) -> anyhow::Result<(Vec<Duration>, Errors)> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let schedule: Schedule = Arc::new(Mutex::new(rx));
+    let measure_from = Instant::from_std(measure_from);
+    let mut workers = Vec::new();
+    for i in 0..job.connections {
+        let target = job.targets[i % job.targets.len()].clone();
+        workers.push(tokio::spawn(worker(job.mode, job.format, target, job.timeout, measure_from, schedule.clone())));
+    }
+
+    let period = Duration::from_secs_f64(1.0 / rate.max(1) as f64);
+    let mut ticks = interval_at(Instant::now(), period);
+    // Ticks we were too slow for fire straight away, keeping us on schedule
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Burst);
+    let mut sent = 0;
+    while !load.done(sent) {
+        let intended = ticks.tick().await;
+        if tx.send(intended).is_err() {
+            break;
+        }
+        // The request count only covers what's measured
+        if intended >= measure_from {
+            sent += 1;
+        }
+    }
+    // Workers finish once the schedule runs dry
+    drop(tx);
+
+    let mut samples = Vec::new();
+    let mut errors = Errors::default();
+    for worker in workers {
+        let (worker_samples, worker_errors) = worker.await?;
+        samples.extend(worker_samples);
+        errors.add(&worker_errors);
+    }
+    Ok((samples, errors))
+}
+
+/// Make each scheduled request as soon as we can. A persistent connection that
+/// fails is replaced for the next request, so no scheduled request goes unmade.
+async fn worker(mode: Mode, format: Format, target: String, limit: Duration, measure_from: Instant, schedule: Schedule) -> (Vec<Duration>, Errors) {
+    let mut samples = Vec::new();
+    let mut errors = Errors::default();
+    let mut client: Option<Connection> = None;
+    loop {
+        let Some(intended) = schedule.lock().await.recv().await else {
+            break;
+        };
+        let outcome = match client.as_mut() {
+            Some(client) => client.login("herbert", "password").await,
+            None => match Connection::open(mode, format, target.clone(), limit).await {
+                Ok(connection) => client.insert(connection).login("herbert", "password").await,
+                Err(failure) => Err(failure),
+            },
+        };
+        // Warmup requests are made, but not measured
+        let measured = intended >= measure_from;
+        match outcome {
+            Ok(..) if measured => samples.push(intended.elapsed()),
+            Ok(..) => {}
+            Err(failure) => {
+                if client.as_ref().is_some_and(|c| !c.survives_failure(failure)) {
+                    client = None;
+                }
+                if measured {
+                    errors.record(failure);
+                }
+            }
+        }
+    }
+    (samples, errors)
+}
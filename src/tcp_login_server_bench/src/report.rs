@@ -0,0 +1,218 @@
+use std::time::Duration;
+use serde::{Serialize, Deserialize};
+
+/// Why a request didn't produce a login result.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Failure {
+    /// Couldn't connect, or the hello exchange failed
+    Connect,
+    /// No answer within --timeout
+    Timeout,
+    /// The answer couldn't be deserialized
+    Decode,
+    /// The server answered, but denied the login (e.g. too many attempts)
+    Refused,
+    /// Anything else: a dropped connection, an unexpected response...
+    Other,
+}
+
+/// Failed requests, counted by cause.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Errors {
+    pub connect: usize,
+    pub timeout: usize,
+    pub decode: usize,
+    // Results recorded before refusals were counted don't have this
+    #[serde(default)]
+    pub refused: usize,
+    pub other: usize,
+}
+
+impl Errors {
+    pub fn record(&mut self, failure: Failure) {
+        match failure {
+            Failure::Connect => self.connect += 1,
+            Failure::Timeout => self.timeout += 1,
+            Failure::Decode => self.decode += 1,
+            Failure::Refused => self.refused += 1,
+            Failure::Other => self.other += 1,
+        }
+    }
+
+    pub fn add(&mut self, other: &Errors) {
+        self.connect += other.connect;
+        self.timeout += other.timeout;
+        self.decode += other.decode;
+        self.refused += other.refused;
+        self.other += other.other;
+    }
+
+    pub fn total(&self) -> usize {
+        self.connect + self.timeout + self.decode + self.refused + self.other
+    }
+}
+
+/// Latency figures for one run, computed from every sample it recorded.
+pub struct Summary {
+    pub requests: usize,
+    pub errors: Errors,
+    pub elapsed: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl Summary {
+    pub fn new(mut samples: Vec<Duration>, errors: Errors, elapsed: Duration) -> Self {
+        samples.sort_unstable();
+        let total: Duration = samples.iter().sum();
+        Self {
+            requests: samples.len(),
+            errors,
+            elapsed,
+            mean: total.checked_div(samples.len() as u32).unwrap_or_default(),
+            p50: percentile(&samples, 50.0),
+            p90: percentile(&samples, 90.0),
+            p99: percentile(&samples, 99.0),
+            max: samples.last().copied().unwrap_or_default(),
+        }
+    }
+
+    /// The percentage of attempted requests that failed.
+    pub fn error_rate(&self) -> f64 {
+        let attempts = self.requests + self.errors.total();
+        if attempts == 0 {
+            return 0.0;
+        }
+        self.errors.total() as f64 * 100.0 / attempts as f64
+    }
+
+    /// Successful requests per second, over the whole run.
+    pub fn throughput(&self) -> f64 {
+        self.requests as f64 / self.elapsed.as_secs_f64()
+    }
+
+    pub fn print(&self) {
+        println!("{:<16}{}", "Requests", self.requests);
+        println!("{:<16}{} ({:.2}%)", "Errors", self.errors.total(), self.error_rate());
+        if self.errors.total() > 0 {
+            println!("{:<16}{}", "  Connect", self.errors.connect);
+            println!("{:<16}{}", "  Timeout", self.errors.timeout);
+            println!("{:<16}{}", "  Decode", self.errors.decode);
+            println!("{:<16}{}", "  Refused", self.errors.refused);
+            println!("{:<16}{}", "  Other", self.errors.other);
+        }
+        println!("{:<16}{:.2}s", "Elapsed", self.elapsed.as_secs_f64());
+        println!("{:<16}{:.0} req/s", "Throughput", self.throughput());
+        println!("{:<16}{} usecs", "Mean", self.mean.as_micros());
+        println!("{:<16}{} usecs", "p50", self.p50.as_micros());
+        println!("{:<16}{} usecs", "p90", self.p90.as_micros());
+        println!("{:<16}{} usecs", "p99", self.p99.as_micros());
+        println!("{:<16}{} usecs", "Max", self.max.as_micros());
+    }
+}
+
+/// Throughput and latency at each step of a ramp, to find where the server saturates.
+pub struct RampTable {
+    rows: Vec<(usize, Summary)>,
+}
+
+impl RampTable {
+    pub fn new() -> Self {
+        Self { rows: Vec::new() }
+    }
+
+    pub fn add(&mut self, connections: usize, summary: Summary) {
+        println!("{connections} connections: {:.0} req/s", summary.throughput());
+        self.rows.push((connections, summary));
+    }
+
+    pub fn print(&self) {
+        println!("{:>12}{:>12}{:>12}{:>12}{:>10}", "Connections", "req/s", "p50 (us)", "p99 (us)", "Errors");
+        for (connections, summary) in &self.rows {
+            println!(
+                "{:>12}{:>12.0}{:>12}{:>12}{:>10}",
+                connections,
+                summary.throughput(),
+                summary.p50.as_micros(),
+                summary.p99.as_micros(),
+                summary.errors.total(),
+            );
+        }
+    }
+}
+
+/// Several runs of the same load side by side, e.g. in different wire formats.
+pub struct Comparison {
+    rows: Vec<(String, usize, Summary)>,
+}
+
+impl Comparison {
+    pub fn new() -> Self {
+        Self { rows: Vec::new() }
+    }
+
+    pub fn add(&mut self, label: String, bytes_per_login: usize, summary: Summary) {
+        self.rows.push((label, bytes_per_login, summary));
+    }
+
+    pub fn print(&self) {
+        println!("{:<24}{:>12}{:>12}{:>12}{:>12}", "Run", "Bytes/login", "req/s", "p50 (us)", "p99 (us)");
+        for (label, bytes, summary) in &self.rows {
+            println!(
+                "{:<24}{:>12}{:>12.0}{:>12}{:>12}",
+                label,
+                bytes,
+                summary.throughput(),
+                summary.p50.as_micros(),
+                summary.p99.as_micros(),
+            );
+        }
+    }
+}
+
+/// The sample below which `pct` percent of the (sorted) samples fall.
+fn percentile(sorted: &[Duration], pct: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (pct / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn percentiles_of_one_to_a_hundred() {
+        let samples = (1..=100).rev().map(Duration::from_micros).collect();
+        let summary = Summary::new(samples, Errors::default(), Duration::from_secs(1));
+        assert_eq!(summary.requests, 100);
+        assert_eq!(summary.p50, Duration::from_micros(50));
+        assert_eq!(summary.p99, Duration::from_micros(99));
+        assert_eq!(summary.max, Duration::from_micros(100));
+    }
+
+    #[test]
+    fn no_samples() {
+        let summary = Summary::new(Vec::new(), Errors::default(), Duration::from_secs(1));
+        assert_eq!(summary.mean, Duration::ZERO);
+        assert_eq!(summary.p99, Duration::ZERO);
+        assert_eq!(summary.error_rate(), 0.0);
+    }
+
+    #[test]
+    fn error_rate_counts_every_cause() {
+        let mut errors = Errors::default();
+        errors.record(Failure::Timeout);
+        errors.record(Failure::Connect);
+        errors.record(Failure::Refused);
+        let samples = vec![Duration::from_micros(1); 7];
+        let summary = Summary::new(samples, errors, Duration::from_secs(1));
+        assert_eq!(summary.errors.refused, 1);
+        assert_eq!(summary.error_rate(), 30.0);
+    }
+}
@@ -1,23 +1,153 @@
-use std::{sync::mpsc, thread};
+use std::{sync::{atomic::{AtomicBool, Ordering}, mpsc, Arc, Mutex}, thread, time::Duration};
 
-fn main() {
-    let (tx, rx) = mpsc::channel::<i32>();
-
-    let handle = thread::spawn(move || {
-        loop {
-            let n = rx.recv().unwrap();
-            match n {
-                1 => println!("Hi from worker thread"),
-                _ => break,
+const WORKERS: usize = 4;
+/// Workers hold on to this many results before sending them back
+const BATCH: usize = 4;
+
+enum Command {
+    Work(u64),
+    /// Work on this now, and send the answer straight back on the sender that came with it
+    Compute(u64, mpsc::Sender<u64>),
+    /// Send back any results you're holding, even if the batch isn't full
+    Flush,
+    Quit,
+}
+
+/// Results from one worker, as (input, output) pairs.
+struct Batch {
+    worker: usize,
+    results: Vec<(u64, u64)>,
+}
+
+/// Set once `work` has failed, so it only fails once
+static UNLUCKY: AtomicBool = AtomicBool::new(false);
+
+fn work(n: u64) -> u64 {
+    // Pretend this is hard - and sometimes goes wrong
+    thread::sleep(Duration::from_millis(10));
+    if n == 13 && !UNLUCKY.swap(true, Ordering::Relaxed) {
+        panic!("13 is unlucky");
+    }
+    n * n
+}
+
+/// Take commands off the shared queue until told to quit. Whichever worker is
+/// free takes the next command, so each `Flush` or `Quit` reaches just one worker.
+///
+/// `unreported` holds every input this worker has taken on but not yet sent a
+/// result for. If the worker dies, that's the work that would be lost.
+fn worker(id: usize, unreported: Arc<Mutex<Vec<u64>>>, commands: Arc<Mutex<mpsc::Receiver<Command>>>, results: mpsc::Sender<Batch>) {
+    let mut held = Vec::new();
+    let send = |held: &mut Vec<(u64, u64)>| {
+        if !held.is_empty() {
+            results.send(Batch { worker: id, results: std::mem::take(held) }).unwrap();
+            unreported.lock().unwrap().clear();
+        }
+    };
+
+    // Finish whatever the worker before us didn't
+    let leftover = unreported.lock().unwrap().clone();
+    for n in leftover {
+        held.push((n, work(n)));
+    }
+
+    loop {
+        // The lock is only held while waiting for a command, not while working on it
+        let command = commands.lock().unwrap().recv().unwrap();
+        match command {
+            Command::Work(n) => {
+                unreported.lock().unwrap().push(n);
+                held.push((n, work(n)));
+                if held.len() >= BATCH {
+                    send(&mut held);
+                }
+            }
+            Command::Compute(n, reply) => {
+                // Whoever asked may have given up waiting; that's fine
+                let _ = reply.send(work(n));
+            }
+            Command::Flush => send(&mut held),
+            Command::Quit => {
+                send(&mut held);
+                break;
             }
         }
-        println!("Thread closing cleanly");
-    });
+    }
+    println!("Worker {id} closing cleanly");
+}
 
-    for _ in 0..10 {
-        tx.send(1).unwrap();
+/// A running worker, and the work it has yet to report.
+struct Worker {
+    id: usize,
+    handle: thread::JoinHandle<()>,
+    unreported: Arc<Mutex<Vec<u64>>>,
+}
+
+impl Worker {
+    /// Start a worker that begins with `leftover`, before taking anything from the queue.
+    fn start(id: usize, leftover: Vec<u64>, commands: &Arc<Mutex<mpsc::Receiver<Command>>>, results: &mpsc::Sender<Batch>) -> Self {
+        let unreported = Arc::new(Mutex::new(leftover));
+        let handle = {
+            let (unreported, commands, results) = (unreported.clone(), commands.clone(), results.clone());
+            thread::spawn(move || worker(id, unreported, commands, results))
+        };
+        Self { id, handle, unreported }
+    }
+}
+
+/// Ask the pool to compute something. The answer comes back on a channel of
+/// its own, so it can't be mixed up with anyone else's.
+fn ask(commands: &mpsc::Sender<Command>, n: u64) -> mpsc::Receiver<u64> {
+    let (reply, answer) = mpsc::channel();
+    commands.send(Command::Compute(n, reply)).unwrap();
+    answer
+}
+
+fn main() {
+    let (tx, rx) = mpsc::channel::<Command>();
+    let (results_tx, results) = mpsc::channel::<Batch>();
+    let rx = Arc::new(Mutex::new(rx));
+
+    let mut workers: Vec<_> = (0..WORKERS)
+        .map(|id| Worker::start(id, Vec::new(), &rx, &results_tx))
+        .collect();
+
+    for n in 1..=21 {
+        tx.send(Command::Work(n)).unwrap();
     }
-    tx.send(0).unwrap();
 
-    handle.join().unwrap();
+    // Several questions can be in flight at once; each answer still finds its way home
+    let questions: Vec<_> = [100, 200, 300].into_iter().map(|n| (n, ask(&tx, n))).collect();
+    for (n, answer) in questions {
+        println!("{n} squared is {}", answer.recv().unwrap());
+    }
+
+    tx.send(Command::Flush).unwrap();
+    for _ in 0..WORKERS {
+        tx.send(Command::Quit).unwrap();
+    }
+
+    // Keep the pool at full strength until every worker has quit. A worker
+    // that panicked never took its `Quit`, so its replacement will.
+    while !workers.is_empty() {
+        let (finished, running): (Vec<_>, Vec<_>) = workers.into_iter().partition(|worker| worker.handle.is_finished());
+        workers = running;
+        for worker in finished {
+            if worker.handle.join().is_err() {
+                let leftover = std::mem::take(&mut *worker.unreported.lock().unwrap());
+                println!("Worker {} panicked; restarting it with {leftover:?}", worker.id);
+                workers.push(Worker::start(worker.id, leftover, &rx, &results_tx));
+            }
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    // Only the workers held senders besides this one, so `results` ends now
+    drop(results_tx);
+
+    let mut total = 0;
+    for batch in results {
+        println!("Worker {} sent {:?}", batch.worker, batch.results);
+        total += batch.results.len();
+    }
+    println!("{total} results");
 }
@@ -1,7 +1,59 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use clap::Parser;
 use serde::{Serialize, Deserialize};
-use tokio::{net::{TcpListener, TcpStream}, spawn, io::{AsyncReadExt, AsyncWriteExt}, sync::mpsc::{self, Receiver}, time::sleep};
+use tokio::{net::{TcpListener, TcpStream}, spawn, io::{AsyncReadExt, AsyncWriteExt}, sync::{broadcast, mpsc::{self, error::TrySendError}, oneshot, watch}, time::sleep};
+
+#[derive(Parser)]
+struct Args {
+    /// How many ticks can queue up for each client before sending has to wait
+    #[arg(long, default_value_t = 4)]
+    capacity: usize,
+
+    /// How many ticks to send
+    #[arg(long, default_value_t = 20)]
+    ticks: u32,
+
+    /// Milliseconds between ticks, to begin with
+    #[arg(long, default_value_t = 250)]
+    tick_ms: u64,
+
+    /// Milliseconds the slow client spends on each tick, to begin with
+    #[arg(long, default_value_t = 1000)]
+    slow_ms: u64,
+
+    /// Drop ticks for clients whose queue is full, instead of waiting for room
+    #[arg(long)]
+    drop: bool,
+}
+
+/// Settings that can be changed while the program runs, by typing
+/// `tick <ms>` or `slow <ms>`.
+#[derive(Debug, Clone, Copy)]
+struct Config {
+    tick: Duration,
+    slow: Duration,
+}
+
+/// Read config changes from stdin. This is a plain thread rather than a task:
+/// it spends its life blocked on stdin, and a blocked thread doesn't stop the
+/// program exiting.
+fn read_config(config: watch::Sender<Config>) {
+    for line in std::io::stdin().lines() {
+        let Ok(line) = line else { return };
+        let mut words = line.split_whitespace();
+        let (Some(setting), Some(Ok(ms))) = (words.next(), words.next().map(str::parse::<u64>)) else {
+            println!("Try `tick <ms>` or `slow <ms>`");
+            continue;
+        };
+        let value = Duration::from_millis(ms);
+        match setting {
+            "tick" => config.send_modify(|config| config.tick = value),
+            "slow" => config.send_modify(|config| config.slow = value),
+            _ => println!("Unknown setting {setting}"),
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize)]
 enum Request {
@@ -12,80 +64,281 @@ enum Request {
 enum Response {
     Error,
     Ack,
+    /// The server is paused, and isn't answering pings
+    Paused,
 }
 
-async fn rpc_server() -> anyhow::Result<()> {
-    let listener = TcpListener::bind("127.0.0.1:8123").await?;
+#[derive(Debug, Default, Clone, Copy)]
+struct Stats {
+    connections: u64,
+    pings: u64,
+    /// Pings that arrived while the server was paused
+    refused: u64,
+}
+
+/// Everything the server task can be asked to do. Each command carries the
+/// sender its reply goes back on.
+enum Command {
+    Stats(oneshot::Sender<Stats>),
+    Pause(oneshot::Sender<()>),
+    Resume(oneshot::Sender<()>),
+    /// Sent by the server's own connections, so only the server task touches its state
+    Answer(Request, oneshot::Sender<Response>),
+}
+
+/// A way to talk to the server task. Cheap to clone; every clone talks to the same server.
+#[derive(Clone)]
+struct ServerHandle {
+    commands: mpsc::Sender<Command>,
+}
+
+impl ServerHandle {
+    /// Send a command, and wait for the server to reply to it.
+    async fn ask<T>(&self, command: impl FnOnce(oneshot::Sender<T>) -> Command) -> anyhow::Result<T> {
+        let (tx, rx) = oneshot::channel();
+        self.commands.send(command(tx)).await.map_err(|_| anyhow::Error::msg("The server has stopped"))?;
+        Ok(rx.await?)
+    }
+
+    async fn stats(&self) -> anyhow::Result<Stats> {
+        self.ask(Command::Stats).await
+    }
+
+    async fn pause(&self) -> anyhow::Result<()> {
+        self.ask(Command::Pause).await
+    }
+
+    async fn resume(&self) -> anyhow::Result<()> {
+        self.ask(Command::Resume).await
+    }
+
+    async fn answer(&self, request: Request) -> anyhow::Result<Response> {
+        self.ask(|tx| Command::Answer(request, tx)).await
+    }
+}
+
+/// Accept connections and answer commands until told to shut down, then wait
+/// for the connections already open to finish. The server's state lives here,
+/// and nowhere else - so it needs no locks.
+async fn rpc_server(
+    listener: TcpListener,
+    handle: ServerHandle,
+    mut commands: mpsc::Receiver<Command>,
+    mut shutdown: broadcast::Receiver<()>,
+) -> anyhow::Result<()> {
+    let mut stats = Stats::default();
+    let mut paused = false;
+    let mut connections = Vec::new();
 
     loop {
-        let (mut socket, address) = listener.accept().await?;
-        spawn(async move {
-            let mut buf = vec![0; 1024];
-            loop {
-                let n = socket
-                    .read(&mut buf)
-                    .await
-                    .expect("failed to read data from socket");
-                
-                if n == 0 {
-                    return;
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (socket, address) = accepted?;
+                stats.connections += 1;
+                connections.push((address, spawn(handle_connection(socket, address, handle.clone()))));
+            }
+            Some(command) = commands.recv() => match command {
+                Command::Stats(reply) => {
+                    let _ = reply.send(stats);
                 }
-
-                let mut response = Response::Error;
-                let request = serde_json::from_slice(&buf[0..n]);
-                match request {
-                    Err(..) => return,
-                    Ok(request) => {
-                        match request {
-                            Request::Ping => response = Response::Ack,
-                        }
-                    }
+                Command::Pause(reply) => {
+                    paused = true;
+                    let _ = reply.send(());
+                }
+                Command::Resume(reply) => {
+                    paused = false;
+                    let _ = reply.send(());
                 }
+                Command::Answer(Request::Ping, reply) => {
+                    let response = if paused {
+                        stats.refused += 1;
+                        Response::Paused
+                    } else {
+                        stats.pings += 1;
+                        Response::Ack
+                    };
+                    let _ = reply.send(response);
+                }
+            },
+            _ = shutdown.recv() => break,
+        }
+    }
 
-                let bytes = serde_json::to_vec(&response).unwrap();
-                socket
-                    .write_all(&bytes)
-                    .await
-                    .expect("failed to write data to socket");
-            }
-        });
+    // Nobody is answering any more, so connections still waiting on an answer give up.
+    // The clients were told to stop too, so the rest close soon.
+    // One connection going wrong doesn't stop us waiting for the others.
+    drop(commands);
+    for (address, connection) in connections {
+        match connection.await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => println!("Connection from {address} failed: {e}"),
+            Err(e) => println!("Connection from {address} panicked: {e}"),
+        }
     }
+    println!("Server stopped: {stats:?}");
     Ok(())
 }
 
-async fn rpc_client(mut rx: tokio::sync::broadcast::Receiver<u32>) -> anyhow::Result<()> {
+/// Answer one client until it disconnects or the server stops. A client that
+/// resets its connection is an error, but only for this connection.
+async fn handle_connection(mut socket: TcpStream, address: std::net::SocketAddr, server: ServerHandle) -> anyhow::Result<()> {
+    let mut buf = vec![0; 1024];
+    loop {
+        let n = socket.read(&mut buf).await?;
+        if n == 0 {
+            println!("{address} disconnected");
+            return Ok(());
+        }
+
+        let request = serde_json::from_slice(&buf[0..n]);
+        let response = match request {
+            Err(..) => Response::Error,
+            Ok(request) => match server.answer(request).await {
+                Ok(response) => response,
+                Err(..) => return Ok(()),
+            },
+        };
+
+        let bytes = serde_json::to_vec(&response)?;
+        socket.write_all(&bytes).await?;
+    }
+}
+
+/// Ping the server for every tick. Client 0 is slow: it takes `config.slow`
+/// over each tick, so its queue fills up.
+async fn rpc_client(id: usize, mut rx: mpsc::Receiver<u32>, mut shutdown: broadcast::Receiver<()>, mut config: watch::Receiver<Config>) -> anyhow::Result<()> {
     let mut stream = TcpStream::connect("127.0.0.1:8123").await?;
 
     loop {
-        let _n = rx.recv().await?;
+        let tick = tokio::select! {
+            tick = rx.recv() => match tick {
+                Some(tick) => tick,
+                None => return Ok(()),
+            },
+            Ok(()) = config.changed() => {
+                println!("Client {id} sees new settings: {:?}", *config.borrow());
+                continue;
+            }
+            _ = shutdown.recv() => return Ok(()),
+        };
         let message = serde_json::to_vec(&Request::Ping)?;
         stream.write_all(&message).await?;
 
         let mut buf = vec![0; 1024];
         let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            // The server shut down while we were waiting for it
+            return Ok(());
+        }
         let response: Response = serde_json::from_slice(&buf[0..n])?;
         match response {
-            Response::Error => println!("Error!"),
-            Response::Ack => println!("Ack"),
-        }       
+            Response::Error => println!("Client {id}, tick {tick}: Error!"),
+            Response::Ack => println!("Client {id}, tick {tick}: Ack"),
+            Response::Paused => println!("Client {id}, tick {tick}: Paused"),
+        }
+        if id == 0 {
+            // Copy the setting out, rather than holding the borrow (and blocking updates) while we sleep
+            let slow = config.borrow().slow;
+            sleep(slow).await;
+        }
     }
+}
 
-    Ok(())
+/// How sending to one client went.
+#[derive(Debug, Default)]
+struct SendStats {
+    sent: u32,
+    /// Ticks thrown away because the client's queue was full (with `--drop`)
+    dropped: u32,
+    /// Time spent waiting for room in the client's queue
+    blocked: Duration,
+    /// The most ticks ever waiting in the client's queue
+    deepest_queue: usize,
+}
+
+/// Send a tick to every client, waiting for room in each queue (or dropping
+/// the tick if `drop` is set).
+async fn send_tick(tick: u32, clients: &[mpsc::Sender<u32>], stats: &mut [SendStats], drop: bool) {
+    for (tx, stats) in clients.iter().zip(stats.iter_mut()) {
+        let queued = tx.max_capacity() - tx.capacity();
+        stats.deepest_queue = stats.deepest_queue.max(queued);
+
+        if drop {
+            match tx.try_send(tick) {
+                Ok(()) => stats.sent += 1,
+                Err(TrySendError::Full(..)) => stats.dropped += 1,
+                Err(TrySendError::Closed(..)) => {}
+            }
+        } else {
+            let start = Instant::now();
+            if tx.send(tick).await.is_ok() {
+                stats.sent += 1;
+            }
+            stats.blocked += start.elapsed();
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Create a channel
-    let (tx, _rx) = tokio::sync::broadcast::channel::<u32>(32);
-    spawn(rpc_server());
-    for _ in 0..10 {
-        spawn(rpc_client(tx.subscribe()));
+    let args = Args::parse();
+    // Everyone listens on this one, and stops when anything arrives
+    let (shutdown, _) = broadcast::channel::<()>(1);
+    // Commands for the server task
+    let (commands, commands_rx) = mpsc::channel(32);
+    let server_handle = ServerHandle { commands };
+
+    // Bind before starting the clients, so they have something to connect to
+    let listener = TcpListener::bind("127.0.0.1:8123").await?;
+    let server = spawn(rpc_server(listener, server_handle.clone(), commands_rx, shutdown.subscribe()));
+
+    // Every client sees the latest settings, whenever they change
+    let (config_tx, config) = watch::channel(Config {
+        tick: Duration::from_millis(args.tick_ms),
+        slow: Duration::from_millis(args.slow_ms),
+    });
+    std::thread::spawn(move || read_config(config_tx));
+
+    // Each client gets its own bounded queue of ticks
+    let mut senders = Vec::new();
+    let mut clients = Vec::new();
+    for id in 0..10 {
+        let (tx, rx) = mpsc::channel::<u32>(args.capacity);
+        senders.push(tx);
+        clients.push(spawn(rpc_client(id, rx, shutdown.subscribe(), config.clone())));
+    }
+    let mut stats: Vec<SendStats> = senders.iter().map(|_| SendStats::default()).collect();
+
+    for tick in 1..=args.ticks {
+        let tick_interval = config.borrow().tick;
+        sleep(tick_interval).await;
+        let start = Instant::now();
+        send_tick(tick, &senders, &mut stats, args.drop).await;
+        println!("Tick {tick} sent in {}ms", start.elapsed().as_millis());
+
+        // Take the server offline for a few ticks, to show it answering commands
+        if tick == args.ticks / 3 {
+            server_handle.pause().await?;
+            println!("Paused the server: {:?}", server_handle.stats().await?);
+        } else if tick == args.ticks * 2 / 3 {
+            server_handle.resume().await?;
+            println!("Resumed the server: {:?}", server_handle.stats().await?);
+        }
+    }
+
+    // One slow client holds up every send behind it - unless we drop its ticks
+    println!("Client  Sent  Dropped  Blocked (ms)  Deepest queue");
+    for (id, stats) in stats.iter().enumerate() {
+        println!("{id:>6}  {:>4}  {:>7}  {:>12}  {:>13}", stats.sent, stats.dropped, stats.blocked.as_millis(), stats.deepest_queue);
     }
 
-    for _ in 0..10 {
-        sleep(Duration::from_secs(1)).await;
-        let _ = tx.send(1);
+    // Stop everything, and wait until it has
+    println!("Shutting down");
+    let _ = shutdown.send(());
+    for client in clients {
+        client.await??;
     }
+    server.await??;
 
     Ok(())
 }
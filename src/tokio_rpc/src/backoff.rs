@@ -0,0 +1,63 @@
+use std::{collections::hash_map::RandomState, hash::{BuildHasher, Hasher}, time::Duration};
+
+/// How long to wait between attempts to reach the server, and when to give up.
+#[derive(Clone, Copy)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max_delay: Duration,
+    pub max_retries: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            max_retries: 8,
+        }
+    }
+}
+
+impl Backoff {
+    /// The longest wait before retry number `retry` (counting from 0). It
+    /// doubles each time, up to `max_delay`.
+    fn ceiling(&self, retry: u32) -> Duration {
+        self.initial
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay)
+    }
+
+    /// A random wait up to the ceiling, so clients that lost the server at the
+    /// same moment don't all come back at the same moment too.
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.ceiling(retry).mul_f64(random_fraction())
+    }
+}
+
+/// A number in `0.0..=1.0`. The standard library seeds every `RandomState`
+/// randomly, which is plenty for jitter.
+fn random_fraction() -> f64 {
+    RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ceiling_doubles_then_caps() {
+        let backoff = Backoff::default();
+        assert_eq!(backoff.ceiling(0), Duration::from_millis(100));
+        assert_eq!(backoff.ceiling(3), Duration::from_millis(800));
+        assert_eq!(backoff.ceiling(10), Duration::from_secs(5));
+        assert_eq!(backoff.ceiling(100), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn delay_never_exceeds_ceiling() {
+        let backoff = Backoff::default();
+        for retry in 0..20 {
+            assert!(backoff.delay(retry) <= backoff.ceiling(retry));
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::{collections::HashMap, sync::{Arc, Mutex, atomic::{AtomicBool, AtomicU64, Ordering}}, time::Instant};
+use futures::{SinkExt, StreamExt};
+use tokio::{net::{TcpStream, tcp::OwnedWriteHalf}, spawn, sync::{self, mpsc, oneshot}, time::sleep};
+use tokio_util::codec::{FramedRead, FramedWrite};
+use tracing::{info, instrument, warn, Span};
+use crate::{backoff::Backoff, protocol::*};
+
+/// Someone waiting for responses: either a single one, or a stream of them.
+enum Waiting {
+    Call(oneshot::Sender<Response>),
+    Stream(mpsc::UnboundedSender<Response>),
+}
+
+type Pending = Arc<Mutex<HashMap<u64, Waiting>>>;
+
+/// A connection that can have many requests in flight at once. A background
+/// task reads responses and hands each one to whoever sent the matching request.
+struct Connection {
+    writer: sync::Mutex<FramedWrite<OwnedWriteHalf, Json<Envelope<Request>>>>,
+    pending: Pending,
+    next_id: AtomicU64,
+    closed: Arc<AtomicBool>,
+}
+
+impl Connection {
+    async fn connect(address: &str) -> anyhow::Result<Self> {
+        let (reader, writer) = TcpStream::connect(address).await?.into_split();
+        let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+        let closed = Arc::new(AtomicBool::new(false));
+
+        let waiting = pending.clone();
+        let reader_closed = closed.clone();
+        spawn(async move {
+            let mut responses = FramedRead::new(reader, Json::<Envelope<Response>>::new());
+            while let Some(Ok(response)) = responses.next().await {
+                let mut waiting = waiting.lock().unwrap();
+                match waiting.remove(&response.id) {
+                    Some(Waiting::Call(tx)) => {
+                        let _ = tx.send(response.message);
+                    }
+                    // Streams stay registered until they end
+                    Some(Waiting::Stream(tx)) if !matches!(response.message, Response::StreamEnd) => {
+                        let _ = tx.send(response.message);
+                        waiting.insert(response.id, Waiting::Stream(tx));
+                    }
+                    _ => {}
+                }
+            }
+            // Dropping the senders tells every caller still waiting that the connection is gone.
+            // Closing under the lock means send can't register anyone after the clear.
+            let mut waiting = waiting.lock().unwrap();
+            reader_closed.store(true, Ordering::Relaxed);
+            waiting.clear();
+        });
+
+        Ok(Self {
+            writer: sync::Mutex::new(FramedWrite::new(writer, Json::new())),
+            pending,
+            next_id: AtomicU64::new(1),
+            closed,
+        })
+    }
+
+    fn is_open(&self) -> bool {
+        !self.closed.load(Ordering::Relaxed)
+    }
+
+    /// Register whoever is waiting for the answer, then send the request.
+    /// Fails straight away if the connection has closed, since nobody would
+    /// ever answer.
+    async fn send(&self, request: Request, waiting: Waiting) -> anyhow::Result<u64> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        {
+            // The reader closes under this lock too, so it's either open and
+            // will clear us out when it closes, or already closed
+            let mut pending = self.pending.lock().unwrap();
+            if !self.is_open() {
+                return Err(anyhow::Error::msg("Connection closed"));
+            }
+            pending.insert(id, waiting);
+        }
+
+        let sent = self.writer.lock().await.send(Envelope { id, message: request }).await;
+        if let Err(e) = sent {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(e);
+        }
+        Ok(id)
+    }
+
+    /// The span gets the request's ID once it has one.
+    #[instrument(name = "call", skip(self), fields(id))]
+    async fn call(&self, request: Request) -> anyhow::Result<Response> {
+        let start = Instant::now();
+        let (tx, rx) = oneshot::channel();
+        let id = self.send(request, Waiting::Call(tx)).await?;
+        Span::current().record("id", id);
+
+        let response = rx.await.map_err(|_| anyhow::Error::msg("Connection closed before the response arrived"))?;
+        info!(elapsed_us = start.elapsed().as_micros() as u64, ?response, "response received");
+        Ok(response)
+    }
+}
+
+/// A client that connects when it first needs to, and reconnects (backing off
+/// between attempts) whenever the connection drops. Calls that were in flight
+/// when it dropped are sent again on the new connection.
+pub struct RpcClient {
+    address: String,
+    backoff: Backoff,
+    current: sync::Mutex<Option<Arc<Connection>>>,
+}
+
+impl RpcClient {
+    pub fn new(address: &str, backoff: Backoff) -> Self {
+        Self { address: address.to_string(), backoff, current: sync::Mutex::new(None) }
+    }
+
+    /// The live connection, making a new one if there isn't one.
+    async fn connection(&self) -> anyhow::Result<Arc<Connection>> {
+        let mut current = self.current.lock().await;
+        if let Some(connection) = current.as_ref().filter(|c| c.is_open()) {
+            return Ok(connection.clone());
+        }
+
+        let mut retry = 0;
+        let connection = loop {
+            match Connection::connect(&self.address).await {
+                Ok(connection) => break Arc::new(connection),
+                Err(e) if retry >= self.backoff.max_retries => {
+                    return Err(anyhow::Error::msg(format!("Unable to reach {} after {retry} retries: {e}", self.address)));
+                }
+                Err(e) => {
+                    let delay = self.backoff.delay(retry);
+                    warn!(address = %self.address, error = %e, delay_ms = delay.as_millis() as u64, "unable to reach the server, retrying");
+                    sleep(delay).await;
+                    retry += 1;
+                }
+            }
+        };
+        *current = Some(connection.clone());
+        Ok(connection)
+    }
+
+    /// Send a request and wait for its response. Other calls may be made while
+    /// this one waits. Requests are sent again if the connection drops, so they
+    /// should be safe to repeat.
+    pub async fn call(&self, request: Request) -> anyhow::Result<Response> {
+        let mut retry = 0;
+        loop {
+            match self.connection().await?.call(request.clone()).await {
+                Ok(response) => return Ok(response),
+                Err(e) if retry >= self.backoff.max_retries => return Err(e),
+                Err(e) => {
+                    warn!(error = %e, "sending the request again");
+                    retry += 1;
+                }
+            }
+        }
+    }
+
+    /// Start a stream of ticks from the server. Streams aren't resumed after a
+    /// reconnect; they simply end.
+    pub async fn subscribe(&self) -> anyhow::Result<Subscription> {
+        let connection = self.connection().await?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let id = connection.send(Request::Subscribe, Waiting::Stream(tx)).await?;
+        Ok(Subscription { id, rx, connection })
+    }
+}
+
+/// Messages from a server stream.
+pub struct Subscription {
+    id: u64,
+    rx: mpsc::UnboundedReceiver<Response>,
+    connection: Arc<Connection>,
+}
+
+impl Subscription {
+    /// The next message, or `None` once the stream has ended.
+    pub async fn next(&mut self) -> Option<Response> {
+        self.rx.recv().await
+    }
+
+    /// Ask the server to end the stream. Anything already on its way still arrives.
+    pub async fn unsubscribe(&self) -> anyhow::Result<()> {
+        match self.connection.call(Request::Unsubscribe { id: self.id }).await? {
+            Response::Ack => Ok(()),
+            _ => Err(anyhow::Error::msg("The server doesn't know that stream")),
+        }
+    }
+}
+
+fn print_response(response: Response) {
+    match response {
+        Response::Error => println!("Error!"),
+        Response::Ack => println!("Ack"),
+        Response::Echo(text) => println!("Echo: {text}"),
+        Response::Time(millis) => println!("Server time: {millis} ms since the Unix epoch"),
+        Response::ShuttingDown => println!("The server is shutting down"),
+        Response::NotAuthorized => println!("Not authorized"),
+        Response::Tick(count) => println!("Tick {count}"),
+        Response::StreamEnd => println!("End of stream"),
+    }
+}
+
+/// Send several pings at once over the one connection.
+pub async fn ping(backoff: Backoff) -> anyhow::Result<()> {
+    let client = Arc::new(RpcClient::new("127.0.0.1:8123", backoff));
+
+    let mut handles = Vec::new();
+    for n in 0..10 {
+        let client = client.clone();
+        handles.push(spawn(async move { (n, client.call(Request::Ping).await) }));
+    }
+    for handle in handles {
+        let (n, response) = handle.await?;
+        print!("Ping {n}: ");
+        print_response(response?);
+    }
+
+    Ok(())
+}
+
+/// Make a single request and print the answer.
+pub async fn call_once(backoff: Backoff, request: Request) -> anyhow::Result<()> {
+    let client = RpcClient::new("127.0.0.1:8123", backoff);
+    print_response(client.call(request).await?);
+    Ok(())
+}
+
+/// Print ticks from the server, then cancel the stream.
+pub async fn watch(backoff: Backoff, ticks: u64) -> anyhow::Result<()> {
+    let client = RpcClient::new("127.0.0.1:8123", backoff);
+    let mut subscription = client.subscribe().await?;
+    let mut seen = 0;
+    while let Some(response) = subscription.next().await {
+        print_response(response);
+        seen += 1;
+        if seen == ticks {
+            subscription.unsubscribe().await?;
+        }
+    }
+    println!("End of stream");
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn calls_on_a_closed_connection_fail_fast() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let connection = Connection::connect(&address).await.unwrap();
+        // Hang up straight away, and wait for the reader to notice
+        drop(listener.accept().await.unwrap());
+        while connection.is_open() {
+            sleep(Duration::from_millis(1)).await;
+        }
+
+        let call = tokio::time::timeout(Duration::from_secs(1), connection.call(Request::Ping)).await;
+        assert!(call.expect("the call hung").is_err());
+        assert!(connection.pending.lock().unwrap().is_empty());
+    }
+}
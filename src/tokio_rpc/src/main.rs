@@ -1,83 +1,91 @@
-use serde::{Serialize, Deserialize};
-use tokio::{net::{TcpListener, TcpStream}, spawn, io::{AsyncReadExt, AsyncWriteExt}};
+use clap::{ArgGroup, Parser, ValueEnum};
+use protocol::Request;
 
-#[derive(Serialize, Deserialize)]
-enum Request {
-    Ping,
-}
+// Messages exchanged by the client and server, and how they're written to the socket
+mod protocol;
+
+// Answers requests, several at a time
+mod server;
+
+// Sends requests without waiting for earlier ones to finish
+mod client;
 
-#[derive(Serialize, Deserialize)]
-enum Response {
-    Error,
-    Ack,
+// Waiting a little longer each time the server can't be reached
+mod backoff;
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LogFormat {
+    /// One line of readable text per event
+    Text,
+    /// One JSON object per event, for feeding to a log collector
+    Json,
 }
 
-async fn rpc_server() -> anyhow::Result<()> {
-    let listener = TcpListener::bind("127.0.0.1:8123").await?;
+#[derive(Parser)]
+#[command(group(
+    ArgGroup::new("mode")
+        .required(true)
+        .args(["server", "client", "echo", "time", "shutdown", "subscribe"])
+))]
+struct Args {
+    /// Run the server
+    #[arg(long)]
+    server: bool,
 
-    loop {
-        let (mut socket, address) = listener.accept().await?;
-        spawn(async move {
-            let mut buf = vec![0; 1024];
-            loop {
-                let n = socket
-                    .read(&mut buf)
-                    .await
-                    .expect("failed to read data from socket");
-                
-                if n == 0 {
-                    return;
-                }
+    /// Token clients must send to shut the server down. Without one, shutdown is refused.
+    #[arg(long, requires = "server")]
+    admin_token: Option<String>,
 
-                let mut response = Response::Error;
-                let request = serde_json::from_slice(&buf[0..n]);
-                match request {
-                    Err(..) => return,
-                    Ok(request) => {
-                        match request {
-                            Request::Ping => response = Response::Ack,
-                        }
-                    }
-                }
+    /// Send a burst of pings
+    #[arg(long)]
+    client: bool,
 
-                let bytes = serde_json::to_vec(&response).unwrap();
-                socket
-                    .write_all(&bytes)
-                    .await
-                    .expect("failed to write data to socket");
-            }
-        });
-    }
-    Ok(())
-}
+    /// Ask the server to echo some text
+    #[arg(long)]
+    echo: Option<String>,
 
-async fn rpc_client() -> anyhow::Result<()> {
-    let mut stream = TcpStream::connect("127.0.0.1:8123").await?;
-    let message = serde_json::to_vec(&Request::Ping)?;
-    stream.write_all(&message).await?;
+    /// Ask the server for the time
+    #[arg(long)]
+    time: bool,
 
-    let mut buf = vec![0; 1024];
-    let n = stream.read(&mut buf).await?;
-    let response: Response = serde_json::from_slice(&buf[0..n])?;
-    match response {
-        Response::Error => println!("Error!"),
-        Response::Ack => println!("Ack"),
-    }
+    /// Shut the server down, using this admin token
+    #[arg(long)]
+    shutdown: Option<String>,
 
-    Ok(())
+    /// Watch this many of the server's ticks, then cancel
+    #[arg(long)]
+    subscribe: Option<u64>,
+
+    /// How many times the client tries again to reach the server before giving up
+    #[arg(long, default_value_t = 8)]
+    retries: u32,
+
+    /// How to write logs (to stderr)
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() != 2 {
-        println!("You must run with either --server or --client");
-    } else {
-        match args[1].as_str() {
-            "--server" => rpc_server().await?,
-            "--client" => rpc_client().await?,
-            _ => println!("You must run with either --server or --client"),
-        }
+    let args = Args::parse();
+    let logs = tracing_subscriber::fmt().with_writer(std::io::stderr);
+    match args.log_format {
+        LogFormat::Text => logs.init(),
+        LogFormat::Json => logs.json().init(),
+    }
+    let backoff = backoff::Backoff { max_retries: args.retries, ..Default::default() };
+    if args.server {
+        server::rpc_server(args.admin_token).await?;
+    } else if args.client {
+        client::ping(backoff).await?;
+    } else if let Some(text) = args.echo {
+        client::call_once(backoff, Request::Echo(text)).await?;
+    } else if args.time {
+        client::call_once(backoff, Request::ServerTime).await?;
+    } else if let Some(token) = args.shutdown {
+        client::call_once(backoff, Request::Shutdown { token }).await?;
+    } else if let Some(ticks) = args.subscribe {
+        client::watch(backoff, ticks).await?;
     }
     Ok(())
 }
@@ -0,0 +1,114 @@
+use std::marker::PhantomData;
+use bytes::BytesMut;
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use tokio_util::codec::{Decoder, Encoder, LengthDelimitedCodec};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Request {
+    Ping,
+    /// Sends the text straight back
+    Echo(String),
+    ServerTime,
+    /// Stops the server, if the token matches the one it was started with
+    Shutdown { token: String },
+    /// Start a stream of `Tick`s, one a second, all carrying this request's ID
+    Subscribe,
+    /// Stop the stream started by the `Subscribe` request with this ID
+    Unsubscribe { id: u64 },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum Response {
+    Error,
+    Ack,
+    Echo(String),
+    /// Milliseconds since the Unix epoch, by the server's clock
+    Time(u64),
+    ShuttingDown,
+    NotAuthorized,
+    Tick(u64),
+    /// The last message of a stream
+    StreamEnd,
+}
+
+/// Wraps every message with the ID of the request it belongs to. The client
+/// picks the IDs; the server copies the ID of each request onto its response,
+/// so responses can arrive in any order.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Envelope<T> {
+    pub id: u64,
+    pub message: T,
+}
+
+/// Turns a stream of bytes into messages of type `T`, and back again.
+///
+/// `LengthDelimitedCodec` does the framing: every message is sent as its length
+/// followed by that many bytes, and it keeps buffering until a whole frame has
+/// arrived. A bare `read` can return half a message, or two at once - the codec
+/// means we never have to think about that. This layer only converts each
+/// complete frame to and from JSON.
+pub struct Json<T> {
+    frames: LengthDelimitedCodec,
+    message: PhantomData<T>,
+}
+
+impl<T> Json<T> {
+    pub fn new() -> Self {
+        Self { frames: LengthDelimitedCodec::new(), message: PhantomData }
+    }
+}
+
+impl<T> Default for Json<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: DeserializeOwned> Decoder for Json<T> {
+    type Item = T;
+    type Error = anyhow::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> anyhow::Result<Option<T>> {
+        match self.frames.decode(src)? {
+            Some(frame) => Ok(Some(serde_json::from_slice(&frame)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<T: Serialize> Encoder<T> for Json<T> {
+    type Error = anyhow::Error;
+
+    fn encode(&mut self, message: T, dst: &mut BytesMut) -> anyhow::Result<()> {
+        let bytes = serde_json::to_vec(&message)?;
+        self.frames.encode(bytes.into(), dst)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn messages_survive_partial_reads() {
+        let mut codec = Json::<Envelope<Request>>::new();
+        let mut wire = BytesMut::new();
+        codec.encode(Envelope { id: 1, message: Request::Echo("hello".to_string()) }, &mut wire).unwrap();
+        codec.encode(Envelope { id: 2, message: Request::Ping }, &mut wire).unwrap();
+
+        // Feed the bytes in one at a time, the worst case a socket can give us
+        let mut received = BytesMut::new();
+        let mut decoded = Vec::new();
+        for byte in wire {
+            received.extend_from_slice(&[byte]);
+            if let Some(message) = codec.decode(&mut received).unwrap() {
+                decoded.push(message);
+            }
+        }
+
+        assert_eq!(decoded.len(), 2);
+        assert!(matches!(&decoded[0], Envelope { id: 1, message: Request::Echo(text) } if text == "hello"));
+        assert!(matches!(decoded[1], Envelope { id: 2, message: Request::Ping }));
+    }
+}
@@ -0,0 +1,135 @@
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+use futures::{SinkExt, StreamExt};
+use tokio::{net::{TcpListener, TcpStream}, spawn, sync::{Notify, mpsc, oneshot}, time::interval};
+use tokio_util::codec::{FramedRead, FramedWrite};
+use tracing::{info, info_span, warn, Instrument};
+use crate::protocol::*;
+
+/// What every connection needs to know about the server.
+struct Shared {
+    /// Clients must present this to shut the server down. Without one, nobody can.
+    admin_token: Option<String>,
+    shutdown: Notify,
+}
+
+async fn handle_request(shared: &Shared, request: Request) -> Response {
+    match request {
+        Request::Ping => Response::Ack,
+        Request::Echo(text) => Response::Echo(text),
+        Request::ServerTime => match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(now) => Response::Time(now.as_millis() as u64),
+            Err(..) => Response::Error,
+        },
+        Request::Shutdown { token } if shared.admin_token.as_ref() == Some(&token) => Response::ShuttingDown,
+        Request::Shutdown { .. } => Response::NotAuthorized,
+        // Streams belong to a connection, so handle_connection deals with these
+        Request::Subscribe | Request::Unsubscribe { .. } => Response::Error,
+    }
+}
+
+pub async fn rpc_server(admin_token: Option<String>) -> anyhow::Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:8123").await?;
+    let shared = Arc::new(Shared { admin_token, shutdown: Notify::new() });
+
+    loop {
+        let (socket, address) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = shared.shutdown.notified() => {
+                info!("shutting down");
+                return Ok(());
+            }
+        };
+        let shared = shared.clone();
+        spawn(async move {
+            if let Err(e) = handle_connection(shared, socket, address).await {
+                warn!(%address, error = %e, "connection failed");
+            }
+        });
+    }
+}
+
+/// Answer each request in its own task, so a slow request doesn't hold up the
+/// ones behind it. Responses are written as they become ready.
+async fn handle_connection(shared: Arc<Shared>, socket: TcpStream, address: SocketAddr) -> anyhow::Result<()> {
+    let (reader, writer) = socket.into_split();
+    let mut requests = FramedRead::new(reader, Json::<Envelope<Request>>::new());
+    let mut responses = FramedWrite::new(writer, Json::<Envelope<Response>>::new());
+
+    let (tx, mut rx) = mpsc::channel::<Envelope<Response>>(32);
+    let responder_shared = shared.clone();
+    let responder = spawn(async move {
+        while let Some(response) = rx.recv().await {
+            let shutting_down = matches!(response.message, Response::ShuttingDown);
+            responses.send(response).await?;
+            // Only stop once the client has been told we're going
+            if shutting_down {
+                responder_shared.shutdown.notify_one();
+            }
+        }
+        Ok::<(), anyhow::Error>(())
+    });
+
+    // Streams this client has open, and how to stop each one
+    let mut subscriptions: HashMap<u64, oneshot::Sender<()>> = HashMap::new();
+
+    while let Some(request) = requests.next().await {
+        let request = request?;
+        let span = info_span!("request", id = request.id, %address);
+        match request.message {
+            Request::Subscribe => {
+                span.in_scope(|| info!("stream started"));
+                let (cancel, cancelled) = oneshot::channel();
+                subscriptions.insert(request.id, cancel);
+                spawn(tick(request.id, tx.clone(), cancelled));
+                continue;
+            }
+            Request::Unsubscribe { id } => {
+                let message = match subscriptions.remove(&id) {
+                    Some(cancel) => {
+                        let _ = cancel.send(());
+                        Response::Ack
+                    }
+                    None => Response::Error,
+                };
+                span.in_scope(|| info!(stream = id, response = ?message, "stream cancelled"));
+                tx.send(Envelope { id: request.id, message }).await?;
+                continue;
+            }
+            _ => {}
+        }
+
+        let tx = tx.clone();
+        let shared = shared.clone();
+        spawn(async move {
+            let start = Instant::now();
+            let message = handle_request(&shared, request.message).await;
+            info!(elapsed_us = start.elapsed().as_micros() as u64, response = ?message, "answered");
+            let _ = tx.send(Envelope { id: request.id, message }).await;
+        }.instrument(span));
+    }
+
+    // The client hung up. Stop its streams, and let the responder finish whatever is still in flight.
+    drop(subscriptions);
+    drop(tx);
+    responder.await?
+}
+
+/// Send a `Tick` every second until cancelled, then end the stream.
+async fn tick(id: u64, tx: mpsc::Sender<Envelope<Response>>, mut cancelled: oneshot::Receiver<()>) {
+    let mut ticks = interval(Duration::from_secs(1));
+    let mut count = 0;
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => {
+                count += 1;
+                if tx.send(Envelope { id, message: Response::Tick(count) }).await.is_err() {
+                    return;
+                }
+            }
+            _ = &mut cancelled => {
+                let _ = tx.send(Envelope { id, message: Response::StreamEnd }).await;
+                return;
+            }
+        }
+    }
+}